@@ -30,6 +30,10 @@ error_chain! {
             description("an operation has failed because the client has too many sessions")
             display("An operation has failed because the client has too many sessions")
         }
+        SaslFailed(desc: Cow<'static, str>) {
+            description("SASL authentication failed")
+            display("SASL authentication failed: {}", desc)
+        }
         SessionIdFromWrongClient(session_id: SessionId, operation_name: Cow<'static, str>) {
             description("a client operation taking a `SessionId` was given a `SessionId` from the \
                          wrong client")
@@ -7,7 +7,6 @@ use self::session::TryIntoSession;
 pub use self::thick::ThickClient;
 pub use self::thin::ThinClient;
 use Message;
-use connection::Connection;
 use mio;
 use std::sync::mpsc;
 use uuid::Uuid;
@@ -28,10 +27,9 @@ where
 {
     fn handle(&self) -> ClientHandle<Msg>;
 
-    fn add_session<Conn, Sess>(&mut self, session: Sess) -> Result<SessionId>
+    fn add_session<Sess>(&mut self, session: Sess) -> Result<SessionId>
     where
-        Conn: Connection,
-        Sess: TryIntoSession<Conn>;
+        Sess: TryIntoSession;
 
     fn run<MsgHandler>(self, msg_handler: MsgHandler) -> Result<()>
     where
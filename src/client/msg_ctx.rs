@@ -1,6 +1,9 @@
 use super::ClientHandle;
 use super::SessionId;
+use super::session::Capabilities;
+use super::session::ServerFeatures;
 use Message;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct MessageContext<Msg>
@@ -9,6 +12,8 @@ where
 {
     pub(crate) client_handle: ClientHandle<Msg>,
     pub(crate) session_id: SessionId,
+    pub(crate) server_features: Arc<ServerFeatures>,
+    pub(crate) capabilities: Arc<Capabilities>,
 }
 
 impl<Msg> MessageContext<Msg>
@@ -22,4 +27,16 @@ where
     pub fn session_id(&self) -> SessionId {
         self.session_id
     }
+
+    /// Returns the features that the session's server had advertised in `RPL_ISUPPORT` replies as
+    /// of the receipt of the message.
+    pub fn server_features(&self) -> &ServerFeatures {
+        &self.server_features
+    }
+
+    /// Returns the IRCv3 capabilities that were enabled for the session as of the receipt of the
+    /// message.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
 }
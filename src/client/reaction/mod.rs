@@ -10,11 +10,23 @@ where
 
     /// React by sending an IRC message. No line-wrapping or other formatting will be performed on
     /// the message, except that the message-terminating sequence of a carriage return character
-    /// and a line feed character ("CR-LF") will be appended. If the message exceeds 512 octets in
-    /// length (including the terminating CR-LF sequence, but excluding any IRCv3 message tags), it
-    /// may be truncated to 512 octets.
+    /// and a line feed character ("CR-LF") will be appended. If the message exceeds the
+    /// connection's line-length limits (by default, 512 octets, including the terminating CR-LF
+    /// sequence but excluding any IRCv3 message tags), it will not be sent, and an error will be
+    /// logged; see `Privmsg` and `Notice` for a way to send text of any length.
     RawMsg(Msg),
 
+    /// React by sending the given text to the given target in one or more `PRIVMSG` messages.
+    /// The text is split as needed to fit within the length limit of the IRC protocol, preferably
+    /// at spaces and at line breaks, which cannot otherwise be sent; see
+    /// [`message::cmd::split_text`] for details.
+    ///
+    /// [`message::cmd::split_text`]: ../message/cmd/fn.split_text.html
+    Privmsg { target: String, text: String },
+
+    /// Like `Privmsg`, but sends `NOTICE` messages.
+    Notice { target: String, text: String },
+
     /// Return multiple reactions, which will be processed in the order given.
     Multi(Vec<Reaction<Msg>>),
 }
@@ -0,0 +1,372 @@
+//! IRCv3 capability negotiation.
+//!
+//! A client that wishes to enable IRCv3 capabilities sends `CAP LS 302` before registering, which
+//! suspends its registration until it sends `CAP END`. The server lists the capabilities it
+//! supports, possibly over several `CAP LS` replies and with values, as in `sasl=PLAIN,EXTERNAL`;
+//! the client requests those it wants with `CAP REQ`, and the server acknowledges (`ACK`) or
+//! rejects (`NAK`) each request as a whole. Afterwards, the server may announce that capabilities
+//! have become available (`NEW`) or unavailable (`DEL`).
+//!
+//! The `tls` capability is not requested like the others: rather, the client sends `STARTTLS`
+//! once the server has listed its capabilities, and, once the connection has been upgraded to TLS,
+//! lists them anew and requests the others.
+//!
+//! See the [capability negotiation specification] and the [`tls` extension] for details.
+//!
+//! [capability negotiation specification]: <https://ircv3.net/specs/core/capability-negotiation>
+//! [`tls` extension]: <https://ircv3.net/specs/deprecated/tls>
+
+use Message;
+use message;
+use message::Command;
+use message::MessageBuilder;
+use message::cmd;
+use message::numeric::RPL_WELCOME;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::btree_set;
+use string_cache::DefaultAtom as CachedString;
+
+mod tests;
+
+/// The maximum length of the list of capabilities in a `CAP REQ` message, which leaves room for
+/// `CAP REQ :` and the terminating CR-LF sequence in a message of the maximum length.
+const REQ_LIST_MAX_LEN: usize = message::BODY_MAX_LEN - "CAP REQ :".len() - "\r\n".len();
+
+/// The IRCv3 capabilities that a server supports and that have been enabled for a session.
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    available: BTreeMap<String, Option<String>>,
+    enabled: BTreeSet<String>,
+}
+
+/// The state of a session's capability negotiation.
+#[derive(Clone, Debug)]
+pub(crate) struct Negotiator {
+    requested: Vec<CachedString>,
+    state: State,
+    /// The number of `CAP REQ` messages that the server has yet to reply to, including those sent
+    /// for capabilities announced with `NEW` after the initial negotiation.
+    pending_reqs: usize,
+    end_deferred: bool,
+    starttls_pending: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// `CAP LS` has been sent, and the server's replies to it are being collected.
+    Listing,
+
+    /// `STARTTLS` has been sent, and the connection is to be upgraded to TLS before any
+    /// capabilities are requested.
+    StartingTls,
+
+    /// `CAP REQ` has been sent, and the server's replies to it are awaited.
+    Requesting,
+
+    /// The capabilities have been negotiated, but `CAP END` is being withheld, as while the
+    /// session authenticates.
+    AwaitingEnd,
+
+    /// The initial negotiation is over, because `CAP END` has been sent, because no capabilities
+    /// were requested, or because the server completed registration without negotiating.
+    Done,
+}
+
+impl Capabilities {
+    /// Returns whether the given capability has been enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.enabled.contains(name)
+    }
+
+    /// Returns an iterator over the names of the enabled capabilities, in order by name.
+    pub fn enabled(&self) -> btree_set::Iter<String> {
+        self.enabled.iter()
+    }
+
+    /// Returns whether the server has advertised the given capability as available.
+    pub fn is_available(&self, name: &str) -> bool {
+        self.available.contains_key(name)
+    }
+
+    /// Returns the value with which the server advertised the given capability, such as
+    /// `PLAIN,EXTERNAL` for `sasl=PLAIN,EXTERNAL`, if it advertised one.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.available
+            .get(name)
+            .and_then(|value| value.as_ref().map(|s| &s[..]))
+    }
+}
+
+impl Negotiator {
+    /// Creates a negotiator that will request the given capabilities, where available. If the
+    /// `tls` capability is among them, `STARTTLS` will be sent before the others are requested.
+    pub(crate) fn new(requested: Vec<CachedString>) -> Self {
+        let starttls_pending = requested.iter().any(|name| &name[..] == "tls");
+
+        Negotiator {
+            requested,
+            state: State::Done,
+            pending_reqs: 0,
+            end_deferred: false,
+            starttls_pending,
+        }
+    }
+
+    /// Causes `CAP END` to be withheld once the capabilities have been negotiated, until [`end`]
+    /// is called.
+    ///
+    /// [`end`]: #method.end
+    pub(crate) fn defer_end(&mut self) {
+        self.end_deferred = true;
+    }
+
+    /// Returns whether the capabilities have been negotiated and `CAP END` is being withheld.
+    pub(crate) fn is_awaiting_end(&self) -> bool {
+        self.state == State::AwaitingEnd
+    }
+
+    /// Returns whether `STARTTLS` has been sent, and the connection is to be upgraded to TLS.
+    pub(crate) fn is_starting_tls(&self) -> bool {
+        self.state == State::StartingTls
+    }
+
+    /// Resumes the negotiation once the connection has been upgraded to TLS, returning the
+    /// messages with which the capabilities are listed anew, as the server may offer others over
+    /// TLS.
+    pub(crate) fn tls_started<Msg>(&mut self, caps: &mut Capabilities) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        if self.state != State::StartingTls {
+            return Ok(Vec::new());
+        }
+
+        caps.available.clear();
+        self.start()
+    }
+
+    /// Returns `CAP END`, if it is being withheld, and ends the negotiation.
+    pub(crate) fn end<Msg>(&mut self) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        if self.state != State::AwaitingEnd {
+            return Ok(Vec::new());
+        }
+
+        self.state = State::Done;
+
+        Ok(vec![MessageBuilder::new(Command::Cap).param("END").build()?])
+    }
+
+    /// Returns the messages with which to begin negotiation, which are to be sent before `NICK`
+    /// and `USER`. If no capabilities are to be requested, no negotiation takes place.
+    pub(crate) fn start<Msg>(&mut self) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        if self.requested.is_empty() {
+            self.state = State::Done;
+            return Ok(Vec::new());
+        }
+
+        self.state = State::Listing;
+
+        Ok(vec![MessageBuilder::new(Command::Cap).params(&["LS", "302"]).build()?])
+    }
+
+    /// Updates the negotiation and the given capabilities according to the given message from the
+    /// server, returning the messages to be sent in response.
+    pub(crate) fn handle<Msg>(
+        &mut self,
+        msg: &Msg,
+        caps: &mut Capabilities,
+    ) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        if msg.numeric() == Some(RPL_WELCOME) {
+            // The server has completed registration, so it either does not support capability
+            // negotiation or considers it over.
+            self.state = State::Done;
+            return Ok(Vec::new());
+        }
+
+        if msg.command() != Command::Cap {
+            return Ok(Vec::new());
+        }
+
+        // The parameters are the target, the subcommand, an optional `*` marking a reply that is
+        // continued in the next message, and the list of capabilities.
+        let params = msg.params().skip(1).collect::<Vec<_>>();
+
+        let (subcommand, is_continued, list) = match params.len() {
+            3 if params[1] == b"*" => (params[0], true, params[2]),
+            2 => (params[0], false, params[1]),
+            _ => return Ok(Vec::new()),
+        };
+
+        let entries = list.split(|&b| b == b' ')
+            .filter(|entry| !entry.is_empty())
+            .map(parse_entry)
+            .collect::<Vec<_>>();
+
+        match &subcommand.to_ascii_uppercase()[..] {
+            b"LS" => {
+                caps.available.extend(entries);
+
+                if self.state == State::Listing && !is_continued {
+                    self.listed(caps)
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+            b"ACK" => {
+                for (name, _) in entries {
+                    // A leading `-` marks a capability that has been disabled.
+                    if name.starts_with('-') {
+                        caps.enabled.remove(&name[1..]);
+                    } else {
+                        caps.enabled.insert(name);
+                    }
+                }
+
+                self.req_replied()
+            }
+            b"NAK" => self.req_replied(),
+            b"NEW" => {
+                let new_names = entries
+                    .iter()
+                    .map(|&(ref name, _)| name.clone())
+                    .collect::<Vec<_>>();
+
+                caps.available.extend(entries);
+
+                if self.state == State::Done || self.state == State::AwaitingEnd {
+                    let msgs = self.request(caps, |name| new_names.iter().any(|n| n == name))?;
+                    self.pending_reqs += msgs.len();
+                    Ok(msgs)
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+            b"DEL" => {
+                for (name, _) in entries {
+                    caps.available.remove(&name);
+                    caps.enabled.remove(&name);
+                }
+
+                Ok(Vec::new())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Sends `STARTTLS`, if the connection is to be upgraded to TLS, or else requests the
+    /// capabilities that are wanted, once the server has listed its capabilities.
+    ///
+    /// `STARTTLS` is sent even if the server does not list the `tls` capability, as some servers
+    /// support the command without advertising it; those that do not will reply with an error.
+    fn listed<Msg>(&mut self, caps: &Capabilities) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        if self.starttls_pending {
+            self.starttls_pending = false;
+            self.state = State::StartingTls;
+            return Ok(vec![MessageBuilder::new(Command::Starttls).build()?]);
+        }
+
+        self.request_available(caps)
+    }
+
+    /// Requests the capabilities that are wanted and available, or ends negotiation if there are
+    /// none.
+    fn request_available<Msg>(&mut self, caps: &Capabilities) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        let msgs = self.request(caps, |_| true)?;
+
+        if msgs.is_empty() {
+            self.finish()
+        } else {
+            self.state = State::Requesting;
+            self.pending_reqs += msgs.len();
+            Ok(msgs)
+        }
+    }
+
+    /// Builds `CAP REQ` messages requesting the capabilities that are wanted, available, not yet
+    /// enabled, and accepted by the given filter. The `tls` capability is never requested, as it
+    /// is enabled with `STARTTLS` instead.
+    fn request<Msg, F>(&self, caps: &Capabilities, filter: F) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+        F: Fn(&str) -> bool,
+    {
+        let names = self.requested
+            .iter()
+            .map(|name| &name[..])
+            .filter(|name| *name != "tls")
+            .filter(|name| caps.is_available(name) && !caps.is_enabled(name) && filter(name))
+            .collect::<Vec<_>>();
+
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        cmd::split_text(&names.join(" "), REQ_LIST_MAX_LEN)?
+            .into_iter()
+            .map(|list| {
+                MessageBuilder::new(Command::Cap)
+                    .param("REQ")
+                    .param(list)
+                    .build()
+            })
+            .collect()
+    }
+
+    /// Counts a reply to a `CAP REQ` message, and finishes the initial negotiation once all of its
+    /// requests have been replied to. Replies to requests made afterwards leave the negotiation in
+    /// whatever state it is in, so that a withheld `CAP END` stays withheld.
+    fn req_replied<Msg>(&mut self) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        self.pending_reqs = self.pending_reqs.saturating_sub(1);
+
+        if self.state == State::Requesting && self.pending_reqs == 0 {
+            self.finish()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn finish<Msg>(&mut self) -> message::Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        self.state = State::AwaitingEnd;
+
+        if self.end_deferred {
+            Ok(Vec::new())
+        } else {
+            self.end()
+        }
+    }
+}
+
+/// Parses an entry of a capability list, such as `sasl=PLAIN,EXTERNAL`, into the capability's
+/// name and value. The modifiers `~` and `=` of earlier versions of the specification are
+/// discarded.
+fn parse_entry(entry: &[u8]) -> (String, Option<String>) {
+    let entry = String::from_utf8_lossy(entry);
+    let entry = entry.trim_left_matches(|c| c == '~' || c == '=');
+
+    match entry.find('=') {
+        Some(i) => (entry[..i].to_owned(), Some(entry[i + 1..].to_owned())),
+        None => (entry.to_owned(), None),
+    }
+}
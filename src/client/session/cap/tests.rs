@@ -0,0 +1,356 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use std::borrow::Cow;
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+fn negotiator(requested: &[&str]) -> Negotiator {
+    Negotiator::new(requested.iter().map(|&name| name.into()).collect())
+}
+
+/// Feeds the given lines to the negotiator, returning the bytes of the messages it sends in
+/// response.
+fn handle(negotiator: &mut Negotiator, caps: &mut Capabilities, lines: &[&[u8]]) -> Vec<Vec<u8>> {
+    let mut output = Vec::new();
+
+    for line in lines {
+        let replies: Vec<OwnedMessage> = negotiator.handle(&msg(line), caps).unwrap();
+        output.extend(replies.into_iter().map(OwnedMessage::into_bytes));
+    }
+
+    output
+}
+
+#[test]
+fn start_1() {
+    let msgs: Vec<OwnedMessage> = negotiator(&["multi-prefix"]).start().unwrap();
+
+    assert_eq!(msgs.len(), 1);
+    assert_eq!(msgs[0].as_bytes(), b"CAP LS 302");
+
+    let msgs: Vec<OwnedMessage> = negotiator(&[]).start().unwrap();
+
+    assert!(msgs.is_empty());
+}
+
+#[test]
+fn negotiate_1() {
+    let mut negotiator = negotiator(&["multi-prefix", "sasl", "away-notify", "echo-message"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP * LS * :multi-prefix sasl=PLAIN,EXTERNAL",
+            b":irc.example.com CAP * LS :away-notify account-notify",
+        ],
+    );
+
+    assert_eq!(output, [&b"CAP REQ :multi-prefix sasl away-notify"[..]]);
+    assert!(caps.is_available("account-notify"));
+    assert!(!caps.is_available("echo-message"));
+    assert_eq!(caps.value("sasl"), Some("PLAIN,EXTERNAL"));
+    assert_eq!(caps.value("multi-prefix"), None);
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[b":irc.example.com CAP * ACK :multi-prefix sasl away-notify"],
+    );
+
+    assert_eq!(output, [&b"CAP END"[..]]);
+    assert!(caps.is_enabled("sasl"));
+    assert!(!caps.is_enabled("account-notify"));
+    assert_eq!(
+        caps.enabled().collect::<Vec<_>>(),
+        ["away-notify", "multi-prefix", "sasl"]
+    );
+}
+
+#[test]
+fn negotiate_nak_1() {
+    let mut negotiator = negotiator(&["multi-prefix"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP * LS :multi-prefix",
+            b":irc.example.com CAP * NAK :multi-prefix",
+        ],
+    );
+
+    assert_eq!(output, [&b"CAP REQ multi-prefix"[..], b"CAP END"]);
+    assert!(!caps.is_enabled("multi-prefix"));
+}
+
+#[test]
+fn negotiate_none_available_1() {
+    let mut negotiator = negotiator(&["multi-prefix"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(&mut negotiator, &mut caps, &[b":irc.example.com CAP * LS :sasl"]);
+
+    assert_eq!(output, [&b"CAP END"[..]]);
+}
+
+#[test]
+fn negotiate_unsupported_1() {
+    let mut negotiator = negotiator(&["multi-prefix"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com 001 nick :Welcome",
+            b":irc.example.com CAP nick LS :multi-prefix",
+        ],
+    );
+
+    assert!(output.is_empty());
+    assert!(caps.is_available("multi-prefix"));
+}
+
+#[test]
+fn cap_notify_1() {
+    let mut negotiator = negotiator(&["multi-prefix", "sasl"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP * LS :multi-prefix",
+            b":irc.example.com CAP * ACK multi-prefix",
+            b":irc.example.com CAP nick NEW :sasl=PLAIN account-tag",
+            b":irc.example.com CAP nick ACK :sasl",
+        ],
+    );
+
+    assert_eq!(
+        output,
+        [&b"CAP REQ multi-prefix"[..], b"CAP END", b"CAP REQ sasl"]
+    );
+    assert!(caps.is_enabled("sasl"));
+    assert!(caps.is_available("account-tag"));
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP nick DEL :sasl",
+            b":irc.example.com CAP nick ACK :-multi-prefix",
+        ],
+    );
+
+    assert!(output.is_empty());
+    assert!(!caps.is_enabled("sasl"));
+    assert!(!caps.is_available("sasl"));
+    assert_eq!(caps.enabled().count(), 0);
+}
+
+#[test]
+fn req_split_1() {
+    let names = (0..100).map(|i| format!("vendor.example/cap-{}", i)).collect::<Vec<_>>();
+    let mut negotiator = negotiator(&names.iter().map(|name| &name[..]).collect::<Vec<_>>());
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let ls = format!(":irc.example.com CAP * LS :{}", names.join(" "));
+    let output = handle(&mut negotiator, &mut caps, &[ls.as_bytes()]);
+
+    assert!(output.len() > 1);
+    assert!(output.iter().all(|line| line.len() + 2 <= message::BODY_MAX_LEN));
+
+    let acks = output
+        .iter()
+        .map(|req| {
+            let list = String::from_utf8_lossy(&req[b"CAP REQ ".len()..]).into_owned();
+            format!(":irc.example.com CAP * ACK {}", list)
+        })
+        .collect::<Vec<_>>();
+
+    let (last, rest) = acks.split_last().unwrap();
+
+    for ack in rest {
+        assert!(handle(&mut negotiator, &mut caps, &[ack.as_bytes()]).is_empty());
+    }
+
+    assert_eq!(handle(&mut negotiator, &mut caps, &[last.as_bytes()]), [&b"CAP END"[..]]);
+    assert_eq!(caps.enabled().count(), 100);
+}
+
+#[test]
+fn defer_end_1() {
+    let mut negotiator = negotiator(&["sasl"]);
+    let mut caps = Capabilities::default();
+
+    negotiator.defer_end();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP * LS :sasl",
+            b":irc.example.com CAP * ACK :sasl",
+        ],
+    );
+
+    assert_eq!(output, [&b"CAP REQ sasl"[..]]);
+    assert!(negotiator.is_awaiting_end());
+
+    let output: Vec<OwnedMessage> = negotiator.end().unwrap();
+
+    assert_eq!(output[0].as_bytes(), b"CAP END");
+    assert!(!negotiator.is_awaiting_end());
+
+    let output: Vec<OwnedMessage> = negotiator.end().unwrap();
+
+    assert!(output.is_empty());
+}
+
+#[test]
+fn starttls_1() {
+    let mut negotiator = negotiator(&["tls", "multi-prefix"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[b":irc.example.com CAP * LS :tls multi-prefix"],
+    );
+
+    assert_eq!(output, [&b"STARTTLS"[..]]);
+    assert!(negotiator.is_starting_tls());
+
+    let output: Vec<OwnedMessage> = negotiator.tls_started(&mut caps).unwrap();
+
+    assert_eq!(output[0].as_bytes(), b"CAP LS 302");
+    assert!(!negotiator.is_starting_tls());
+    assert!(!caps.is_available("multi-prefix"));
+
+    // Once the connection uses TLS, `tls` is neither requested nor started again.
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP * LS :tls multi-prefix sasl",
+            b":irc.example.com CAP * ACK :multi-prefix",
+        ],
+    );
+
+    assert_eq!(output, [&b"CAP REQ multi-prefix"[..], b"CAP END"]);
+    assert!(!caps.is_enabled("tls"));
+
+    let output: Vec<OwnedMessage> = negotiator.tls_started(&mut caps).unwrap();
+
+    assert!(output.is_empty());
+}
+
+#[test]
+fn starttls_unlisted_1() {
+    let mut negotiator = negotiator(&["tls"]);
+    let mut caps = Capabilities::default();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(&mut negotiator, &mut caps, &[b":irc.example.com CAP * LS :sasl"]);
+
+    assert_eq!(output, [&b"STARTTLS"[..]]);
+}
+
+/// Returns a negotiator that has negotiated the `multi-prefix` capability and is withholding
+/// `CAP END`.
+fn awaiting_end(requested: &[&str], caps: &mut Capabilities) -> Negotiator {
+    let mut negotiator = negotiator(requested);
+
+    negotiator.defer_end();
+
+    let _: Vec<OwnedMessage> = negotiator.start().unwrap();
+
+    let output = handle(
+        &mut negotiator,
+        caps,
+        &[
+            b":irc.example.com CAP * LS :multi-prefix",
+            b":irc.example.com CAP * ACK :multi-prefix",
+        ],
+    );
+
+    assert_eq!(output, [&b"CAP REQ multi-prefix"[..]]);
+    assert!(negotiator.is_awaiting_end());
+
+    negotiator
+}
+
+#[test]
+fn cap_notify_awaiting_end_1() {
+    let mut caps = Capabilities::default();
+    let mut negotiator = awaiting_end(&["multi-prefix", "sasl"], &mut caps);
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[b":irc.example.com CAP * NEW :sasl=PLAIN"],
+    );
+
+    assert_eq!(output, [&b"CAP REQ sasl"[..]]);
+    assert_eq!(negotiator.pending_reqs, 1);
+
+    let output = handle(&mut negotiator, &mut caps, &[b":irc.example.com CAP * ACK :sasl"]);
+
+    assert!(output.is_empty());
+    assert_eq!(negotiator.pending_reqs, 0);
+    assert!(caps.is_enabled("sasl"));
+    assert!(negotiator.is_awaiting_end());
+
+    let output: Vec<OwnedMessage> = negotiator.end().unwrap();
+
+    assert_eq!(output[0].as_bytes(), b"CAP END");
+}
+
+#[test]
+fn cap_notify_awaiting_end_2() {
+    let mut caps = Capabilities::default();
+    let mut negotiator = awaiting_end(&["multi-prefix", "sasl"], &mut caps);
+
+    let output = handle(
+        &mut negotiator,
+        &mut caps,
+        &[
+            b":irc.example.com CAP * NEW :sasl=PLAIN",
+            b":irc.example.com CAP * NAK :sasl",
+        ],
+    );
+
+    assert_eq!(output, [&b"CAP REQ sasl"[..]]);
+    assert_eq!(negotiator.pending_reqs, 0);
+    assert!(!caps.is_enabled("sasl"));
+    assert!(negotiator.is_awaiting_end());
+
+    let output: Vec<OwnedMessage> = negotiator.end().unwrap();
+
+    assert_eq!(output[0].as_bytes(), b"CAP END");
+}
@@ -0,0 +1,229 @@
+//! Tracking of the features that a server advertises in `RPL_ISUPPORT` (005).
+
+use Message;
+use message::casemap::CaseMapping;
+use message::modes::ModeSpec;
+use message::numeric::RPL_ISUPPORT;
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use std::str;
+
+mod tests;
+
+/// The features that a server has advertised in `RPL_ISUPPORT` replies.
+///
+/// A server may send several such replies, each with some tokens, and may later revoke a token by
+/// sending it again with a leading `-`; each reply updates the features accordingly. Where the
+/// server has not advertised a token, the corresponding accessor returns the value that RFC 1459
+/// and RFC 2811 imply, if there is one.
+#[derive(Clone, Debug, Default)]
+pub struct ServerFeatures {
+    tokens: BTreeMap<Vec<u8>, Vec<u8>>,
+    network: Option<String>,
+    casemapping: CaseMapping,
+    mode_spec: ModeSpec,
+    nicklen: Option<usize>,
+    topiclen: Option<usize>,
+    targmax: BTreeMap<Vec<u8>, Option<usize>>,
+    monitor: Option<Option<usize>>,
+}
+
+impl ServerFeatures {
+    /// Returns the name of the network, as given in the `NETWORK` token.
+    pub fn network(&self) -> Option<&str> {
+        self.network.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the case mapping under which the server compares nicknames and channel names, as
+    /// given in the `CASEMAPPING` token. Unknown mappings are disregarded.
+    pub fn casemapping(&self) -> CaseMapping {
+        self.casemapping
+    }
+
+    /// Returns the characters with which channel names may start, as given in the `CHANTYPES`
+    /// token.
+    pub fn chantypes(&self) -> &[u8] {
+        self.mode_spec.chantypes()
+    }
+
+    /// Returns a description of the server's channel modes, assembled from the `CHANMODES`,
+    /// `PREFIX`, `MODES`, and `CHANTYPES` tokens, for use with the [`modes`] module. Invalid
+    /// values of these tokens are disregarded.
+    ///
+    /// [`modes`]: ../../message/modes/index.html
+    pub fn mode_spec(&self) -> &ModeSpec {
+        &self.mode_spec
+    }
+
+    /// Returns the maximum length of a nickname, as given in the `NICKLEN` token.
+    pub fn nicklen(&self) -> Option<usize> {
+        self.nicklen
+    }
+
+    /// Returns the maximum length of a channel topic, as given in the `TOPICLEN` token.
+    pub fn topiclen(&self) -> Option<usize> {
+        self.topiclen
+    }
+
+    /// Returns the maximum number of targets that a message with the given command may have, as
+    /// given in the `TARGMAX` token. Returns `None` if the server advertised no limit for the
+    /// command, whether by omitting it or by giving it without a number.
+    pub fn targmax(&self, command: &str) -> Option<usize> {
+        self.targmax
+            .get(command.to_ascii_uppercase().as_bytes())
+            .and_then(|&max| max)
+    }
+
+    /// Returns whether the server supports the `MONITOR` command, as indicated by the `MONITOR`
+    /// token.
+    pub fn supports_monitor(&self) -> bool {
+        self.monitor.is_some()
+    }
+
+    /// Returns the maximum number of nicknames that a client may monitor, if the server supports
+    /// the `MONITOR` command and limits this.
+    pub fn monitor_limit(&self) -> Option<usize> {
+        self.monitor.and_then(|limit| limit)
+    }
+
+    /// Returns the value of the token with the given name, if the server has advertised it. A
+    /// token advertised without a value has an empty value. Escape sequences of the form `\xHH`
+    /// in the value have been replaced with the bytes they stand for.
+    pub fn token(&self, name: &str) -> Option<&[u8]> {
+        self.tokens.get(name.as_bytes()).map(|value| &value[..])
+    }
+
+    /// Returns an iterator over the names and values of the tokens that the server has
+    /// advertised, including any of which this type does not otherwise know, in order by name.
+    pub fn tokens(&self) -> btree_map::Iter<Vec<u8>, Vec<u8>> {
+        self.tokens.iter()
+    }
+
+    /// Updates the features with the tokens of the given message, if it is an `RPL_ISUPPORT`
+    /// reply.
+    pub(crate) fn handle_isupport<Msg>(&mut self, msg: &Msg)
+    where
+        Msg: Message,
+    {
+        if msg.numeric() != Some(RPL_ISUPPORT) {
+            return;
+        }
+
+        // The first parameter is our nickname, and the last is a human-readable description.
+        let param_count = msg.params().count();
+
+        for token in msg.params().take(param_count.saturating_sub(1)).skip(1) {
+            if token.starts_with(b"-") {
+                self.tokens.remove(&token[1..]);
+                continue;
+            }
+
+            let (name, value) = match token.iter().position(|&b| b == b'=') {
+                Some(i) => (&token[..i], unescape(&token[i + 1..])),
+                None => (token, Vec::new()),
+            };
+
+            if !name.is_empty() {
+                self.tokens.insert(name.to_owned(), value);
+            }
+        }
+
+        self.update_typed_features();
+    }
+
+    /// Recomputes the typed features from the tokens.
+    fn update_typed_features(&mut self) {
+        self.network = self.tokens
+            .get(&b"NETWORK"[..])
+            .map(|value| String::from_utf8_lossy(value).into_owned());
+
+        self.casemapping = self.tokens
+            .get(&b"CASEMAPPING"[..])
+            .and_then(|value| CaseMapping::from_name(value))
+            .unwrap_or_default();
+
+        let mut mode_spec = ModeSpec::default();
+
+        if let Some(value) = self.tokens.get(&b"CHANMODES"[..]) {
+            mode_spec = mode_spec.clone().with_chanmodes(value).unwrap_or(mode_spec);
+        }
+
+        if let Some(value) = self.tokens.get(&b"PREFIX"[..]) {
+            mode_spec = mode_spec.clone().with_prefix(value).unwrap_or(mode_spec);
+        }
+
+        if let Some(value) = self.tokens.get(&b"MODES"[..]) {
+            // A `MODES` token without a value means that there is no limit.
+            mode_spec = match parse_number(value) {
+                Some(max_modes) => mode_spec.with_max_modes(Some(max_modes)),
+                None if value.is_empty() => mode_spec.with_max_modes(None),
+                None => mode_spec,
+            };
+        }
+
+        if let Some(value) = self.tokens.get(&b"CHANTYPES"[..]) {
+            mode_spec = mode_spec.with_chantypes(value);
+        }
+
+        self.mode_spec = mode_spec;
+
+        self.nicklen = self.tokens
+            .get(&b"NICKLEN"[..])
+            .and_then(|value| parse_number(value));
+
+        self.topiclen = self.tokens
+            .get(&b"TOPICLEN"[..])
+            .and_then(|value| parse_number(value));
+
+        self.targmax = self.tokens
+            .get(&b"TARGMAX"[..])
+            .map(|value| {
+                value
+                    .split(|&b| b == b',')
+                    .filter_map(|entry| {
+                        let i = entry.iter().position(|&b| b == b':')?;
+                        Some((entry[..i].to_ascii_uppercase(), parse_number(&entry[i + 1..])))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.monitor = self.tokens
+            .get(&b"MONITOR"[..])
+            .map(|value| parse_number(value));
+    }
+}
+
+fn parse_number(value: &[u8]) -> Option<usize> {
+    str::from_utf8(value).ok()?.parse().ok()
+}
+
+/// Replaces the `\xHH` escape sequences in the given token value with the bytes they stand for.
+/// Malformed escape sequences are left as they are.
+fn unescape(value: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(value.len());
+    let mut i = 0;
+
+    while i < value.len() {
+        let escaped = if value[i..].starts_with(b"\\x") && value.len() >= i + 4 {
+            str::from_utf8(&value[i + 2..i + 4])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(b) => {
+                output.push(b);
+                i += 4;
+            }
+            None => {
+                output.push(value[i]);
+                i += 1;
+            }
+        }
+    }
+
+    output
+}
@@ -0,0 +1,97 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use message::modes::ModeType;
+use std::borrow::Cow;
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+fn features(lines: &[&[u8]]) -> ServerFeatures {
+    let mut features = ServerFeatures::default();
+
+    for line in lines {
+        features.handle_isupport(&msg(line));
+    }
+
+    features
+}
+
+#[test]
+fn defaults_1() {
+    let features = ServerFeatures::default();
+
+    assert_eq!(features.network(), None);
+    assert_eq!(features.casemapping(), CaseMapping::Rfc1459);
+    assert_eq!(features.chantypes(), b"#&");
+    assert_eq!(features.mode_spec(), &ModeSpec::default());
+    assert_eq!(features.nicklen(), None);
+    assert_eq!(features.targmax("PRIVMSG"), None);
+    assert!(!features.supports_monitor());
+    assert_eq!(features.tokens().count(), 0);
+}
+
+#[test]
+fn isupport_1() {
+    let features = features(&[
+        b":irc.example.com 005 nick NETWORK=Example\\x20Net CASEMAPPING=ascii CHANTYPES=# \
+          PREFIX=(qaohv)~&@%+ CHANMODES=beI,k,l,imnpst MODES=4 :are supported by this server",
+        b":irc.example.com 005 nick NICKLEN=30 TOPICLEN=390 MONITOR=100 EXCEPTS \
+          TARGMAX=PRIVMSG:4,NOTICE:4,JOIN: :are supported by this server",
+    ]);
+
+    assert_eq!(features.network(), Some("Example Net"));
+    assert_eq!(features.casemapping(), CaseMapping::Ascii);
+    assert_eq!(features.chantypes(), b"#");
+    assert_eq!(features.mode_spec().mode_type(b'h'), ModeType::Prefix);
+    assert_eq!(features.mode_spec().prefix_symbol(b'q'), Some(b'~'));
+    assert_eq!(features.mode_spec().max_modes(), Some(4));
+    assert_eq!(features.nicklen(), Some(30));
+    assert_eq!(features.topiclen(), Some(390));
+    assert!(features.supports_monitor());
+    assert_eq!(features.monitor_limit(), Some(100));
+    assert_eq!(features.targmax("privmsg"), Some(4));
+    assert_eq!(features.targmax("JOIN"), None);
+    assert_eq!(features.targmax("KICK"), None);
+    assert_eq!(features.token("EXCEPTS"), Some(&b""[..]));
+    assert_eq!(features.token("NETWORK"), Some(&b"Example Net"[..]));
+    assert_eq!(features.token("WHOX"), None);
+    assert_eq!(features.tokens().count(), 11);
+}
+
+#[test]
+fn isupport_negation_1() {
+    let features = features(&[
+        b":irc.example.com 005 nick CASEMAPPING=ascii MONITOR MODES= :are supported",
+        b":irc.example.com 005 nick -CASEMAPPING -MONITOR :are supported",
+    ]);
+
+    assert_eq!(features.casemapping(), CaseMapping::Rfc1459);
+    assert!(!features.supports_monitor());
+    assert_eq!(features.mode_spec().max_modes(), None);
+    assert_eq!(features.token("CASEMAPPING"), None);
+}
+
+#[test]
+fn isupport_invalid_1() {
+    let features = features(&[
+        b":irc.example.com 005 nick CASEMAPPING=rfc7613 PREFIX=@+ CHANMODES=b NICKLEN=x :hi",
+        b":irc.example.com NOTICE nick NETWORK=NotThis :hi",
+    ]);
+
+    assert_eq!(features.casemapping(), CaseMapping::Rfc1459);
+    assert_eq!(features.mode_spec(), &ModeSpec::default());
+    assert_eq!(features.nicklen(), None);
+    assert_eq!(features.network(), None);
+    assert_eq!(features.token("PREFIX"), Some(&b"@+"[..]));
+}
+
+#[test]
+fn unescape_1() {
+    assert_eq!(unescape(b"a\\x20b\\x3Dc"), b"a b=c");
+    assert_eq!(unescape(b"\\x2"), b"\\x2");
+    assert_eq!(unescape(b"\\xZZ"), b"\\xZZ");
+    assert_eq!(unescape(b""), b"");
+}
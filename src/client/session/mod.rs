@@ -1,25 +1,56 @@
+pub use self::cap::Capabilities;
+pub use self::features::ServerFeatures;
+pub use self::sasl::SaslMechanism;
 use Message;
+use client;
 use client::Result;
 use connection;
-use connection::Connection;
+use connection::ConfigureDecoding;
+use connection::ConfigureLineLimits;
 use connection::ConnectionPrivate;
 use connection::GenericConnection;
 use connection::GetPeerAddr;
+use connection::LineLimits;
 use connection::ReceiveMessage;
 use connection::SendMessage;
+use message::casemap::CaseMapping;
+use message::casemap::Nickname;
+use message::cmd;
+use message::encoding::DecodingPolicy;
+use message::numeric::ERR_STARTTLS;
+use message::numeric::ERR_UNKNOWNCOMMAND;
+use message::numeric::RPL_HOSTHIDDEN;
+use message::numeric::RPL_ISUPPORT;
+use message::numeric::RPL_STARTTLS;
+use message::numeric::RPL_WELCOME;
+use message::Command;
+use message::MessageRef;
+use message::OwnedMessage;
 use mio;
-#[cfg(feature = "pircolate")]
-use pircolate;
+use rustls;
 use std::fmt;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use string_cache::DefaultAtom as CachedString;
 
+mod cap;
+mod features;
+mod sasl;
+mod tests;
+
 lazy_static! {
     static ref DEFAULT_REALNAME: CachedString = format!(
             "Connected with <{url}> v{ver}",
             url = env!("CARGO_PKG_HOMEPAGE"),
             ver = env!("CARGO_PKG_VERSION")
         ).into();
+
+    static ref CTCP_VERSION: String = format!(
+            "{name} v{ver} <{url}>",
+            name = env!("CARGO_PKG_NAME"),
+            ver = env!("CARGO_PKG_VERSION"),
+            url = env!("CARGO_PKG_HOMEPAGE")
+        );
 }
 
 #[derive(Debug)]
@@ -28,9 +59,17 @@ pub struct Session {
     nickname: CachedString,
     username: CachedString,
     realname: CachedString,
+    own_user: Option<String>,
+    own_host: Option<String>,
+    casemapping: Option<CaseMapping>,
+    server_features: Arc<ServerFeatures>,
+    capabilities: Arc<Capabilities>,
+    cap_negotiator: cap::Negotiator,
+    sasl: Option<sasl::Authenticator>,
+    starttls: Option<StartTls>,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct SessionBuilder<
     ConnField = Option<GenericConnection>,
     NicknameField = Option<CachedString>,
@@ -46,6 +85,16 @@ pub struct SessionBuilder<
     nickname: NicknameField,
     username: UsernameField,
     realname: RealnameField,
+    capabilities: Vec<CachedString>,
+    sasl: Vec<Box<SaslMechanism>>,
+    starttls: Option<StartTls>,
+}
+
+/// The configuration with which a session upgrades its connection to TLS with `STARTTLS`.
+#[derive(Clone)]
+struct StartTls {
+    config: Arc<rustls::ClientConfig>,
+    hostname: String,
 }
 
 impl<ConnField, NicknameField, UsernameField, RealnameField>
@@ -68,6 +117,9 @@ where
             nickname,
             username,
             realname,
+            capabilities,
+            sasl,
+            starttls,
         } = self;
 
         SessionBuilder {
@@ -75,6 +127,9 @@ where
             nickname,
             username,
             realname,
+            capabilities,
+            sasl,
+            starttls,
         }
     }
 
@@ -90,6 +145,9 @@ where
             nickname: _,
             username,
             realname,
+            capabilities,
+            sasl,
+            starttls,
         } = self;
 
         SessionBuilder {
@@ -97,6 +155,9 @@ where
             nickname: value.into(),
             username,
             realname,
+            capabilities,
+            sasl,
+            starttls,
         }
     }
 
@@ -112,6 +173,9 @@ where
             nickname,
             username: _,
             realname,
+            capabilities,
+            sasl,
+            starttls,
         } = self;
 
         SessionBuilder {
@@ -119,6 +183,9 @@ where
             nickname,
             username: value.into(),
             realname,
+            capabilities,
+            sasl,
+            starttls,
         }
     }
 
@@ -134,6 +201,9 @@ where
             nickname,
             username,
             realname: _,
+            capabilities,
+            sasl,
+            starttls,
         } = self;
 
         SessionBuilder {
@@ -141,8 +211,128 @@ where
             nickname,
             username,
             realname: value.into(),
+            capabilities,
+            sasl,
+            starttls,
         }
     }
+
+    /// Adds the given IRCv3 capability to those that the session will request during
+    /// registration, if the server supports it. See [`Session::capabilities`] for the
+    /// capabilities that were enabled.
+    ///
+    /// [`Session::capabilities`]: struct.Session.html#method.capabilities
+    pub fn capability<S>(mut self, name: S) -> Self
+    where
+        S: Into<CachedString>,
+    {
+        self.capabilities.push(name.into());
+        self
+    }
+
+    /// Adds the given IRCv3 capabilities to those that the session will request, as with
+    /// [`capability`].
+    ///
+    /// [`capability`]: #method.capability
+    pub fn capabilities<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<CachedString>,
+    {
+        self.capabilities.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a mechanism with which the session may authenticate with SASL before completing
+    /// registration. The `sasl` capability is requested automatically.
+    ///
+    /// Of the mechanisms added, the session uses the first, in the order in which they were added,
+    /// that the server lists in the value of the `sasl` capability, or the first of all if the
+    /// server lists none. If the server rejects a mechanism before the exchange has begun, as one
+    /// that it does not support, the next that it supports is tried, where the server has listed
+    /// its mechanisms in `RPL_SASLMECHS` (908), or else the next of all. If authentication
+    /// fails, registration is completed without it, and the failure is reported as an error of
+    /// kind [`ErrorKind::SaslFailed`] to the client's message handler.
+    ///
+    /// [`ErrorKind::SaslFailed`]: ../enum.ErrorKind.html#variant.SaslFailed
+    pub fn sasl_mechanism<M>(mut self, mechanism: M) -> Self
+    where
+        M: SaslMechanism + 'static,
+    {
+        self.sasl.push(Box::new(mechanism));
+        self
+    }
+
+    /// Adds the SASL `PLAIN` mechanism, with the given account name and password, as with
+    /// [`sasl_mechanism`]. This mechanism sends the password to the server; to prefer SCRAM where
+    /// the server supports it, call [`sasl_scram`] first.
+    ///
+    /// [`sasl_mechanism`]: #method.sasl_mechanism
+    /// [`sasl_scram`]: #method.sasl_scram
+    pub fn sasl_plain<A, P>(self, account: A, password: P) -> Self
+    where
+        A: Into<CachedString>,
+        P: Into<String>,
+    {
+        self.sasl_mechanism(sasl::Plain::new(
+            sasl::Credentials::new(account.into(), password.into()),
+        ))
+    }
+
+    /// Adds the SASL `SCRAM-SHA-256` and `SCRAM-SHA-1` mechanisms, in that order of preference,
+    /// with the given account name and password, as with [`sasl_mechanism`]. With these
+    /// mechanisms, the client proves that it knows the password without sending it, and the server
+    /// must prove the same in turn.
+    ///
+    /// [`sasl_mechanism`]: #method.sasl_mechanism
+    pub fn sasl_scram<A, P>(self, account: A, password: P) -> Self
+    where
+        A: Into<CachedString>,
+        P: Into<String>,
+    {
+        let credentials = sasl::Credentials::new(account.into(), password.into());
+
+        self.sasl_mechanism(sasl::Scram::new(sasl::ScramAlgorithm::Sha256, credentials.clone()))
+            .sasl_mechanism(sasl::Scram::new(sasl::ScramAlgorithm::Sha1, credentials))
+    }
+
+    /// Adds the SASL `EXTERNAL` mechanism, as with [`sasl_mechanism`].
+    ///
+    /// With this mechanism, the server identifies the client by credentials established outside
+    /// of IRC, usually the TLS client certificate that the session's connection presented; see
+    /// [`ClientCertificate`]. The account is that with which the certificate's fingerprint has
+    /// been registered.
+    ///
+    /// [`sasl_mechanism`]: #method.sasl_mechanism
+    /// [`ClientCertificate`]: ../../connection/struct.ClientCertificate.html
+    pub fn sasl_external(self) -> Self {
+        self.sasl_mechanism(sasl::External)
+    }
+
+    /// Causes the session to upgrade its plaintext connection to TLS with `STARTTLS`, verifying
+    /// the server's certificate for the given hostname with the given configuration, as made with
+    /// [`TlsOptions`]. The `tls` capability is requested automatically; requesting it is what
+    /// causes `STARTTLS` to be sent.
+    ///
+    /// `STARTTLS` is sent once the server has listed its capabilities; the other capabilities are
+    /// requested, and SASL authentication takes place, only once the connection uses TLS. If the
+    /// server refuses `STARTTLS` or does not support it, or completes registration without it, the
+    /// failure is reported as an error of kind [`ErrorKind::StartTlsFailed`] to the client's
+    /// message handler, and the session does not carry on to negotiate capabilities or
+    /// authenticate in plaintext.
+    ///
+    /// [`TlsOptions`]: ../../connection/tls/struct.TlsOptions.html
+    /// [`ErrorKind::StartTlsFailed`]: ../../connection/enum.ErrorKind.html#variant.StartTlsFailed
+    pub fn starttls<S>(mut self, config: Arc<rustls::ClientConfig>, hostname: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.starttls = Some(StartTls {
+            config,
+            hostname: hostname.into(),
+        });
+        self
+    }
 }
 
 pub fn build() -> SessionBuilder {
@@ -151,6 +341,9 @@ pub fn build() -> SessionBuilder {
         nickname: None,
         username: None,
         realname: None,
+        capabilities: Vec::new(),
+        sasl: Vec::new(),
+        starttls: None,
     }
 }
 
@@ -173,27 +366,325 @@ where
             nickname,
             username,
             realname,
+            capabilities,
+            sasl,
+            starttls,
         } = self;
 
         let username = username.into().unwrap_or(nickname.clone());
         let realname = realname.into().unwrap_or(DEFAULT_REALNAME.clone());
 
-        connection.try_send(&pircolate::Message::try_from(
-            format!("NICK {}", nickname),
-        )?)?;
-        connection.try_send(&pircolate::Message::try_from(
-            format!("USER {} 8 * :{}", username, realname),
-        )?)?;
+        let mut capabilities = capabilities;
+
+        if !sasl.is_empty() && !capabilities.iter().any(|name| &name[..] == "sasl") {
+            capabilities.push("sasl".into());
+        }
+
+        if capabilities.iter().any(|name| &name[..] == "tls") {
+            ensure!(
+                starttls.is_some(),
+                starttls_failed("the `tls` capability was requested without a TLS configuration")
+            );
+        } else if starttls.is_some() {
+            capabilities.push("tls".into());
+        }
+
+        // Capability negotiation, if any, must begin before registration, so that the server
+        // suspends registration until negotiation is over.
+        let mut cap_negotiator = cap::Negotiator::new(capabilities);
+
+        if !sasl.is_empty() {
+            cap_negotiator.defer_end();
+        }
+
+        for msg in cap_negotiator.start::<OwnedMessage>()? {
+            connection.try_send(&msg)?;
+        }
+
+        connection.try_send(&cmd::nick::<OwnedMessage>(&nickname)?)?;
+        connection.try_send(&cmd::user::<OwnedMessage>(&username, &realname)?)?;
 
         Ok(Session {
             connection,
             nickname,
             username,
             realname,
+            own_user: None,
+            own_host: None,
+            casemapping: None,
+            server_features: Default::default(),
+            capabilities: Default::default(),
+            cap_negotiator,
+            sasl: if sasl.is_empty() {
+                None
+            } else {
+                Some(sasl::Authenticator::new(sasl))
+            },
+            starttls,
         })
     }
 }
 
+impl fmt::Debug for StartTls {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StartTls")
+            .field("hostname", &self.hostname)
+            .finish()
+    }
+}
+
+fn starttls_failed(desc: &'static str) -> client::Error {
+    connection::Error::from(connection::ErrorKind::StartTlsFailed(desc.into())).into()
+}
+
+/// Returns the version string with which the client replies to CTCP `VERSION` queries.
+pub(crate) fn ctcp_version() -> &'static str {
+    &CTCP_VERSION
+}
+
+impl Session {
+    /// Returns the session's nickname, which is that with which it was registered until the server
+    /// confirms a change of nickname.
+    pub fn nickname(&self) -> Nickname {
+        Nickname::new(self.nickname.clone(), self.casemapping())
+    }
+
+    /// Returns the case mapping under which the server compares nicknames and channel names.
+    /// This is the mapping set with [`set_casemapping`], if any; otherwise, it is the mapping the
+    /// server advertised in `RPL_ISUPPORT`, if any, or the default mapping.
+    ///
+    /// [`set_casemapping`]: #method.set_casemapping
+    pub fn casemapping(&self) -> CaseMapping {
+        self.casemapping.unwrap_or_else(|| self.server_features.casemapping())
+    }
+
+    /// Overrides the case mapping that the server advertised, for servers that advertise the
+    /// wrong mapping.
+    pub fn set_casemapping(&mut self, casemapping: CaseMapping) {
+        self.casemapping = Some(casemapping)
+    }
+
+    /// Returns the features that the server has advertised in `RPL_ISUPPORT` replies.
+    pub fn server_features(&self) -> &ServerFeatures {
+        &self.server_features
+    }
+
+    /// Returns a shared reference to the features that the server has advertised so far, which
+    /// later `RPL_ISUPPORT` replies will not change.
+    pub(crate) fn server_features_snapshot(&self) -> Arc<ServerFeatures> {
+        self.server_features.clone()
+    }
+
+    /// Returns the IRCv3 capabilities that the server supports and that have been enabled.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Returns a shared reference to the capabilities as they are now, which later capability
+    /// negotiation will not change.
+    pub(crate) fn capabilities_snapshot(&self) -> Arc<Capabilities> {
+        self.capabilities.clone()
+    }
+
+    /// Returns the account as which the server has reported the session to be logged in with
+    /// SASL, if any.
+    pub fn account(&self) -> Option<&str> {
+        self.sasl.as_ref().and_then(|sasl| sasl.account())
+    }
+
+    /// Updates the session's state according to the given message from the server, sending any
+    /// messages with which the session must respond.
+    pub(crate) fn handle_incoming<Msg>(&mut self, msg: &Msg) -> Result<()>
+    where
+        Msg: Message,
+    {
+        // Only some messages change the features or capabilities, so other messages need not
+        // copy any that message contexts share.
+        if msg.numeric() == Some(RPL_ISUPPORT) {
+            Arc::make_mut(&mut self.server_features).handle_isupport(msg);
+        }
+
+        self.track_own_prefix(msg);
+
+        let mut replies = Vec::new();
+
+        // The connection is upgraded, if at all, before any replies are sent, which must be sent
+        // over TLS.
+        let starttls_result = self.handle_starttls(msg, &mut replies);
+
+        if msg.command() == Command::Cap || msg.numeric() == Some(RPL_WELCOME) {
+            let cap_replies = self.cap_negotiator
+                .handle::<Msg>(msg, Arc::make_mut(&mut self.capabilities))?;
+
+            replies.extend(cap_replies);
+        }
+
+        // The replies are sent even if authentication fails, so that registration can proceed.
+        let sasl_result = self.handle_sasl(msg, &mut replies);
+
+        for reply in replies {
+            self.connection.try_send(&reply)?;
+        }
+
+        starttls_result.and(sasl_result)
+    }
+
+    /// Upgrades the session's connection to TLS once the server has agreed to `STARTTLS`, if the
+    /// session is to use `STARTTLS`, adding any messages with which the session must respond to
+    /// `replies`.
+    ///
+    /// If the upgrade fails, capability negotiation is left suspended, and registration with it,
+    /// so that the session does not carry on in plaintext.
+    fn handle_starttls<Msg>(&mut self, msg: &Msg, replies: &mut Vec<Msg>) -> Result<()>
+    where
+        Msg: Message,
+    {
+        let failure = match msg.numeric() {
+            Some(RPL_STARTTLS) if self.cap_negotiator.is_starting_tls() => None,
+            Some(ERR_STARTTLS) => Some("the server failed to start TLS"),
+            Some(ERR_UNKNOWNCOMMAND) if msg.params().nth(1) == Some(&b"STARTTLS"[..]) => {
+                Some("the server does not support STARTTLS")
+            }
+            Some(RPL_WELCOME) => Some("the server completed registration without starting TLS"),
+            _ => return Ok(()),
+        };
+
+        // The upgrade is attempted only once, whether or not it succeeds.
+        let starttls = match self.starttls.take() {
+            Some(starttls) => starttls,
+            None => return Ok(()),
+        };
+
+        if let Some(desc) = failure {
+            bail!(starttls_failed(desc));
+        }
+
+        self.connection.start_tls(&starttls.config, &starttls.hostname)?;
+
+        let cap_replies = self.cap_negotiator
+            .tls_started(Arc::make_mut(&mut self.capabilities))?;
+
+        replies.extend(cap_replies);
+
+        Ok(())
+    }
+
+    /// Advances SASL authentication, if the session is to authenticate, adding any messages with
+    /// which the session must respond to `replies`. Capability negotiation is ended once
+    /// authentication has succeeded or failed.
+    fn handle_sasl<Msg>(&mut self, msg: &Msg, replies: &mut Vec<Msg>) -> Result<()>
+    where
+        Msg: Message,
+    {
+        let sasl = match self.sasl {
+            Some(ref mut sasl) => sasl,
+            None => return Ok(()),
+        };
+
+        let result = if self.cap_negotiator.is_awaiting_end() && !sasl.is_started() {
+            if self.capabilities.is_enabled("sasl") {
+                sasl.start(self.capabilities.value("sasl"))
+            } else {
+                sasl.fail_unavailable()
+            }
+        } else {
+            sasl.handle(msg)
+        };
+
+        match result {
+            Ok(msgs) => replies.extend(msgs),
+            Err(err) => {
+                replies.extend(self.cap_negotiator.end()?);
+                return Err(err);
+            }
+        }
+
+        if sasl.is_finished() {
+            replies.extend(self.cap_negotiator.end()?);
+        }
+
+        Ok(())
+    }
+
+    /// Keeps track of the `nick!user@host` prefix with which the server will relay this session's
+    /// messages: the nickname as the server confirms changes to it with `NICK`, and the username
+    /// and hostname as the server gives them in `RPL_WELCOME` and `RPL_HOSTHIDDEN`.
+    fn track_own_prefix<Msg>(&mut self, msg: &Msg)
+    where
+        Msg: Message,
+    {
+        if msg.command() == Command::Nick {
+            let is_own = msg.nick().map_or(false, |nick| {
+                self.casemapping()
+                    .eq_ignore_case(nick, self.nickname.as_bytes())
+            });
+
+            if let (true, Some(nickname)) = (is_own, msg.param(0)) {
+                self.nickname = String::from_utf8_lossy(nickname).into_owned().into();
+            }
+
+            return;
+        }
+
+        match msg.numeric() {
+            Some(RPL_WELCOME) => {
+                // The server addresses the reply to the nickname under which it registered the
+                // session, and commonly ends it with the session's `nick!user@host`.
+                if let Some(nickname) = msg.param(0) {
+                    self.nickname = String::from_utf8_lossy(nickname).into_owned().into();
+                }
+
+                let user_host = msg.trailing()
+                    .and_then(|text| text.split(|&b| b == b' ').last())
+                    .and_then(|mask| {
+                        mask.iter()
+                            .position(|&b| b == b'!')
+                            .map(|i| &mask[i + 1..])
+                    });
+
+                if let Some(user_host) = user_host {
+                    self.set_own_user_host(user_host);
+                }
+            }
+            Some(RPL_HOSTHIDDEN) => {
+                // Some servers give the username with the new hostname.
+                if let Some(host) = msg.param(1) {
+                    self.set_own_user_host(host);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Records the given hostname or `user@host` as those with which the server will relay this
+    /// session's messages.
+    fn set_own_user_host(&mut self, user_host: &[u8]) {
+        let user_host = String::from_utf8_lossy(user_host);
+
+        match user_host.find('@') {
+            Some(i) => {
+                self.own_user = Some(user_host[..i].to_owned());
+                self.own_host = Some(user_host[i + 1..].to_owned());
+            }
+            None => self.own_host = Some(user_host.into_owned()),
+        }
+    }
+
+    /// Returns an upper bound on the length of the `nick!user@host` prefix with which the server
+    /// will relay this session's messages. Until the server gives them, the username is assumed
+    /// to be given a leading `~` by the server, and the longest plausible hostname is assumed.
+    pub(crate) fn own_prefix_len(&self) -> usize {
+        let user_len = self.own_user
+            .as_ref()
+            .map_or("~".len() + self.username.len(), |user| user.len());
+        let host_len = self.own_host
+            .as_ref()
+            .map_or(cmd::HOST_MAX_LEN, |host| host.len());
+
+        self.nickname.len() + "!".len() + user_len + "@".len() + host_len
+    }
+}
+
 pub trait TryIntoSession {
     fn try_into_session(self) -> Result<Session>;
 }
@@ -223,6 +714,13 @@ impl ReceiveMessage for Session {
     {
         self.connection.recv()
     }
+
+    fn recv_with<F, T>(&mut self, f: F) -> connection::Result<Option<T>>
+    where
+        F: FnOnce(MessageRef) -> T,
+    {
+        self.connection.recv_with(f)
+    }
 }
 
 impl SendMessage for Session {
@@ -240,6 +738,26 @@ impl GetPeerAddr for Session {
     }
 }
 
+impl ConfigureLineLimits for Session {
+    fn line_limits(&self) -> LineLimits {
+        self.connection.line_limits()
+    }
+
+    fn set_line_limits(&mut self, limits: LineLimits) {
+        self.connection.set_line_limits(limits)
+    }
+}
+
+impl ConfigureDecoding for Session {
+    fn decoding_policy(&self) -> DecodingPolicy {
+        self.connection.decoding_policy()
+    }
+
+    fn set_decoding_policy(&mut self, policy: DecodingPolicy) {
+        self.connection.set_decoding_policy(policy)
+    }
+}
+
 impl ConnectionPrivate for Session {
     fn mio_registerable(&self) -> &mio::event::Evented {
         self.connection.mio_registerable()
@@ -252,4 +770,8 @@ impl ConnectionPrivate for Session {
     fn mio_poll_opts(&self) -> mio::PollOpt {
         self.connection.mio_poll_opts()
     }
+
+    fn process_mio_event(&mut self, readiness: mio::Ready) -> connection::Result<()> {
+        self.connection.process_mio_event(readiness)
+    }
 }
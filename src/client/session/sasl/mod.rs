@@ -0,0 +1,477 @@
+//! SASL authentication during registration.
+//!
+//! Once the `sasl` capability has been enabled, and before capability negotiation ends, the client
+//! names a mechanism with `AUTHENTICATE`, and the client and server exchange base64-encoded
+//! `AUTHENTICATE` messages until the server reports success or failure with a numeric reply
+//! between 900 and 908. Payloads are sent in pieces of at most 400 bytes; a payload whose last
+//! piece is exactly 400 bytes long is followed by `AUTHENTICATE +`, which also stands for an empty
+//! payload. The client may abort the exchange with `AUTHENTICATE *`.
+//!
+//! See the [SASL specification] for details. The `PLAIN`, `EXTERNAL`, `SCRAM-SHA-256`, and
+//! `SCRAM-SHA-1` mechanisms are provided, and others may be added by implementing
+//! [`SaslMechanism`].
+//!
+//! [SASL specification]: <https://ircv3.net/specs/extensions/sasl-3.1>
+//! [`SaslMechanism`]: trait.SaslMechanism.html
+
+pub(crate) use self::scram::Scram;
+pub(crate) use self::scram::ScramAlgorithm;
+use Message;
+use base64;
+use client;
+use client::ErrorKind;
+use client::Result;
+use message;
+use message::Command;
+use message::MessageBuilder;
+use message::numeric::ERR_NICKLOCKED;
+use message::numeric::ERR_SASLABORTED;
+use message::numeric::ERR_SASLALREADY;
+use message::numeric::ERR_SASLFAIL;
+use message::numeric::ERR_SASLTOOLONG;
+use message::numeric::RPL_LOGGEDIN;
+use message::numeric::RPL_LOGGEDOUT;
+use message::numeric::RPL_SASLMECHS;
+use message::numeric::RPL_SASLSUCCESS;
+use message::numeric::RPL_WELCOME;
+use std::borrow::Cow;
+use std::fmt;
+use std::mem;
+use string_cache::DefaultAtom as CachedString;
+
+mod scram;
+mod tests;
+
+/// The maximum length of a piece of an `AUTHENTICATE` payload.
+const PAYLOAD_PIECE_MAX_LEN: usize = 400;
+
+/// A SASL mechanism with which a session may authenticate.
+///
+/// The session base64-encodes and decodes the mechanism's messages, and divides them into pieces,
+/// as the IRC protocol requires; the mechanism deals only in whole messages. The server's first
+/// challenge, which begins the exchange, is empty.
+pub trait SaslMechanism: fmt::Debug + Send {
+    /// Returns the mechanism's name, such as `PLAIN`, as sent with `AUTHENTICATE` and listed in
+    /// the value of the `sasl` capability.
+    fn name(&self) -> &str;
+
+    /// Returns the client's response to the given challenge from the server.
+    ///
+    /// If the challenge is invalid, or the server fails to authenticate itself where the mechanism
+    /// requires it to, this must return an error, preferably of kind [`ErrorKind::SaslFailed`];
+    /// the session then aborts authentication.
+    ///
+    /// [`ErrorKind::SaslFailed`]: ../enum.ErrorKind.html#variant.SaslFailed
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>>;
+
+    /// Called when the server reports that authentication has succeeded. Must return an error if
+    /// the exchange is incomplete, as where the server has yet to authenticate itself, in which
+    /// case the session considers authentication to have failed.
+    fn confirm_success(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns a boxed copy of the mechanism, which allows a [`SessionBuilder`] to be cloned. The
+    /// mechanism will not have been used.
+    ///
+    /// [`SessionBuilder`]: struct.SessionBuilder.html
+    fn box_clone(&self) -> Box<SaslMechanism>;
+}
+
+impl Clone for Box<SaslMechanism> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// An account name and password with which to authenticate. The password is not shown in the
+/// `Debug` representation.
+#[derive(Clone)]
+pub(crate) struct Credentials {
+    account: CachedString,
+    password: String,
+}
+
+/// The `PLAIN` mechanism, with which the client sends an account name and password.
+#[derive(Clone, Debug)]
+pub(crate) struct Plain {
+    credentials: Credentials,
+}
+
+/// The `EXTERNAL` mechanism, with which the server authenticates the client by credentials
+/// established outside of IRC, such as a TLS client certificate.
+#[derive(Clone, Debug)]
+pub(crate) struct External;
+
+/// The state of a session's SASL authentication.
+#[derive(Debug)]
+pub(crate) struct Authenticator {
+    candidates: Vec<Box<SaslMechanism>>,
+    mechanism: Option<Box<SaslMechanism>>,
+    state: State,
+    challenge: Vec<u8>,
+    is_challenged: bool,
+    abort_reason: Option<Cow<'static, str>>,
+    account: Option<String>,
+    server_mechanisms: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// Authentication has not begun.
+    Idle,
+
+    /// The mechanism has been named, and challenges and responses are being exchanged.
+    Exchanging,
+
+    /// The client has aborted authentication, and the server's acknowledgement is awaited.
+    Aborting,
+
+    /// Authentication has succeeded.
+    Succeeded,
+
+    /// Authentication has failed.
+    Failed,
+}
+
+impl Credentials {
+    pub(crate) fn new(account: CachedString, password: String) -> Self {
+        Credentials { account, password }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("account", &self.account)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Plain {
+    pub(crate) fn new(credentials: Credentials) -> Self {
+        Plain { credentials }
+    }
+}
+
+impl SaslMechanism for Plain {
+    fn name(&self) -> &str {
+        "PLAIN"
+    }
+
+    /// Returns the mechanism's sole message, which consists of an authorization identity, here
+    /// empty so that the server derives it from the account, the account name, and the password,
+    /// separated by NUL bytes.
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        ensure!(challenge.is_empty(), unexpected_challenge());
+
+        let account = self.credentials.account.as_bytes();
+        let password = self.credentials.password.as_bytes();
+
+        let mut payload = Vec::with_capacity(account.len() + password.len() + 2);
+        payload.push(b'\0');
+        payload.extend_from_slice(account);
+        payload.push(b'\0');
+        payload.extend_from_slice(password);
+        Ok(payload)
+    }
+
+    fn box_clone(&self) -> Box<SaslMechanism> {
+        Box::new(self.clone())
+    }
+}
+
+impl SaslMechanism for External {
+    fn name(&self) -> &str {
+        "EXTERNAL"
+    }
+
+    /// Returns an empty message, which asks the server to derive the authorization identity from
+    /// the external credentials.
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        ensure!(challenge.is_empty(), unexpected_challenge());
+
+        Ok(Vec::new())
+    }
+
+    fn box_clone(&self) -> Box<SaslMechanism> {
+        Box::new(self.clone())
+    }
+}
+
+impl Authenticator {
+    /// Creates an authenticator that will use the first of the given mechanisms that the server
+    /// supports.
+    pub(crate) fn new(candidates: Vec<Box<SaslMechanism>>) -> Self {
+        Authenticator {
+            candidates,
+            mechanism: None,
+            state: State::Idle,
+            challenge: Vec::new(),
+            is_challenged: false,
+            abort_reason: None,
+            account: None,
+            server_mechanisms: None,
+        }
+    }
+
+    /// Returns whether authentication has begun.
+    pub(crate) fn is_started(&self) -> bool {
+        self.state != State::Idle
+    }
+
+    /// Returns whether authentication has succeeded or failed.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.state == State::Succeeded || self.state == State::Failed
+    }
+
+    /// Returns the account as which the server has reported the client to be logged in, if any.
+    pub(crate) fn account(&self) -> Option<&str> {
+        self.account.as_ref().map(|s| &s[..])
+    }
+
+    /// Begins authentication, given the value with which the server advertised the `sasl`
+    /// capability, which lists the mechanisms it supports, if it has one. If it has none, the
+    /// first mechanism is attempted.
+    pub(crate) fn start<Msg>(&mut self, cap_value: Option<&str>) -> Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        if self.candidates.is_empty() {
+            return self.fail("no SASL mechanism was configured");
+        }
+
+        self.start_next(cap_value)
+    }
+
+    /// Names the first of the remaining mechanisms that is among the given comma-separated
+    /// mechanisms that the server supports, if they are known, or else the first of all.
+    fn start_next<Msg>(&mut self, supported: Option<&str>) -> Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        let index = match supported {
+            Some(advertised) => {
+                let index = self.candidates.iter().position(|mechanism| {
+                    advertised
+                        .split(',')
+                        .any(|name| name.eq_ignore_ascii_case(mechanism.name()))
+                });
+
+                match index {
+                    Some(index) => index,
+                    None => {
+                        let names = self.candidates
+                            .iter()
+                            .map(|mechanism| mechanism.name())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+
+                        return self.fail(format!(
+                            "the server supports none of the mechanisms {}, but only {}",
+                            names,
+                            advertised
+                        ));
+                    }
+                }
+            }
+            None => 0,
+        };
+
+        let mechanism = self.candidates.remove(index);
+        let msg = MessageBuilder::new(Command::Authenticate)
+            .param(mechanism.name())
+            .build()?;
+
+        self.mechanism = Some(mechanism);
+        self.state = State::Exchanging;
+        self.is_challenged = false;
+
+        Ok(vec![msg])
+    }
+
+    /// Fails authentication because it could not take place at all, as where the server does not
+    /// support SASL.
+    pub(crate) fn fail_unavailable<Msg>(&mut self) -> Result<Vec<Msg>> {
+        self.fail("the server does not support SASL")
+    }
+
+    /// Updates the authentication according to the given message from the server, returning the
+    /// messages to be sent in response. Returns a `SaslFailed` error if authentication fails.
+    pub(crate) fn handle<Msg>(&mut self, msg: &Msg) -> Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        match msg.numeric() {
+            Some(RPL_LOGGEDIN) => {
+                self.account = msg.param(2).map(|s| String::from_utf8_lossy(s).into_owned());
+                return Ok(Vec::new());
+            }
+            Some(RPL_LOGGEDOUT) => {
+                self.account = None;
+                return Ok(Vec::new());
+            }
+            _ => {}
+        }
+
+        if self.is_finished() {
+            return Ok(Vec::new());
+        }
+
+        if msg.command() == Command::Authenticate {
+            return match msg.param(0) {
+                Some(piece) if self.state == State::Exchanging => self.handle_challenge(piece),
+                _ => Ok(Vec::new()),
+            };
+        }
+
+        match msg.numeric() {
+            Some(RPL_SASLMECHS) => {
+                self.server_mechanisms = msg.param(1)
+                    .map(|s| String::from_utf8_lossy(s).into_owned());
+                Ok(Vec::new())
+            }
+            Some(RPL_SASLSUCCESS) | Some(ERR_SASLALREADY) | Some(ERR_NICKLOCKED) |
+            Some(ERR_SASLFAIL) | Some(ERR_SASLTOOLONG) | Some(ERR_SASLABORTED) |
+            Some(RPL_WELCOME) if self.state == State::Aborting => {
+                let reason = self.abort_reason.take().unwrap_or_default();
+                self.fail(reason)
+            }
+            Some(RPL_SASLSUCCESS) => {
+                let confirmation = match self.mechanism {
+                    Some(ref mechanism) => mechanism.confirm_success(),
+                    None => Ok(()),
+                };
+
+                match confirmation {
+                    Ok(()) => {
+                        self.state = State::Succeeded;
+                        Ok(Vec::new())
+                    }
+                    Err(err) => self.fail(failure_desc(err)),
+                }
+            }
+            Some(ERR_SASLALREADY) => {
+                self.state = State::Succeeded;
+                Ok(Vec::new())
+            }
+            Some(ERR_SASLFAIL) if !self.is_challenged && !self.candidates.is_empty() => {
+                // The server rejected the mechanism before the exchange began, as where it does
+                // not support the mechanism, having not listed those it does. It follows
+                // `RPL_SASLMECHS` with this reply, so the mechanisms it lists there are known.
+                let supported = self.server_mechanisms.take();
+                let result = self.start_next(supported.as_ref().map(|s| &s[..]));
+                self.server_mechanisms = supported;
+                result
+            }
+            Some(ERR_NICKLOCKED) | Some(ERR_SASLFAIL) | Some(ERR_SASLTOOLONG) |
+            Some(ERR_SASLABORTED) => {
+                let text = msg.trailing().map(String::from_utf8_lossy).unwrap_or_default();
+                let desc = match self.server_mechanisms {
+                    Some(ref mechanisms) => {
+                        format!("{} (the server supports {})", text, mechanisms)
+                    }
+                    None => text.into_owned(),
+                };
+
+                self.fail(desc)
+            }
+            Some(RPL_WELCOME) => {
+                self.fail("the server completed registration without authenticating the client")
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Collects a piece of a challenge from the server, and, once the challenge is complete,
+    /// returns the mechanism's response, or aborts authentication if the mechanism rejects the
+    /// challenge.
+    fn handle_challenge<Msg>(&mut self, piece: &[u8]) -> Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        self.is_challenged = true;
+
+        if piece != b"+" {
+            self.challenge.extend_from_slice(piece);
+
+            if piece.len() == PAYLOAD_PIECE_MAX_LEN {
+                // The challenge continues in the next piece.
+                return Ok(Vec::new());
+            }
+        }
+
+        let challenge = match base64::decode(&mem::replace(&mut self.challenge, Vec::new())) {
+            Ok(challenge) => challenge,
+            Err(_) => return self.abort("the server sent a challenge that is not valid base64"),
+        };
+
+        let response = match self.mechanism {
+            Some(ref mut mechanism) => mechanism.respond(&challenge),
+            None => return Ok(Vec::new()),
+        };
+
+        match response {
+            Ok(response) => Ok(authenticate_payload(&response)?),
+            Err(err) => self.abort(failure_desc(err)),
+        }
+    }
+
+    /// Aborts authentication, which fails for the given reason once the server acknowledges it.
+    fn abort<Msg, S>(&mut self, reason: S) -> Result<Vec<Msg>>
+    where
+        Msg: Message,
+        S: Into<Cow<'static, str>>,
+    {
+        self.state = State::Aborting;
+        self.abort_reason = Some(reason.into());
+
+        Ok(vec![MessageBuilder::new(Command::Authenticate).param("*").build()?])
+    }
+
+    fn fail<Msg, S>(&mut self, desc: S) -> Result<Vec<Msg>>
+    where
+        S: Into<Cow<'static, str>>,
+    {
+        self.state = State::Failed;
+        bail!(ErrorKind::SaslFailed(desc.into()))
+    }
+}
+
+/// Constructs the `AUTHENTICATE` messages that send the given payload, base64-encoded and divided
+/// into pieces of at most 400 bytes.
+fn authenticate_payload<Msg>(payload: &[u8]) -> message::Result<Vec<Msg>>
+where
+    Msg: Message,
+{
+    let encoded = base64::encode(payload);
+    let mut msgs = encoded
+        .as_bytes()
+        .chunks(PAYLOAD_PIECE_MAX_LEN)
+        .map(|piece| {
+            MessageBuilder::new(Command::Authenticate)
+                .param(piece)
+                .build()
+        })
+        .collect::<message::Result<Vec<Msg>>>()?;
+
+    if encoded.len() % PAYLOAD_PIECE_MAX_LEN == 0 {
+        msgs.push(MessageBuilder::new(Command::Authenticate).param("+").build()?);
+    }
+
+    Ok(msgs)
+}
+
+/// Returns the description of a failure of a mechanism, without the `SaslFailed` error's own
+/// preamble.
+fn failure_desc(err: client::Error) -> Cow<'static, str> {
+    match err {
+        client::Error(ErrorKind::SaslFailed(desc), _) => desc,
+        err => err.to_string().into(),
+    }
+}
+
+fn unexpected_challenge() -> ErrorKind {
+    ErrorKind::SaslFailed("the server sent an unexpected challenge".into())
+}
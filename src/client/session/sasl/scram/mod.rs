@@ -0,0 +1,307 @@
+//! The `SCRAM-SHA-256` and `SCRAM-SHA-1` SASL mechanisms.
+//!
+//! With SCRAM, the client and the server each prove that they know the password without sending
+//! it: the client sends its account name and a random nonce; the server replies with a nonce of
+//! its own, which extends the client's, and with the salt and iteration count with which it
+//! derived the password's verifiers; the client then sends a proof of the password, and the server
+//! replies with a signature, which the client checks to authenticate the server in turn.
+//!
+//! Channel binding is not supported, and passwords are not normalized with SASLprep, which matters
+//! only for passwords that are not ASCII. See [RFC 5802] and [RFC 7677] for details.
+//!
+//! [RFC 5802]: <https://tools.ietf.org/html/rfc5802>
+//! [RFC 7677]: <https://tools.ietf.org/html/rfc7677>
+
+use super::Credentials;
+use super::SaslMechanism;
+use base64;
+use client::ErrorKind;
+use client::Result;
+use ring::constant_time;
+use ring::digest;
+use ring::hmac;
+use ring::pbkdf2;
+use ring::rand::SecureRandom;
+use ring::rand::SystemRandom;
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+
+mod tests;
+
+/// The header with which the client's messages begin, which says that the client does not support
+/// channel binding and that the authorization identity is to be derived from the account.
+const GS2_HEADER: &str = "n,,";
+
+/// The length, in bytes, of the random data from which the client's nonce is made.
+const NONCE_LEN: usize = 18;
+
+/// The greatest iteration count that the client accepts from the server, which keeps a malicious
+/// server from making the client spend minutes deriving the password's verifiers.
+const ITERATION_COUNT_MAX: u32 = 1_000_000;
+
+/// A SCRAM mechanism, which authenticates with an account name and password.
+#[derive(Clone)]
+pub(crate) struct Scram {
+    algorithm: ScramAlgorithm,
+    credentials: Credentials,
+    state: State,
+}
+
+/// The hash function with which a SCRAM mechanism is used.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ScramAlgorithm {
+    Sha256,
+    Sha1,
+}
+
+#[derive(Clone)]
+enum State {
+    /// The exchange has not begun.
+    Initial,
+
+    /// The client's first message has been sent.
+    ClientFirstSent {
+        client_nonce: String,
+        client_first_bare: String,
+    },
+
+    /// The client's final message, with its proof of the password, has been sent, and the
+    /// server's signature is awaited.
+    ClientFinalSent { server_signature: Vec<u8> },
+
+    /// The server's signature has been verified.
+    Verified,
+}
+
+impl Scram {
+    pub(crate) fn new(algorithm: ScramAlgorithm, credentials: Credentials) -> Self {
+        Scram {
+            algorithm,
+            credentials,
+            state: State::Initial,
+        }
+    }
+
+    /// Returns the client's first message, which carries the given nonce.
+    fn client_first(&mut self, client_nonce: String) -> Vec<u8> {
+        let client_first_bare = format!(
+            "n={},r={}",
+            saslname(&self.credentials.account),
+            client_nonce
+        );
+        let msg = format!("{}{}", GS2_HEADER, client_first_bare);
+
+        self.state = State::ClientFirstSent {
+            client_nonce,
+            client_first_bare,
+        };
+
+        msg.into_bytes()
+    }
+
+    /// Returns the client's final message, in reply to the server's first message, and the
+    /// signature with which the server's final message must answer it.
+    fn client_final(
+        &self,
+        client_nonce: &str,
+        client_first_bare: &str,
+        server_first: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        let server_first = str::from_utf8(server_first)
+            .map_err(|_| failed("the server's first message is not valid UTF-8"))?;
+
+        ensure!(
+            attribute(server_first, "m").is_none(),
+            failed("the server requires an unsupported SCRAM extension")
+        );
+
+        let nonce = match attribute(server_first, "r") {
+            Some(nonce) if nonce.len() > client_nonce.len() && nonce.starts_with(client_nonce) => {
+                nonce
+            }
+            _ => bail!(failed("the server's nonce does not extend the client's")),
+        };
+
+        let salt = attribute(server_first, "s")
+            .and_then(|salt| base64::decode(salt).ok())
+            .ok_or_else(|| failed("the server's salt is missing or invalid"))?;
+
+        let iterations = attribute(server_first, "i")
+            .and_then(|i| i.parse::<u32>().ok())
+            .ok_or_else(|| failed("the server's iteration count is missing or invalid"))?;
+
+        ensure!(
+            iterations > 0 && iterations <= ITERATION_COUNT_MAX,
+            failed(format!(
+                "the server's iteration count, {}, is not between 1 and {}",
+                iterations,
+                ITERATION_COUNT_MAX
+            ))
+        );
+
+        let digest_alg = self.algorithm.digest_alg();
+
+        let mut salted_password = vec![0; digest_alg.output_len];
+        pbkdf2::derive(
+            digest_alg,
+            iterations,
+            &salt,
+            self.credentials.password.as_bytes(),
+            &mut salted_password,
+        );
+
+        let client_key = hmac_sign(digest_alg, &salted_password, b"Client Key");
+        let stored_key = digest::digest(digest_alg, &client_key);
+        let server_key = hmac_sign(digest_alg, &salted_password, b"Server Key");
+
+        let client_final_without_proof =
+            format!("c={},r={}", base64::encode(GS2_HEADER), nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            client_first_bare,
+            server_first,
+            client_final_without_proof
+        );
+
+        let client_signature = hmac_sign(digest_alg, stored_key.as_ref(), auth_message.as_bytes());
+        let server_signature = hmac_sign(digest_alg, &server_key, auth_message.as_bytes());
+
+        let proof = client_key
+            .iter()
+            .zip(&client_signature)
+            .map(|(key_byte, signature_byte)| key_byte ^ signature_byte)
+            .collect::<Vec<u8>>();
+
+        let client_final = format!("{},p={}", client_final_without_proof, base64::encode(&proof));
+
+        Ok((client_final.into_bytes(), server_signature))
+    }
+}
+
+impl SaslMechanism for Scram {
+    fn name(&self) -> &str {
+        match self.algorithm {
+            ScramAlgorithm::Sha256 => "SCRAM-SHA-256",
+            ScramAlgorithm::Sha1 => "SCRAM-SHA-1",
+        }
+    }
+
+    fn respond(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        match self.state.clone() {
+            State::Initial => {
+                ensure!(challenge.is_empty(), failed("the server sent an unexpected challenge"));
+
+                Ok(self.client_first(random_nonce()?))
+            }
+            State::ClientFirstSent {
+                client_nonce,
+                client_first_bare,
+            } => {
+                let (client_final, server_signature) =
+                    self.client_final(&client_nonce, &client_first_bare, challenge)?;
+
+                self.state = State::ClientFinalSent { server_signature };
+
+                Ok(client_final)
+            }
+            State::ClientFinalSent { server_signature } => {
+                verify_server_final(challenge, &server_signature)?;
+
+                self.state = State::Verified;
+
+                Ok(Vec::new())
+            }
+            State::Verified => bail!(failed("the server sent an unexpected challenge")),
+        }
+    }
+
+    fn confirm_success(&self) -> Result<()> {
+        match self.state {
+            State::Verified => Ok(()),
+            _ => bail!(failed(
+                "the server reported success without proving that it knows the password"
+            )),
+        }
+    }
+
+    fn box_clone(&self) -> Box<SaslMechanism> {
+        Box::new(Scram::new(self.algorithm, self.credentials.clone()))
+    }
+}
+
+impl fmt::Debug for Scram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The state is omitted, as the server's expected signature would allow the password to be
+        // guessed offline.
+        f.debug_struct("Scram")
+            .field("algorithm", &self.algorithm)
+            .field("credentials", &self.credentials)
+            .finish()
+    }
+}
+
+impl ScramAlgorithm {
+    fn digest_alg(self) -> &'static digest::Algorithm {
+        match self {
+            ScramAlgorithm::Sha256 => &digest::SHA256,
+            ScramAlgorithm::Sha1 => &digest::SHA1,
+        }
+    }
+}
+
+/// Checks the signature in the server's final message, or reports the error that the server sent
+/// instead.
+fn verify_server_final(server_final: &[u8], server_signature: &[u8]) -> Result<()> {
+    let server_final = str::from_utf8(server_final)
+        .map_err(|_| failed("the server's final message is not valid UTF-8"))?;
+
+    if let Some(err) = attribute(server_final, "e") {
+        bail!(failed(format!("the server rejected the client's proof: {}", err)));
+    }
+
+    let signature = attribute(server_final, "v")
+        .and_then(|signature| base64::decode(signature).ok())
+        .ok_or_else(|| failed("the server's signature is missing or invalid"))?;
+
+    constant_time::verify_slices_are_equal(&signature, server_signature)
+        .map_err(|_| failed("the server's signature is incorrect"))?;
+
+    Ok(())
+}
+
+/// Returns the value of the attribute with the given name in the given SCRAM message, which
+/// consists of comma-separated attributes of the form `name=value`.
+fn attribute<'a>(msg: &'a str, name: &str) -> Option<&'a str> {
+    msg.split(',')
+        .find(|attr| attr.starts_with(name) && attr[name.len()..].starts_with('='))
+        .map(|attr| &attr[name.len() + 1..])
+}
+
+/// Escapes an account name for use in a SCRAM message, in which `,` and `=` are reserved.
+fn saslname(account: &str) -> String {
+    account.replace('=', "=3D").replace(',', "=2C")
+}
+
+fn random_nonce() -> Result<String> {
+    let mut bytes = [0; NONCE_LEN];
+
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| failed("no random data could be obtained for the client's nonce"))?;
+
+    Ok(base64::encode(&bytes))
+}
+
+fn hmac_sign(digest_alg: &'static digest::Algorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::SigningKey::new(digest_alg, key), data)
+        .as_ref()
+        .to_owned()
+}
+
+fn failed<S>(desc: S) -> ErrorKind
+where
+    S: Into<Cow<'static, str>>,
+{
+    ErrorKind::SaslFailed(desc.into())
+}
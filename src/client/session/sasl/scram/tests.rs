@@ -0,0 +1,155 @@
+#![cfg(test)]
+
+use super::*;
+use client;
+
+fn scram(algorithm: ScramAlgorithm) -> Scram {
+    Scram::new(algorithm, Credentials::new("user".into(), "pencil".into()))
+}
+
+fn is_sasl_failure<T>(result: Result<T>) -> bool {
+    match result {
+        Err(client::Error(ErrorKind::SaslFailed(_), _)) => true,
+        _ => false,
+    }
+}
+
+// The exchanges are the examples given in RFC 5802 and RFC 7677.
+
+#[test]
+fn sha1_1() {
+    let mut scram = scram(ScramAlgorithm::Sha1);
+
+    assert_eq!(scram.name(), "SCRAM-SHA-1");
+    assert_eq!(
+        scram.client_first("fyko+d2lbbFgONRv9qkxdawL".into()),
+        &b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"[..]
+    );
+    assert_eq!(
+        scram
+            .respond(b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096")
+            .unwrap(),
+        &b"c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="[..]
+    );
+    assert!(scram.confirm_success().is_err());
+    assert!(
+        scram
+            .respond(b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ=")
+            .unwrap()
+            .is_empty()
+    );
+    assert!(scram.confirm_success().is_ok());
+}
+
+#[test]
+fn sha256_1() {
+    let mut scram = scram(ScramAlgorithm::Sha256);
+
+    assert_eq!(scram.name(), "SCRAM-SHA-256");
+    assert_eq!(
+        scram.client_first("rOprNGfwEbeRWgbNEkqO".into()),
+        &b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO"[..]
+    );
+    assert_eq!(
+        scram
+            .respond(
+                b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+                  s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            )
+            .unwrap(),
+        &b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+           p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="[..]
+    );
+    assert!(
+        scram
+            .respond(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+            .unwrap()
+            .is_empty()
+    );
+    assert!(scram.confirm_success().is_ok());
+    assert!(is_sasl_failure(scram.respond(b"")));
+}
+
+#[test]
+fn random_nonce_1() {
+    let mut scram = scram(ScramAlgorithm::Sha256);
+
+    let client_first = String::from_utf8(scram.respond(b"").unwrap()).unwrap();
+    let nonce = &client_first["n,,n=user,r=".len()..];
+
+    assert!(client_first.starts_with("n,,n=user,r="));
+    assert_eq!(base64::decode(nonce).unwrap().len(), NONCE_LEN);
+    assert!(!nonce.contains(','));
+}
+
+#[test]
+fn bad_server_signature_1() {
+    let mut scram = scram(ScramAlgorithm::Sha1);
+
+    scram.client_first("fyko+d2lbbFgONRv9qkxdawL".into());
+    scram
+        .respond(b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096")
+        .unwrap();
+
+    assert!(is_sasl_failure(scram.respond(b"v=AAAAAAAAAAAAAAAAAAAAAAAAAAA=")));
+    assert!(is_sasl_failure(scram.confirm_success()));
+}
+
+#[test]
+fn bad_server_first_1() {
+    for server_first in &[
+        &b"r=fyko+d2lbbFgONRv9qkxdawL,s=QSXCR+Q6sek8bf92,i=4096"[..],
+        b"r=someone-elses-nonce,s=QSXCR+Q6sek8bf92,i=4096",
+        b"r=fyko+d2lbbFgONRv9qkxdawL3rfc,s=not base64,i=4096",
+        b"r=fyko+d2lbbFgONRv9qkxdawL3rfc,s=QSXCR+Q6sek8bf92,i=0",
+        b"r=fyko+d2lbbFgONRv9qkxdawL3rfc,s=QSXCR+Q6sek8bf92,i=4000000000",
+        b"m=ext,r=fyko+d2lbbFgONRv9qkxdawL3rfc,s=QSXCR+Q6sek8bf92,i=4096",
+        b"s=QSXCR+Q6sek8bf92,i=4096",
+    ] {
+        let mut scram = scram(ScramAlgorithm::Sha1);
+
+        scram.client_first("fyko+d2lbbFgONRv9qkxdawL".into());
+
+        assert!(
+            is_sasl_failure(scram.respond(server_first)),
+            "server-first message: {:?}",
+            String::from_utf8_lossy(server_first)
+        );
+    }
+}
+
+#[test]
+fn server_error_1() {
+    let mut scram = scram(ScramAlgorithm::Sha1);
+
+    scram.client_first("fyko+d2lbbFgONRv9qkxdawL".into());
+    scram
+        .respond(b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096")
+        .unwrap();
+
+    match scram.respond(b"e=invalid-proof") {
+        Err(client::Error(ErrorKind::SaslFailed(desc), _)) => {
+            assert!(desc.contains("invalid-proof"))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn saslname_1() {
+    assert_eq!(saslname("user"), "user");
+    assert_eq!(saslname("a=b,c"), "a=3Db=2Cc");
+}
+
+#[test]
+fn debug_1() {
+    let mut scram = scram(ScramAlgorithm::Sha1);
+
+    scram.client_first("fyko+d2lbbFgONRv9qkxdawL".into());
+
+    let debug = format!("{:?}", scram);
+
+    assert!(debug.contains("user"));
+    assert!(!debug.contains("pencil"));
+    assert!(!debug.contains("fyko"));
+}
@@ -0,0 +1,336 @@
+#![cfg(test)]
+
+use super::*;
+use client;
+use message::OwnedMessage;
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+fn credentials() -> Credentials {
+    Credentials::new("alice".into(), "hunter2".into())
+}
+
+fn authenticator() -> Authenticator {
+    Authenticator::new(vec![Box::new(Plain::new(credentials()))])
+}
+
+fn handle(authenticator: &mut Authenticator, line: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let replies: Vec<OwnedMessage> = authenticator.handle(&msg(line))?;
+    Ok(replies.into_iter().map(OwnedMessage::into_bytes).collect())
+}
+
+fn authenticator_with_scram() -> Authenticator {
+    Authenticator::new(vec![Box::new(Scram::new(ScramAlgorithm::Sha256, credentials()))])
+}
+
+fn is_sasl_failure(result: Result<Vec<Vec<u8>>>) -> bool {
+    match result {
+        Err(client::Error(ErrorKind::SaslFailed(_), _)) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn plain_1() {
+    let mut authenticator = authenticator();
+
+    let start: Vec<OwnedMessage> = authenticator.start(Some("PLAIN,EXTERNAL")).unwrap();
+
+    assert_eq!(start[0].as_bytes(), b"AUTHENTICATE PLAIN");
+    assert!(authenticator.is_started());
+
+    // The payload is `\0alice\0hunter2`.
+    assert_eq!(
+        handle(&mut authenticator, b"AUTHENTICATE +").unwrap(),
+        [&b"AUTHENTICATE AGFsaWNlAGh1bnRlcjI="[..]]
+    );
+
+    assert!(
+        handle(
+            &mut authenticator,
+            b":irc.example.com 900 nick nick!user@host alice :You are now logged in as alice",
+        ).unwrap()
+            .is_empty()
+    );
+    assert!(!authenticator.is_finished());
+    assert!(
+        handle(
+            &mut authenticator,
+            b":irc.example.com 903 nick :SASL authentication successful",
+        ).unwrap()
+            .is_empty()
+    );
+    assert!(authenticator.is_finished());
+    assert_eq!(authenticator.account(), Some("alice"));
+}
+
+#[test]
+fn plain_failed_1() {
+    let mut authenticator = authenticator();
+
+    let _: Vec<OwnedMessage> = authenticator.start(None).unwrap();
+
+    handle(&mut authenticator, b"AUTHENTICATE +").unwrap();
+
+    assert!(is_sasl_failure(handle(
+        &mut authenticator,
+        b":irc.example.com 904 nick :SASL authentication failed",
+    )));
+    assert!(authenticator.is_finished());
+    assert_eq!(authenticator.account(), None);
+}
+
+#[test]
+fn plain_unsupported_1() {
+    let mut without_plain = authenticator();
+
+    let result: Result<Vec<OwnedMessage>> = without_plain.start(Some("EXTERNAL,SCRAM-SHA-256"));
+
+    assert!(result.is_err());
+    assert!(without_plain.is_finished());
+
+    let mut unstarted = authenticator();
+
+    assert!(is_sasl_failure(handle(
+        &mut unstarted,
+        b":irc.example.com 001 nick :Welcome",
+    )));
+}
+
+#[test]
+fn external_1() {
+    let mut authenticator = Authenticator::new(vec![Box::new(External)]);
+
+    let start: Vec<OwnedMessage> = authenticator.start(Some("PLAIN,EXTERNAL")).unwrap();
+
+    assert_eq!(start[0].as_bytes(), b"AUTHENTICATE EXTERNAL");
+
+    // The payload is empty.
+    assert_eq!(
+        handle(&mut authenticator, b"AUTHENTICATE +").unwrap(),
+        [&b"AUTHENTICATE +"[..]]
+    );
+
+    handle(
+        &mut authenticator,
+        b":irc.example.com 900 nick nick!user@host bot :You are now logged in as bot",
+    ).unwrap();
+    handle(&mut authenticator, b":irc.example.com 903 nick :SASL authentication successful")
+        .unwrap();
+
+    assert!(authenticator.is_finished());
+    assert_eq!(authenticator.account(), Some("bot"));
+
+    let mut without_external = Authenticator::new(vec![Box::new(External)]);
+    let result: Result<Vec<OwnedMessage>> = without_external.start(Some("PLAIN"));
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn mechanism_selection_1() {
+    let authenticator = || {
+        Authenticator::new(vec![
+            Box::new(Scram::new(ScramAlgorithm::Sha256, credentials())),
+            Box::new(Scram::new(ScramAlgorithm::Sha1, credentials())),
+            Box::new(Plain::new(credentials())),
+        ])
+    };
+
+    for &(cap_value, expected) in &[
+        (Some("PLAIN,SCRAM-SHA-1,SCRAM-SHA-256"), &b"AUTHENTICATE SCRAM-SHA-256"[..]),
+        (Some("PLAIN,SCRAM-SHA-1"), b"AUTHENTICATE SCRAM-SHA-1"),
+        (Some("EXTERNAL,PLAIN"), b"AUTHENTICATE PLAIN"),
+        (None, b"AUTHENTICATE SCRAM-SHA-256"),
+    ] {
+        let start: Vec<OwnedMessage> = authenticator().start(cap_value).unwrap();
+
+        assert_eq!(start[0].as_bytes(), expected, "advertised: {:?}", cap_value);
+    }
+
+    let mut unsupported = authenticator();
+
+    match unsupported.start::<OwnedMessage>(Some("EXTERNAL")) {
+        Err(client::Error(ErrorKind::SaslFailed(desc), _)) => {
+            assert!(desc.contains("SCRAM-SHA-256, SCRAM-SHA-1, PLAIN"))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn mechanism_fallback_1() {
+    let mut authenticator = Authenticator::new(vec![
+        Box::new(Scram::new(ScramAlgorithm::Sha256, credentials())),
+        Box::new(External),
+        Box::new(Plain::new(credentials())),
+    ]);
+
+    // Without the value of the `sasl` capability, the first mechanism is tried.
+    let start: Vec<OwnedMessage> = authenticator.start(None).unwrap();
+
+    assert_eq!(start[0].as_bytes(), b"AUTHENTICATE SCRAM-SHA-256");
+
+    // The next mechanism that the server lists is tried once the first is rejected.
+    assert!(
+        handle(
+            &mut authenticator,
+            b":irc.example.com 908 nick PLAIN :are available SASL mechanisms",
+        ).unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        handle(&mut authenticator, b":irc.example.com 904 nick :SASL authentication failed")
+            .unwrap(),
+        [&b"AUTHENTICATE PLAIN"[..]]
+    );
+    assert!(!authenticator.is_finished());
+
+    assert_eq!(
+        handle(&mut authenticator, b"AUTHENTICATE +").unwrap(),
+        [&b"AUTHENTICATE AGFsaWNlAGh1bnRlcjI="[..]]
+    );
+    handle(&mut authenticator, b":irc.example.com 903 nick :SASL authentication successful")
+        .unwrap();
+
+    assert!(authenticator.is_finished());
+}
+
+#[test]
+fn mechanism_fallback_2() {
+    let authenticator = || {
+        Authenticator::new(vec![Box::new(External), Box::new(Plain::new(credentials()))])
+    };
+
+    // Where the server does not list its mechanisms, the next of all is tried.
+    let mut unlisted = authenticator();
+    let _: Vec<OwnedMessage> = unlisted.start(None).unwrap();
+
+    assert_eq!(
+        handle(&mut unlisted, b":irc.example.com 904 nick :SASL authentication failed").unwrap(),
+        [&b"AUTHENTICATE PLAIN"[..]]
+    );
+
+    // Where it supports none of the rest, authentication fails.
+    let mut unsupported = authenticator();
+    let _: Vec<OwnedMessage> = unsupported.start(None).unwrap();
+
+    handle(&mut unsupported, b":irc.example.com 908 nick SCRAM-SHA-256 :are available").unwrap();
+
+    assert!(is_sasl_failure(handle(
+        &mut unsupported,
+        b":irc.example.com 904 nick :SASL authentication failed",
+    )));
+    assert!(unsupported.is_finished());
+
+    // Once the exchange has begun, a failure is not a rejection of the mechanism.
+    let mut challenged = authenticator();
+    let _: Vec<OwnedMessage> = challenged.start(None).unwrap();
+
+    handle(&mut challenged, b"AUTHENTICATE +").unwrap();
+
+    assert!(is_sasl_failure(handle(
+        &mut challenged,
+        b":irc.example.com 904 nick :SASL authentication failed",
+    )));
+}
+
+#[test]
+fn scram_exchange_1() {
+    let mut authenticator = authenticator_with_scram();
+
+    let start: Vec<OwnedMessage> = authenticator.start(Some("SCRAM-SHA-256")).unwrap();
+
+    assert_eq!(start[0].as_bytes(), b"AUTHENTICATE SCRAM-SHA-256");
+
+    let client_first = handle(&mut authenticator, b"AUTHENTICATE +").unwrap();
+
+    assert_eq!(client_first.len(), 1);
+    assert!(client_first[0].starts_with(b"AUTHENTICATE "));
+
+    // The server claims success without having proven that it knows the password.
+    assert!(is_sasl_failure(handle(
+        &mut authenticator,
+        b":irc.example.com 903 nick :SASL authentication successful",
+    )));
+}
+
+#[test]
+fn abort_1() {
+    let mut authenticator = authenticator_with_scram();
+
+    let _: Vec<OwnedMessage> = authenticator.start(None).unwrap();
+
+    handle(&mut authenticator, b"AUTHENTICATE +").unwrap();
+
+    // The server's first message must carry a nonce that extends the client's.
+    let server_first = base64::encode(b"r=nonce,s=QSXCR+Q6sek8bf92,i=4096");
+    let line = format!("AUTHENTICATE {}", server_first);
+
+    assert_eq!(
+        handle(&mut authenticator, line.as_bytes()).unwrap(),
+        [&b"AUTHENTICATE *"[..]]
+    );
+    assert!(!authenticator.is_finished());
+
+    match handle(&mut authenticator, b":irc.example.com 906 nick :SASL authentication aborted") {
+        Err(client::Error(ErrorKind::SaslFailed(desc), _)) => assert!(desc.contains("nonce")),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert!(authenticator.is_finished());
+}
+
+#[test]
+fn challenge_pieces_1() {
+    let mut authenticator = authenticator_with_scram();
+
+    let _: Vec<OwnedMessage> = authenticator.start(None).unwrap();
+
+    handle(&mut authenticator, b"AUTHENTICATE +").unwrap();
+
+    // A challenge of 400 bytes, once encoded, is followed by `AUTHENTICATE +`.
+    let server_first = base64::encode(&vec![b'x'; 300]);
+    let line = format!("AUTHENTICATE {}", server_first);
+
+    assert!(handle(&mut authenticator, line.as_bytes()).unwrap().is_empty());
+    assert_eq!(
+        handle(&mut authenticator, b"AUTHENTICATE +").unwrap(),
+        [&b"AUTHENTICATE *"[..]]
+    );
+}
+
+#[test]
+fn payload_pieces_1() {
+    for &(payload_len, expected_pieces) in &[(0, 1), (3, 1), (300, 2), (301, 2), (600, 3)] {
+        let msgs: Vec<OwnedMessage> = authenticate_payload(&vec![0; payload_len]).unwrap();
+        let pieces = msgs.iter()
+            .map(|msg| msg.param(0).unwrap().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(pieces.len(), expected_pieces, "payload length: {}", payload_len);
+
+        assert!(pieces.iter().all(|piece| piece.len() <= PAYLOAD_PIECE_MAX_LEN));
+
+        if base64::encode(&vec![0; payload_len]).len() % PAYLOAD_PIECE_MAX_LEN == 0 {
+            assert_eq!(pieces.last().unwrap(), b"+");
+        }
+
+        let encoded = pieces
+            .iter()
+            .filter(|piece| &piece[..] != b"+")
+            .flat_map(|piece| piece.iter().cloned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(base64::decode(&encoded).unwrap(), vec![0; payload_len]);
+    }
+}
+
+#[test]
+fn credentials_debug_1() {
+    let debug = format!("{:?}", authenticator());
+
+    assert!(debug.contains("alice"));
+    assert!(!debug.contains("hunter2"));
+}
@@ -0,0 +1,78 @@
+#![cfg(test)]
+
+use super::*;
+use connection::PlaintextConnection;
+use std::borrow::Cow;
+use std::net::TcpListener;
+use std::net::TcpStream;
+
+/// Registers a session named `alice` over a loopback connection, returning the session and the
+/// server's end of the connection.
+fn session() -> (Session, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let connection = PlaintextConnection::from_addr(listener.local_addr().unwrap()).unwrap();
+    let server = listener.accept().unwrap().0;
+
+    let session = build()
+        .connection(connection)
+        .nickname("alice")
+        .username("alice")
+        .try_into_session()
+        .unwrap();
+
+    (session, server)
+}
+
+fn handle(session: &mut Session, line: &[u8]) {
+    let msg = OwnedMessage::try_from(Cow::Borrowed(line)).unwrap();
+    session.handle_incoming(&msg).unwrap();
+}
+
+#[test]
+fn own_prefix_len_1() {
+    let (mut session, _server) = session();
+
+    assert_eq!(
+        session.own_prefix_len(),
+        "alice!~alice@".len() + cmd::HOST_MAX_LEN
+    );
+
+    handle(&mut session, b":Alice!~alice@example.com NICK :alice_in_wonderland");
+
+    assert_eq!(session.nickname().as_str(), "alice_in_wonderland");
+    assert_eq!(
+        session.own_prefix_len(),
+        "alice_in_wonderland!~alice@".len() + cmd::HOST_MAX_LEN
+    );
+
+    // Others' changes of nickname are not the session's.
+    handle(&mut session, b":bob!bob@example.com NICK :robert_the_magnificent");
+
+    assert_eq!(session.nickname().as_str(), "alice_in_wonderland");
+}
+
+#[test]
+fn own_prefix_len_2() {
+    let (mut session, _server) = session();
+
+    handle(
+        &mut session,
+        b":irc.example.com 001 alice :Welcome to the Example IRC Network alice!~alice@example.com",
+    );
+
+    assert_eq!(session.own_prefix_len(), "alice!~alice@example.com".len());
+
+    handle(
+        &mut session,
+        b":irc.example.com 396 alice users.example.net :is now your displayed host",
+    );
+
+    assert_eq!(session.own_prefix_len(), "alice!~alice@users.example.net".len());
+
+    handle(&mut session, b":alice!~alice@users.example.net NICK :alice_in_wonderland");
+
+    assert_eq!(
+        session.own_prefix_len(),
+        "alice_in_wonderland!~alice@users.example.net".len()
+    );
+}
@@ -8,20 +8,24 @@ use super::Reaction;
 use super::Result;
 use super::ResultExt;
 use super::SessionId;
+use super::session;
 use super::session::Session;
 use super::session::TryIntoSession;
 use Message;
 use connection;
-use connection::Connection;
 use connection::ConnectionPrivate;
-use connection::GenericConnection;
 use connection::ReceiveMessage;
 use connection::SendMessage;
+use message;
+use message::Command;
+use message::cmd;
+use message::ctcp;
 use mio;
 use smallvec::SmallVec;
 use std;
 use std::io;
 use std::sync::mpsc;
+use std::time::SystemTime;
 use util;
 use util::irc::pong_from_ping;
 use uuid::Uuid;
@@ -40,6 +44,7 @@ where
     mpsc_receiver: mpsc::Receiver<Action<Msg>>,
     mpsc_registration: mio::Registration,
     handle_prototype: ClientHandle<Msg>,
+    ctcp_autoreply: bool,
 }
 
 #[derive(Debug)]
@@ -47,7 +52,7 @@ pub(crate) struct SessionEntry<Msg>
 where
     Msg: Message,
 {
-    inner: Session<GenericConnection>,
+    inner: Session,
     output_queue: SmallVec<[Msg; 3]>,
     is_writable: bool,
 }
@@ -84,6 +89,36 @@ where
             mpsc_receiver,
             mpsc_registration,
             handle_prototype,
+            ctcp_autoreply: false,
+        }
+    }
+
+    /// Sets whether the client automatically replies to CTCP `CLIENTINFO`, `PING`, `TIME`, and
+    /// `VERSION` queries, as most IRC clients do. Queries so replied to are not passed to the
+    /// message handler. This is disabled by default.
+    pub fn ctcp_autoreply(mut self, enabled: bool) -> Self {
+        self.ctcp_autoreply = enabled;
+        self
+    }
+
+    fn process_event<MsgHandler>(
+        &mut self,
+        token: mio::Token,
+        readiness: mio::Ready,
+        msg_handler: &MsgHandler,
+    ) where
+        MsgHandler: Fn(&MessageContext<Msg>, Result<Msg>) -> Reaction<Msg>,
+    {
+        match self.mk_event_ctx_id_from_mio_token(token) {
+            EventContextId::MpscQueue => process_mpsc_queue(self),
+            EventContextId::Session(session_id) => process_session_event(
+                readiness,
+                &mut self.sessions[session_id.index],
+                session_id,
+                msg_handler,
+                &self.handle_prototype,
+                self.ctcp_autoreply,
+            ),
         }
     }
 
@@ -128,7 +163,7 @@ where
 
     fn add_session<Sess>(&mut self, session: Sess) -> Result<SessionId>
     where
-        Sess: TryIntoSession<Conn>,
+        Sess: TryIntoSession,
     {
         let index = self.sessions.len();
 
@@ -182,19 +217,7 @@ where
             let _event_qty = poll.poll(&mut events, None)?;
 
             for event in &events {
-                match self.mk_event_ctx_id_from_mio_token(event.token()) {
-                    EventContextId::MpscQueue => process_mpsc_queue(&mut self),
-                    EventContextId::Session(session_id) => {
-                        let &SessionEntry { ref mut inner, ref mut output_queue, ref mut is_writable } = self.sessions[session_id.index];
-                        session.inner.process_mio_event(
-                            event.readiness(),
-                            session,
-                            session_id,
-                            &msg_handler,
-                            &self.handle_prototype,
-                        )
-                    }
-                }
+                self.process_event(event.token(), event.readiness(), &msg_handler);
             }
         }
     }
@@ -202,14 +225,25 @@ where
 
 fn process_session_event<Msg, MsgHandler>(
     readiness: mio::Ready,
+    session: &mut SessionEntry<Msg>,
     session_id: SessionId,
-    output
     msg_handler: &MsgHandler,
     client_handle: &ClientHandle<Msg>,
+    ctcp_autoreply: bool,
 ) where
     Msg: Message,
     MsgHandler: Fn(&MessageContext<Msg>, Result<Msg>) -> Reaction<Msg>,
 {
+    if let Err(err) = session.inner.process_mio_event(readiness) {
+        let msg_ctx = MessageContext {
+            client_handle: client_handle.clone(),
+            session_id,
+            server_features: session.inner.server_features_snapshot(),
+            capabilities: session.inner.capabilities_snapshot(),
+        };
+        process_reaction(session, session_id, msg_handler(&msg_ctx, Err(err.into())));
+    }
+
     if readiness.is_writable() {
         session.is_writable = true;
     }
@@ -219,7 +253,7 @@ fn process_session_event<Msg, MsgHandler>(
     }
 
     if readiness.is_readable() {
-        process_readable(session, session_id, msg_handler, client_handle);
+        process_readable(session, session_id, msg_handler, client_handle, ctcp_autoreply);
     }
 }
 
@@ -228,15 +262,11 @@ fn process_readable<Msg, MsgHandler>(
     session_id: SessionId,
     msg_handler: &MsgHandler,
     client_handle: &ClientHandle<Msg>,
+    ctcp_autoreply: bool,
 ) where
     Msg: Message,
     MsgHandler: Fn(&MessageContext<Msg>, Result<Msg>) -> Reaction<Msg>,
 {
-    let msg_ctx = MessageContext {
-        client_handle: client_handle.clone(),
-        session_id,
-    };
-
     loop {
         let msg = match session.inner.recv::<Msg>() {
             Ok(Some(msg)) => Ok(msg),
@@ -248,7 +278,23 @@ fn process_readable<Msg, MsgHandler>(
             Err(err) => Err(err.into()),
         };
 
-        let reaction = handle_message(msg_handler, &msg_ctx, msg);
+        let incoming_result = match msg {
+            Ok(ref msg) => session.inner.handle_incoming(msg),
+            Err(_) => Ok(()),
+        };
+
+        let msg_ctx = MessageContext {
+            client_handle: client_handle.clone(),
+            session_id,
+            server_features: session.inner.server_features_snapshot(),
+            capabilities: session.inner.capabilities_snapshot(),
+        };
+
+        if let Err(err) = incoming_result {
+            process_reaction(session, session_id, msg_handler(&msg_ctx, Err(err)));
+        }
+
+        let reaction = handle_message(msg_handler, &msg_ctx, msg, ctcp_autoreply);
 
         process_reaction(session, session_id, reaction);
     }
@@ -297,6 +343,8 @@ fn process_writable<Msg, MsgHandler>(
             let msg_ctx = MessageContext {
                 client_handle: client_handle.clone(),
                 session_id,
+                server_features: session.inner.server_features_snapshot(),
+                capabilities: session.inner.capabilities_snapshot(),
             };
             process_reaction(session, session_id, msg_handler(&msg_ctx, Err(err)))
         });
@@ -306,6 +354,7 @@ fn handle_message<Msg, MsgHandler>(
     msg_handler: &MsgHandler,
     msg_ctx: &MessageContext<Msg>,
     msg: Result<Msg>,
+    ctcp_autoreply: bool,
 ) -> Reaction<Msg>
 where
     Msg: Message,
@@ -313,11 +362,17 @@ where
 {
     let msg = match msg {
         Ok(msg) => {
-            if msg.command_bytes() == b"PING" {
+            if msg.command() == Command::Ping {
                 match pong_from_ping(msg) {
                     Ok(pong) => return Reaction::RawMsg(pong),
                     Err(err) => Err(err.into()),
                 }
+            } else if ctcp_autoreply {
+                match ctcp::standard_reply(&msg, session::ctcp_version(), SystemTime::now()) {
+                    Some(Ok(reply)) => return Reaction::RawMsg(reply),
+                    Some(Err(err)) => Err(err.into()),
+                    None => Ok(msg),
+                }
             } else {
                 Ok(msg)
             }
@@ -338,6 +393,14 @@ fn process_reaction<Msg>(
     match reaction {
         Reaction::None => {}
         Reaction::RawMsg(ref msg) => session.send(session_id, msg),
+        Reaction::Privmsg { ref target, ref text } => {
+            let prefix_len = session.inner.own_prefix_len();
+            session.send_all(session_id, cmd::privmsg_split(prefix_len, target, text))
+        }
+        Reaction::Notice { ref target, ref text } => {
+            let prefix_len = session.inner.own_prefix_len();
+            session.send_all(session_id, cmd::notice_split(prefix_len, target, text))
+        }
         Reaction::Multi(reactions) => {
             for r in reactions {
                 process_reaction(session, session_id, r);
@@ -400,6 +463,23 @@ where
             }
         }
     }
+
+    fn send_all(&mut self, session_id: SessionId, msgs: message::Result<Vec<Msg>>) {
+        match msgs {
+            Ok(msgs) => {
+                for msg in &msgs {
+                    self.send(session_id, msg)
+                }
+            }
+            Err(err) => {
+                error!(
+                    "[session {}] Failed to construct messages to send (error: {})",
+                    session_id.index,
+                    err
+                )
+            }
+        }
+    }
 }
 
 impl EventContextId {
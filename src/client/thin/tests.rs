@@ -1,8 +1,20 @@
 #![cfg(test)]
 
 use super::*;
+use connection::PlaintextConnection;
 use message::NullMsg;
+use message::OwnedMessage;
 use quickcheck::TestResult;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
 
 quickcheck! {
     fn event_context_id_mio_token_conversion_bijective_1(n1: usize, n2: usize) -> TestResult {
@@ -48,3 +60,120 @@ quickcheck! {
         TestResult::from_bool(evt_ctx_id_1 == evt_ctx_id_2)
     }
 }
+
+/// The message handler's reply to the last message sent in the tests, by which the client's
+/// responses to the earlier messages can be told apart.
+const DONE_REPLY: &[u8] = b"NOTICE bob :done";
+
+/// Adds a session to the given client over a loopback connection, returning the server's end of
+/// the connection, from which the session's registration has been read.
+fn add_loopback_session(client: &mut ThinClient<OwnedMessage>) -> BufReader<TcpStream> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let connection = PlaintextConnection::from_addr(listener.local_addr().unwrap()).unwrap();
+    let mut server = BufReader::new(listener.accept().unwrap().0);
+
+    client
+        .add_session(session::build().connection(connection).nickname("alice"))
+        .unwrap();
+
+    while !server_recv_line(&mut server).starts_with(b"USER ") {}
+
+    server
+}
+
+fn server_recv_line(server: &mut BufReader<TcpStream>) -> Vec<u8> {
+    let mut line = Vec::new();
+    server.read_until(b'\n', &mut line).unwrap();
+    line
+}
+
+/// Sends the given lines, followed by a `PRIVMSG` of `done`, to a session of the given client, and
+/// returns the messages passed to the message handler before the last, and the lines with which the
+/// client responds ahead of the handler's reply to the last. The handler panics if it is passed an
+/// error.
+fn exchange(mut client: ThinClient<OwnedMessage>, lines: &[u8]) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+    let mut server = add_loopback_session(&mut client);
+    let handled = RefCell::new(Vec::new());
+    let is_done = Cell::new(false);
+
+    let msg_handler = |_: &MessageContext<OwnedMessage>, msg: Result<OwnedMessage>| {
+        let msg = msg.unwrap();
+
+        if msg.param(1) == Some(&b"done"[..]) {
+            is_done.set(true);
+            Reaction::RawMsg(OwnedMessage::try_from(Cow::Borrowed(DONE_REPLY)).unwrap())
+        } else {
+            handled.borrow_mut().push(msg.into_bytes());
+            Reaction::None
+        }
+    };
+
+    server.get_mut().write_all(lines).unwrap();
+    server
+        .get_mut()
+        .write_all(b":bob!bob@example.com PRIVMSG alice :done\r\n")
+        .unwrap();
+
+    // The messages are received as the session's socket is reported readable.
+    for _ in 0..500 {
+        client.process_event(mio::Token(1), mio::Ready::readable(), &msg_handler);
+
+        if is_done.get() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(is_done.get());
+
+    let mut responses = Vec::new();
+
+    loop {
+        match server_recv_line(&mut server) {
+            ref line if line.starts_with(DONE_REPLY) => break,
+            line => responses.push(line),
+        }
+    }
+
+    let handled = handled.borrow().clone();
+
+    (handled, responses)
+}
+
+/// Sends a CTCP `VERSION` query to a session of a client with the given `ctcp_autoreply` setting,
+/// and returns the lines with which the client responds.
+fn ctcp_version_responses(ctcp_autoreply: bool) -> Vec<Vec<u8>> {
+    let client = ThinClient::new().ctcp_autoreply(ctcp_autoreply);
+
+    exchange(client, b":bob!bob@example.com PRIVMSG alice :\x01VERSION\x01\r\n").1
+}
+
+#[test]
+fn ctcp_autoreply_1() {
+    let responses = ctcp_version_responses(true);
+
+    assert_eq!(responses.len(), 1);
+    assert!(responses[0].starts_with(b"NOTICE bob :\x01VERSION "));
+    assert!(responses[0].ends_with(b"\x01\r\n"));
+}
+
+#[test]
+fn ctcp_autoreply_disabled_1() {
+    assert_eq!(ctcp_version_responses(false), Vec::<Vec<u8>>::new());
+}
+
+#[test]
+fn overlong_line_1() {
+    let overlong_line = [
+        &b":bob!bob@example.com PRIVMSG alice :"[..],
+        &[b'x'; 600],
+        b"\r\n",
+    ].concat();
+
+    // The overlong line is discarded, and the session carries on with the next line.
+    let (handled, responses) = exchange(ThinClient::new(), &overlong_line);
+
+    assert_eq!(handled, Vec::<Vec<u8>>::new());
+    assert_eq!(responses, Vec::<Vec<u8>>::new());
+}
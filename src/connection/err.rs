@@ -1,12 +1,15 @@
 use message;
 #[cfg(feature = "pircolate")]
 use pircolate;
+use rustls;
+use std::borrow::Cow;
 use std::io;
 use std::str;
 
 error_chain! {
     foreign_links {
         Io(io::Error);
+        Tls(rustls::TLSError);
         Utf8Error(str::Utf8Error);
     }
 
@@ -17,12 +20,41 @@ error_chain! {
     }
 
     errors {
-        MessageTooLong(message: Vec<u8>) {
-            description("an attempt was made to send an IRC message longer than supported by the \
-                         IRC protocol")
-            display("An attempt was made to send an IRC message longer than supported by the IRC \
-                     protocol: {:?} (length: {:?})",
-                    String::from_utf8_lossy(&message), message.len())
+        CertificateRejected(reason: rustls::TLSError) {
+            description("a TLS certificate was rejected")
+            display("A TLS certificate was rejected: {}", reason)
+        }
+
+        InvalidClientCertificate(desc: Cow<'static, str>) {
+            description("a TLS client certificate or its private key could not be loaded")
+            display("A TLS client certificate or its private key could not be loaded: {}", desc)
+        }
+
+        InvalidTlsOptions(desc: Cow<'static, str>) {
+            description("TLS options could not be applied")
+            display("TLS options could not be applied: {}", desc)
+        }
+
+        MessageTooLong(message: Vec<u8>, limit: usize) {
+            description("an IRC message's body was longer than allowed by the connection's \
+                         line-length limits")
+            display("An IRC message's body exceeded the limit of {} bytes, including the \
+                     terminating CR-LF sequence: {:?} (length of whole message, excluding the \
+                     CR-LF sequence: {:?})",
+                    limit, String::from_utf8_lossy(&message), message.len())
+        }
+
+        StartTlsFailed(desc: Cow<'static, str>) {
+            description("a connection could not be upgraded to TLS with STARTTLS")
+            display("A connection could not be upgraded to TLS with STARTTLS: {}", desc)
+        }
+
+        TagsTooLong(message: Vec<u8>, limit: usize) {
+            description("an IRC message's IRCv3 tag section was longer than allowed by the \
+                         connection's line-length limits")
+            display("An IRC message's IRCv3 tag section exceeded the limit of {} bytes: {:?} \
+                     (length of whole message, excluding the CR-LF sequence: {:?})",
+                    limit, String::from_utf8_lossy(&message), message.len())
         }
     }
 }
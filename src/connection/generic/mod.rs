@@ -1,14 +1,22 @@
+use super::ConfigureDecoding;
+use super::ConfigureLineLimits;
 use super::Connection;
 use super::ConnectionPrivate;
+use super::ErrorKind;
 use super::GetPeerAddr;
+use super::LineLimits;
 use super::PlaintextConnection;
 use super::ReceiveMessage;
 use super::Result;
 use super::SendMessage;
 use super::TlsConnection;
 use Message;
+use message::MessageRef;
+use message::encoding::DecodingPolicy;
 use mio;
+use rustls;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 // TODO: add usage example.
 /// A generic IRC connection.
@@ -60,6 +68,15 @@ macro_rules! impl_generic {
                     $(GenericConnectionInner::$variant(ref mut conn) => conn.recv(),)*
                 }
             }
+
+            fn recv_with<F, T>(&mut self, f: F) -> Result<Option<T>>
+            where
+                F: FnOnce(MessageRef) -> T,
+            {
+                match self.inner {
+                    $(GenericConnectionInner::$variant(ref mut conn) => conn.recv_with(f),)*
+                }
+            }
         }
 
         impl GetPeerAddr for GenericConnection {
@@ -70,6 +87,38 @@ macro_rules! impl_generic {
             }
         }
 
+        impl ConfigureLineLimits for GenericConnection {
+            fn line_limits(&self) -> LineLimits {
+                match self.inner {
+                    $(GenericConnectionInner::$variant(ref conn) => conn.line_limits(),)*
+                }
+            }
+
+            fn set_line_limits(&mut self, limits: LineLimits) {
+                match self.inner {
+                    $(GenericConnectionInner::$variant(ref mut conn) => {
+                        conn.set_line_limits(limits)
+                    })*
+                }
+            }
+        }
+
+        impl ConfigureDecoding for GenericConnection {
+            fn decoding_policy(&self) -> DecodingPolicy {
+                match self.inner {
+                    $(GenericConnectionInner::$variant(ref conn) => conn.decoding_policy(),)*
+                }
+            }
+
+            fn set_decoding_policy(&mut self, policy: DecodingPolicy) {
+                match self.inner {
+                    $(GenericConnectionInner::$variant(ref mut conn) => {
+                        conn.set_decoding_policy(policy)
+                    })*
+                }
+            }
+        }
+
         impl ConnectionPrivate for GenericConnection {
             fn mio_registerable(&self) -> &mio::event::Evented {
                 match self.inner {
@@ -90,6 +139,14 @@ macro_rules! impl_generic {
                     $(GenericConnectionInner::$variant(ref conn) => conn.mio_poll_opts(),)*
                 }
             }
+
+            fn process_mio_event(&mut self, readiness: mio::Ready) -> Result<()> {
+                match self.inner {
+                    $(GenericConnectionInner::$variant(ref mut conn) => {
+                        conn.process_mio_event(readiness)
+                    })*
+                }
+            }
         }
     };
 }
@@ -100,3 +157,26 @@ impl_generic!(
 );
 
 impl Connection for GenericConnection {}
+
+impl GenericConnection {
+    /// Upgrades the connection, which must be a plaintext connection, to TLS in place, as with
+    /// [`PlaintextConnection::start_tls`]. If this fails, the connection is left as it was.
+    ///
+    /// [`PlaintextConnection::start_tls`]: struct.PlaintextConnection.html#method.start_tls
+    pub(crate) fn start_tls(
+        &mut self,
+        config: &Arc<rustls::ClientConfig>,
+        hostname: &str,
+    ) -> Result<()> {
+        let conn = match self.inner {
+            GenericConnectionInner::Plaintext(ref mut conn) => conn.upgrade(config, hostname)?,
+            _ => bail!(ErrorKind::StartTlsFailed(
+                "the connection is not a plaintext connection".into()
+            )),
+        };
+
+        self.inner = GenericConnectionInner::Tls(conn);
+
+        Ok(())
+    }
+}
@@ -1,26 +1,73 @@
 pub use self::err::*;
 pub use self::generic::GenericConnection;
 pub use self::plaintext::PlaintextConnection;
-//pub use self::tls::TlsConnection;
+pub use self::tls::ClientCertificate;
+pub use self::tls::TlsConnection;
 use Message;
+use message;
+use message::MessageRef;
+use message::encoding::DecodingPolicy;
 use mio;
 use std::borrow::Cow;
 use std::fmt::Debug;
 use std::io::BufRead;
 use std::io::Write;
-use client;
+use std::mem;
 use std::net::SocketAddr;
 
 mod err;
 mod generic;
 mod plaintext;
-//mod tls;
+mod tests;
+pub mod tls;
 
-const IRC_LINE_MAX_LEN: usize = 1024;
+/// The limits on the lengths of the lines a connection sends and receives.
+///
+/// The IRC protocol limits the part of a line other than its IRCv3 tags, which is called here its
+/// body, to 512 bytes, including the terminating CR-LF sequence. The IRCv3 message tags
+/// specification separately limits the tag section, including its leading `@` and the space that
+/// follows it, to 8191 bytes. These are the default limits, but some servers allow longer lines,
+/// and may advertise so.
+///
+/// A message that would exceed either limit is not sent, and an error is returned instead: a server
+/// would likely truncate the line rather than reject it, which could change its meaning. A received
+/// line that exceeds either limit is discarded, and a warning logged, so that no more of a line is
+/// kept in memory than the limits allow; the connection carries on with the next line.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct LineLimits {
+    /// The maximum length of a line's body, including the terminating CR-LF sequence.
+    pub body: usize,
+
+    /// The maximum length of a line's tag section, including its leading `@` and the space that
+    /// follows it.
+    pub tags: usize,
+}
+
+impl LineLimits {
+    /// Returns the maximum length of a whole line, including its terminating CR-LF sequence.
+    pub fn line_max_len(&self) -> usize {
+        self.tags.saturating_add(self.body)
+    }
+}
+
+impl Default for LineLimits {
+    fn default() -> Self {
+        LineLimits {
+            body: message::BODY_MAX_LEN,
+            tags: message::TAGS_MAX_LEN,
+        }
+    }
+}
 
 pub trait Connection
-    : Send + ReceiveMessage + SendMessage + GetPeerAddr + Into<GenericConnection> + Debug
-    {
+    : Send
+    + ReceiveMessage
+    + SendMessage
+    + GetPeerAddr
+    + ConfigureLineLimits
+    + ConfigureDecoding
+    + Into<GenericConnection>
+    + Debug {
 }
 
 pub trait SendMessage: Send + GetPeerAddr + Debug {
@@ -35,12 +82,33 @@ pub trait ReceiveMessage: Send + GetPeerAddr + Debug {
     fn recv<Msg>(&mut self) -> Result<Option<Msg>>
     where
         Msg: Message;
+
+    /// Like `recv`, but rather than returning an owned message, lends the message to the given
+    /// closure and returns the closure's result. Where the whole message is already in the
+    /// connection's receive buffer, it is not copied out of that buffer.
+    fn recv_with<F, T>(&mut self, f: F) -> Result<Option<T>>
+    where
+        F: FnOnce(MessageRef) -> T;
 }
 
 pub trait GetPeerAddr {
     fn peer_addr(&self) -> Result<SocketAddr>;
 }
 
+pub trait ConfigureLineLimits {
+    fn line_limits(&self) -> LineLimits;
+
+    /// Sets the limits that will be enforced on the lines subsequently sent and received.
+    fn set_line_limits(&mut self, LineLimits);
+}
+
+pub trait ConfigureDecoding {
+    fn decoding_policy(&self) -> DecodingPolicy;
+
+    /// Sets the policy by which the messages subsequently received will be decoded to UTF-8.
+    fn set_decoding_policy(&mut self, DecodingPolicy);
+}
+
 pub(crate) trait ConnectionPrivate {
     fn mio_registerable(&self) -> &mio::event::Evented;
 
@@ -48,43 +116,249 @@ pub(crate) trait ConnectionPrivate {
 
     fn mio_poll_opts(&self) -> mio::PollOpt;
 
-    fn process_mio_event<Msg>(&mut self, mio::Ready, client::thin::SessionEntry<Msg>) -> Result<()>
-        where Msg:Message;
+    /// Does what the connection itself must on the given readiness of its socket, before messages
+    /// are received from it or sent on it, such as writing out data that the socket would not
+    /// earlier accept.
+    fn process_mio_event(&mut self, readiness: mio::Ready) -> Result<()>;
 }
 
-fn recv_common<R, Msg>(reader: &mut R) -> Result<Option<Msg>>
+/// The part of a line that a connection has received, where the rest of it has yet to arrive.
+///
+/// A non-blocking connection may be able to read only part of a line before its socket has no
+/// more data to give, so the part that has been read is kept here until the line is complete,
+/// rather than being lost, with the rest of the line then taken for a line of its own.
+#[derive(Debug, Default)]
+struct PartialLine {
+    bytes: Vec<u8>,
+
+    /// Whether the line exceeds the limits, so that the rest of it is to be discarded as it
+    /// arrives, rather than kept.
+    is_discarded: bool,
+}
+
+fn recv_common<R, Msg>(
+    reader: &mut R,
+    partial_line: &mut PartialLine,
+    limits: LineLimits,
+    decoding_policy: DecodingPolicy,
+) -> Result<Option<Msg>>
 where
     R: BufRead,
     Msg: Message,
 {
-    let mut line = Vec::new();
+    let mut line = match read_line(reader, partial_line, limits)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
 
-    let bytes_read = reader.read_until(b'\n', &mut line)?;
+    let line_len = trim_line_terminator(&line).len();
+    line.truncate(line_len);
 
-    if bytes_read == 0 {
-        return Ok(None);
+    debug!("Received message: {:?}", String::from_utf8_lossy(&line));
+
+    let (line, decoding) = decoding_policy.decode_owned(line)?;
+
+    Ok(Msg::try_from_decoded(Cow::Owned(line.into_bytes()), decoding).map(Some)?)
+}
+
+fn recv_with_common<R, F, T>(
+    reader: &mut R,
+    partial_line: &mut PartialLine,
+    limits: LineLimits,
+    decoding_policy: DecodingPolicy,
+    f: F,
+) -> Result<Option<T>>
+where
+    R: BufRead,
+    F: FnOnce(MessageRef) -> T,
+{
+    loop {
+        let line_len = if partial_line.bytes.is_empty() && !partial_line.is_discarded {
+            let buffer = reader.fill_buf()?;
+
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+
+            buffer.iter().position(|&b| b == b'\n')
+        } else {
+            None
+        };
+
+        let line_len = match line_len {
+            Some(line_len) => line_len,
+            None => {
+                // The line continues past the end of the reader's buffer, or began before it, so
+                // it must be copied out.
+                let line = match read_line(reader, partial_line, limits)? {
+                    Some(line) => line,
+                    None => return Ok(None),
+                };
+
+                return Ok(Some(parse_line_with(
+                    trim_line_terminator(&line),
+                    decoding_policy,
+                    f,
+                )?));
+            }
+        };
+
+        // The whole line is in the reader's buffer, so it can be parsed in place, unless it must
+        // be transcoded. These calls to `fill_buf` will not perform I/O, as the buffer is not
+        // empty.
+        if !is_within_limits(trim_line_terminator(&reader.fill_buf()?[..line_len]), limits) {
+            reader.consume(line_len + 1);
+            continue;
+        }
+
+        let result = {
+            let line = trim_line_terminator(&reader.fill_buf()?[..line_len]);
+            parse_line_with(line, decoding_policy, f)
+        };
+
+        reader.consume(line_len + 1);
+
+        return Ok(Some(result?));
     }
+}
 
-    while line.ends_with(b"\n") || line.ends_with(b"\r") {
-        let _popped_char = line.pop();
+/// Decodes and parses the given line, which should not include its terminating CR-LF sequence, and
+/// lends the message to the given closure.
+fn parse_line_with<F, T>(line: &[u8], decoding_policy: DecodingPolicy, f: F) -> Result<T>
+where
+    F: FnOnce(MessageRef) -> T,
+{
+    debug!("Received message: {:?}", String::from_utf8_lossy(line));
+
+    let (line, decoding) = decoding_policy.decode(line)?;
+
+    Ok(f(MessageRef::parse_decoded(line.as_bytes(), decoding)?))
+}
+
+/// Reads a line, including its terminator, continuing the given partial line, and returns it, or
+/// `None` where the reader has reached its end. Lines that exceed the given limits are discarded,
+/// with a warning, and no more of such a line is read into memory than the limits allow.
+///
+/// Where reading fails, as where a non-blocking reader would block, the part of the line that has
+/// been read is kept in the partial line, so that the line is continued by the next call.
+fn read_line<R>(
+    reader: &mut R,
+    partial_line: &mut PartialLine,
+    limits: LineLimits,
+) -> Result<Option<Vec<u8>>>
+where
+    R: BufRead,
+{
+    let line_max_len = limits.line_max_len();
+
+    loop {
+        let (consumed_len, is_complete, is_too_long) = {
+            let buffer = reader.fill_buf()?;
+
+            if buffer.is_empty() {
+                // The reader's end may leave a last line unterminated.
+                if partial_line.bytes.is_empty() {
+                    return Ok(None);
+                }
+
+                (0, true, false)
+            } else {
+                let (len, is_complete) = match buffer.iter().position(|&b| b == b'\n') {
+                    Some(i) => (i + 1, true),
+                    None => (buffer.len(), false),
+                };
+
+                // A line that fills the limit without being terminated is too long once its
+                // terminator is added.
+                let total_len = partial_line.bytes.len() + len;
+                let is_too_long = total_len > line_max_len ||
+                    (!is_complete && total_len == line_max_len);
+
+                if !is_too_long && !partial_line.is_discarded {
+                    partial_line.bytes.extend_from_slice(&buffer[..len]);
+                }
+
+                (len, is_complete, is_too_long)
+            }
+        };
+
+        reader.consume(consumed_len);
+
+        if partial_line.is_discarded {
+            // The rest of a line that is too long is discarded as it arrives.
+            partial_line.is_discarded = !is_complete;
+        } else if is_too_long {
+            warn!(
+                "Discarded a received line longer than the limit of {} bytes.",
+                line_max_len
+            );
+
+            partial_line.bytes.clear();
+            partial_line.is_discarded = !is_complete;
+        } else if is_complete {
+            let line = mem::replace(&mut partial_line.bytes, Vec::new());
+
+            if is_within_limits(trim_line_terminator(&line), limits) {
+                return Ok(Some(line));
+            }
+        }
+    }
+}
+
+/// Returns whether the given received line, which should not include its terminating CR-LF
+/// sequence, is within the given limits, and logs a warning that the line is discarded if not.
+fn is_within_limits(line: &[u8], limits: LineLimits) -> bool {
+    match check_line_len(line, limits) {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("Discarded a received line: {}", err);
+            false
+        }
     }
+}
 
-    debug!("Received message: {:?}", String::from_utf8_lossy(&line));
+/// Checks the lengths of the tag section and body of the given line, which should not include the
+/// line's terminating CR-LF sequence, against the given limits.
+fn check_line_len(line: &[u8], limits: LineLimits) -> Result<()> {
+    let tags_len = if line.starts_with(b"@") {
+        // The tag section includes the space that follows it.
+        line.iter()
+            .position(|&b| b == b' ')
+            .map_or(line.len(), |i| i + 1)
+    } else {
+        0
+    };
+
+    ensure!(
+        tags_len <= limits.tags,
+        ErrorKind::TagsTooLong(line.to_owned(), limits.tags)
+    );
+
+    // The body is followed by the CR-LF sequence, which counts toward its limit.
+    ensure!(
+        line.len() - tags_len + 2 <= limits.body,
+        ErrorKind::MessageTooLong(line.to_owned(), limits.body)
+    );
+
+    Ok(())
+}
 
-    Ok(Msg::try_from(Cow::Owned(line)).map(Some)?)
+fn trim_line_terminator(mut line: &[u8]) -> &[u8] {
+    while line.ends_with(b"\n") || line.ends_with(b"\r") {
+        line = &line[..line.len() - 1];
+    }
+
+    line
 }
 
-fn try_send_common<W, Msg>(writer: &mut W, msg: &Msg) -> Result<()>
+fn try_send_common<W, Msg>(writer: &mut W, limits: LineLimits, msg: &Msg) -> Result<()>
 where
     W: Write,
     Msg: Message,
 {
     let msg_bytes = msg.as_bytes();
 
-    ensure!(
-        msg_bytes.len() <= IRC_LINE_MAX_LEN,
-        ErrorKind::MessageTooLong(msg_bytes.to_owned())
-    );
+    check_line_len(msg_bytes, limits)?;
 
     writer.write_all(msg_bytes)?;
     writer.write_all(b"\r\n")?;
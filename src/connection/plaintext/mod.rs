@@ -1,24 +1,41 @@
+use super::ConfigureDecoding;
+use super::ConfigureLineLimits;
 use super::Connection;
 use super::ConnectionPrivate;
+use super::ErrorKind;
 use super::GetPeerAddr;
-use super::IRC_LINE_MAX_LEN;
+use super::LineLimits;
+use super::PartialLine;
 use super::ReceiveMessage;
 use super::Result;
 use super::SendMessage;
+use super::TlsConnection;
 use super::recv_common;
+use super::recv_with_common;
 use super::try_send_common;
 use Message;
+use message::MessageRef;
+use message::encoding::DecodingPolicy;
 use mio;
+use rustls;
+use std::io;
+use std::io::BufRead;
 use std::io::BufReader;
 use std::io::BufWriter;
+use std::io::Write;
+use std::mem;
 use std::net::SocketAddr;
 use std::net::TcpStream;
 use std::net::ToSocketAddrs;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct PlaintextConnection {
     tcp_reader: BufReader<mio::net::TcpStream>,
     tcp_writer: BufWriter<mio::net::TcpStream>,
+    partial_line: PartialLine,
+    line_limits: LineLimits,
+    decoding_policy: DecodingPolicy,
 }
 
 impl PlaintextConnection {
@@ -37,14 +54,97 @@ impl PlaintextConnection {
             tcp_reader.peer_addr()?
         );
 
-        let tcp_writer = BufWriter::with_capacity(IRC_LINE_MAX_LEN, tcp_reader.try_clone()?);
-        let tcp_reader = BufReader::with_capacity(IRC_LINE_MAX_LEN, tcp_reader);
+        let line_limits = LineLimits::default();
+        let buffer_capacity = line_limits.line_max_len();
+        let tcp_writer = BufWriter::with_capacity(buffer_capacity, tcp_reader.try_clone()?);
+        let tcp_reader = BufReader::with_capacity(buffer_capacity, tcp_reader);
 
         Ok(PlaintextConnection {
             tcp_reader,
             tcp_writer,
+            partial_line: PartialLine::default(),
+            line_limits,
+            decoding_policy: DecodingPolicy::default(),
         })
     }
+
+    /// Upgrades the connection to TLS, verifying the server's certificate for the given hostname,
+    /// once the server has agreed to `STARTTLS` with `RPL_STARTTLS` (670).
+    ///
+    /// The TCP stream itself is kept, so that it remains registered for readiness events as it
+    /// was, and messages that have been sent are flushed first. The server must send nothing more
+    /// in plaintext after agreeing, so any data that follows its reply, whether in the receive
+    /// buffer or yet to be read, is rejected with an error of kind [`ErrorKind::StartTlsFailed`]
+    /// rather than taken as having been received over TLS.
+    ///
+    /// Such data is not kept because it cannot be trusted: anyone able to tamper with the
+    /// plaintext connection could have appended it to the server's reply, and, if it were kept, it
+    /// would be handled after the handshake as though the server had sent it over the encrypted
+    /// and authenticated connection. This is the STARTTLS command injection of CVE-2011-0411.
+    ///
+    /// The handshake is completed as the returned connection is used; see [`TlsConnection`].
+    ///
+    /// [`ErrorKind::StartTlsFailed`]: enum.ErrorKind.html#variant.StartTlsFailed
+    /// [`TlsConnection`]: struct.TlsConnection.html
+    pub fn start_tls(
+        mut self,
+        config: &Arc<rustls::ClientConfig>,
+        hostname: &str,
+    ) -> Result<TlsConnection> {
+        self.upgrade(config, hostname)
+    }
+
+    /// Upgrades the connection to TLS as [`start_tls`] does, but without consuming it, so that a
+    /// connection that cannot be upgraded is left as it was. Once this succeeds, this connection
+    /// is left with a stand-in for its TCP stream, and is not to be used further.
+    ///
+    /// [`start_tls`]: #method.start_tls
+    pub(super) fn upgrade(
+        &mut self,
+        config: &Arc<rustls::ClientConfig>,
+        hostname: &str,
+    ) -> Result<TlsConnection> {
+        self.prepare_start_tls()?;
+
+        let stand_in = self.tcp_reader.get_ref().try_clone()?;
+        let tcp_stream = mem::replace(self.tcp_reader.get_mut(), stand_in);
+
+        trace!("Upgrading plaintext connection to TLS.");
+
+        Ok(TlsConnection::from_mio_stream(
+            tcp_stream,
+            config,
+            hostname,
+            self.line_limits,
+            self.decoding_policy,
+        ))
+    }
+
+    /// Flushes the messages that have been sent, and checks that the server has sent nothing
+    /// since agreeing to `STARTTLS`.
+    fn prepare_start_tls(&mut self) -> Result<()> {
+        self.tcp_writer.flush()?;
+
+        // Part of a line that followed the server's reply may already have been read.
+        ensure!(
+            self.partial_line.bytes.is_empty(),
+            ErrorKind::StartTlsFailed(
+                "the server sent data in plaintext after agreeing to STARTTLS".into()
+            )
+        );
+
+        match self.tcp_reader.fill_buf() {
+            Ok(buffer) if buffer.is_empty() => bail!(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the connection was closed before the TLS handshake",
+            )),
+            Ok(_) => bail!(ErrorKind::StartTlsFailed(
+                "the server sent data in plaintext after agreeing to STARTTLS".into()
+            )),
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
 }
 
 impl Connection for PlaintextConnection {}
@@ -54,7 +154,7 @@ impl SendMessage for PlaintextConnection {
     where
         Msg: Message,
     {
-        try_send_common(&mut self.tcp_writer, msg)
+        try_send_common(&mut self.tcp_writer, self.line_limits, msg)
     }
 }
 
@@ -63,7 +163,25 @@ impl ReceiveMessage for PlaintextConnection {
     where
         Msg: Message,
     {
-        recv_common(&mut self.tcp_reader)
+        recv_common(
+            &mut self.tcp_reader,
+            &mut self.partial_line,
+            self.line_limits,
+            self.decoding_policy,
+        )
+    }
+
+    fn recv_with<F, T>(&mut self, f: F) -> Result<Option<T>>
+    where
+        F: FnOnce(MessageRef) -> T,
+    {
+        recv_with_common(
+            &mut self.tcp_reader,
+            &mut self.partial_line,
+            self.line_limits,
+            self.decoding_policy,
+            f,
+        )
     }
 }
 
@@ -73,6 +191,26 @@ impl GetPeerAddr for PlaintextConnection {
     }
 }
 
+impl ConfigureLineLimits for PlaintextConnection {
+    fn line_limits(&self) -> LineLimits {
+        self.line_limits
+    }
+
+    fn set_line_limits(&mut self, limits: LineLimits) {
+        self.line_limits = limits
+    }
+}
+
+impl ConfigureDecoding for PlaintextConnection {
+    fn decoding_policy(&self) -> DecodingPolicy {
+        self.decoding_policy
+    }
+
+    fn set_decoding_policy(&mut self, policy: DecodingPolicy) {
+        self.decoding_policy = policy
+    }
+}
+
 impl ConnectionPrivate for PlaintextConnection {
     fn mio_registerable(&self) -> &mio::event::Evented {
         self.tcp_reader.get_ref()
@@ -85,4 +223,16 @@ impl ConnectionPrivate for PlaintextConnection {
     fn mio_poll_opts(&self) -> mio::PollOpt {
         mio::PollOpt::edge()
     }
+
+    fn process_mio_event(&mut self, readiness: mio::Ready) -> Result<()> {
+        if readiness.is_writable() {
+            // Messages may remain buffered where the socket would not accept them all.
+            match self.tcp_writer.flush() {
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                result => result?,
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,304 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use message::encoding::Decoding;
+use std::collections::VecDeque;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+
+const LINES: &[u8] = b"PING :irc.example.net\r\n\
+                       :nick!user@host PRIVMSG #channel :Hello, world!\r\n\
+                       NOTICE * :unterminated";
+
+/// A reader that gives the given chunks of data, one per read, and fails with `WouldBlock` where a
+/// chunk is `None`, as a non-blocking socket does where the rest of the data has yet to arrive.
+struct ChunkedReader<'a>(VecDeque<Option<&'a [u8]>>);
+
+impl<'a> Read for ChunkedReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.pop_front() {
+            Some(Some(chunk)) => {
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            }
+            Some(None) => Err(io::ErrorKind::WouldBlock.into()),
+            None => Ok(0),
+        }
+    }
+}
+
+fn is_would_block<T>(result: Result<T>) -> bool {
+    match result {
+        Err(Error(ErrorKind::Io(ref err), _)) => err.kind() == io::ErrorKind::WouldBlock,
+        _ => false,
+    }
+}
+
+fn recv_all_with<R>(reader: &mut R) -> Vec<Vec<u8>>
+where
+    R: BufRead,
+{
+    let mut result = Vec::new();
+    let mut partial_line = PartialLine::default();
+    let limits = LineLimits::default();
+    let policy = DecodingPolicy::default();
+
+    while let Some(bytes) = recv_with_common(reader, &mut partial_line, limits, policy, |msg| {
+        msg.as_bytes().to_owned()
+    }).unwrap() {
+        result.push(bytes);
+    }
+
+    result
+}
+
+#[test]
+fn recv_with_matches_recv_1() {
+    let mut reader = BufReader::new(LINES);
+    let mut partial_line = PartialLine::default();
+    let mut expected = Vec::new();
+    let limits = LineLimits::default();
+    let policy = DecodingPolicy::default();
+
+    while let Some(msg) =
+        recv_common::<_, OwnedMessage>(&mut reader, &mut partial_line, limits, policy).unwrap()
+    {
+        expected.push(msg.into_bytes());
+    }
+
+    assert_eq!(expected.len(), 3);
+
+    // A capacity of 16 bytes is smaller than any of the lines, so this exercises the fallback path
+    // where a line must be copied out of the buffer.
+    for &capacity in &[16, 32, LINES.len()] {
+        let mut reader = BufReader::with_capacity(capacity, LINES);
+
+        assert_eq!(recv_all_with(&mut reader), expected, "capacity: {}", capacity);
+    }
+}
+
+#[test]
+fn line_limits_1() {
+    let limits = LineLimits { body: 16, tags: 9 };
+    let lines: &[u8] = b"PRIVMSG #c :hi\r\n\
+                         @a=1;b=2 PING x\r\n\
+                         PRIVMSG #c :hello\r\n\
+                         @a=1;b=22 PING x\r\n\
+                         PING :after\r\n";
+
+    for &capacity in &[8, 64] {
+        let mut reader = BufReader::with_capacity(capacity, lines);
+        let mut partial_line = PartialLine::default();
+        let mut recv = || {
+            recv_with_common(
+                &mut reader,
+                &mut partial_line,
+                limits,
+                DecodingPolicy::Strict,
+                |msg| msg.as_bytes().to_owned(),
+            )
+        };
+
+        assert_eq!(recv().unwrap(), Some(b"PRIVMSG #c :hi".to_vec()));
+        assert_eq!(recv().unwrap(), Some(b"@a=1;b=2 PING x".to_vec()));
+
+        // The lines whose body and whose tags are too long are discarded.
+        assert_eq!(recv().unwrap(), Some(b"PING :after".to_vec()));
+        assert_eq!(recv().unwrap(), None);
+    }
+}
+
+#[test]
+fn line_limits_2() {
+    let limits = LineLimits { body: 16, tags: 9 };
+    let long_line = [&b"PRIVMSG #c :"[..], &[b'x'; 100]].concat();
+    let lines = [&long_line[..], b"\r\nPING :after\r\n"].concat();
+    let mut reader = BufReader::with_capacity(8, &lines[..]);
+    let mut partial_line = PartialLine::default();
+    let mut recv = || {
+        recv_common::<_, OwnedMessage>(
+            &mut reader,
+            &mut partial_line,
+            limits,
+            DecodingPolicy::Strict,
+        )
+    };
+
+    // The rest of the overlong line is discarded, so the next line is read intact.
+    assert_eq!(
+        recv().unwrap().map(OwnedMessage::into_bytes),
+        Some(b"PING :after".to_vec())
+    );
+    assert_eq!(recv().unwrap().map(OwnedMessage::into_bytes), None);
+
+    // The same holds where the overlong line is the last, and is unterminated.
+    let mut reader = BufReader::with_capacity(8, &long_line[..]);
+    let mut partial_line = PartialLine::default();
+
+    let result = recv_common::<_, OwnedMessage>(
+        &mut reader,
+        &mut partial_line,
+        limits,
+        DecodingPolicy::Strict,
+    );
+
+    assert_eq!(result.unwrap().map(OwnedMessage::into_bytes), None);
+}
+
+#[test]
+fn decoding_policy_1() {
+    // "café" in Windows code page 1252, and "€" in UTF-8.
+    let lines: &[u8] = b"PRIVMSG #c :caf\xE9 \x80\r\nPRIVMSG #c :\xE2\x82\xAC\r\n";
+    let limits = LineLimits::default();
+
+    for &(policy, expected, decoding) in &[
+        (DecodingPolicy::Lossy, "caf\u{FFFD} \u{FFFD}", Decoding::Utf8Lossy),
+        (DecodingPolicy::FallbackLatin1, "caf\u{E9} \u{80}", Decoding::Latin1),
+        (DecodingPolicy::FallbackCp1252, "caf\u{E9} \u{20AC}", Decoding::Cp1252),
+    ] {
+        let mut reader = BufReader::new(lines);
+        let mut partial_line = PartialLine::default();
+        let msg = recv_common::<_, OwnedMessage>(&mut reader, &mut partial_line, limits, policy)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(msg.trailing(), Some(expected.as_bytes()));
+        assert_eq!(msg.decoding(), Some(decoding));
+
+        let msg = recv_common::<_, OwnedMessage>(&mut reader, &mut partial_line, limits, policy)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(msg.trailing(), Some("\u{20AC}".as_bytes()));
+        assert_eq!(msg.decoding(), Some(Decoding::Utf8));
+
+        for &capacity in &[8, 64] {
+            let mut reader = BufReader::with_capacity(capacity, lines);
+            let mut partial_line = PartialLine::default();
+            let mut recv = || {
+                recv_with_common(&mut reader, &mut partial_line, limits, policy, |msg| {
+                    (msg.trailing().map(|t| t.to_owned()), msg.decoding())
+                }).unwrap()
+                    .unwrap()
+            };
+
+            assert_eq!(recv(), (Some(expected.as_bytes().to_owned()), Some(decoding)));
+            assert_eq!(recv(), (Some("\u{20AC}".as_bytes().to_owned()), Some(Decoding::Utf8)));
+        }
+    }
+
+    let mut reader = BufReader::new(lines);
+    let mut partial_line = PartialLine::default();
+
+    match recv_common::<_, OwnedMessage>(
+        &mut reader,
+        &mut partial_line,
+        limits,
+        DecodingPolicy::Strict,
+    ) {
+        Err(Error(ErrorKind::Message(message::ErrorKind::InvalidUtf8(ref bytes, _)), _)) => {
+            assert_eq!(bytes, b"PRIVMSG #c :caf\xE9 \x80")
+        }
+        other => panic!("Expected invalid UTF-8; got {:?}", other),
+    }
+}
+
+#[test]
+fn send_line_limits_1() {
+    let limits = LineLimits { body: 16, tags: 9 };
+    let send = |line: &[u8]| {
+        let mut output = Vec::new();
+        let msg = OwnedMessage::try_from(Cow::Borrowed(line)).unwrap();
+        try_send_common(&mut output, limits, &msg).map(|()| output)
+    };
+
+    assert_eq!(send(b"PRIVMSG #c :hi").unwrap(), b"PRIVMSG #c :hi\r\n");
+    assert_eq!(send(b"@a=1;b=2 PING x").unwrap(), b"@a=1;b=2 PING x\r\n");
+
+    match send(b"PRIVMSG #c :hello") {
+        Err(Error(ErrorKind::MessageTooLong(_, 16), _)) => {}
+        other => panic!("Expected body to be too long; got {:?}", other),
+    }
+
+    match send(b"@a=1;b=22 PING x") {
+        Err(Error(ErrorKind::TagsTooLong(_, 9), _)) => {}
+        other => panic!("Expected tags to be too long; got {:?}", other),
+    }
+}
+
+#[test]
+fn partial_line_1() {
+    let chunks: &[Option<&[u8]>] = &[
+        Some(b"PING :first\r\nPRIVMSG #c :hel"),
+        None,
+        Some(b"lo\r"),
+        None,
+        Some(b"\nPING :last\r\n"),
+    ];
+    let limits = LineLimits::default();
+    let policy = DecodingPolicy::Strict;
+
+    let mut reader = BufReader::new(ChunkedReader(chunks.iter().cloned().collect()));
+    let mut partial_line = PartialLine::default();
+    let mut recv = || {
+        recv_common::<_, OwnedMessage>(&mut reader, &mut partial_line, limits, policy)
+            .map(|msg| msg.map(OwnedMessage::into_bytes))
+    };
+
+    // The part of the line received before each `WouldBlock` is kept for the next call.
+    assert_eq!(recv().unwrap(), Some(b"PING :first".to_vec()));
+    assert!(is_would_block(recv()));
+    assert!(is_would_block(recv()));
+    assert_eq!(recv().unwrap(), Some(b"PRIVMSG #c :hello".to_vec()));
+    assert_eq!(recv().unwrap(), Some(b"PING :last".to_vec()));
+    assert_eq!(recv().unwrap(), None);
+
+    let mut reader = BufReader::new(ChunkedReader(chunks.iter().cloned().collect()));
+    let mut partial_line = PartialLine::default();
+    let mut recv = || {
+        recv_with_common(&mut reader, &mut partial_line, limits, policy, |msg| {
+            msg.as_bytes().to_owned()
+        })
+    };
+
+    assert_eq!(recv().unwrap(), Some(b"PING :first".to_vec()));
+    assert!(is_would_block(recv()));
+    assert!(is_would_block(recv()));
+    assert_eq!(recv().unwrap(), Some(b"PRIVMSG #c :hello".to_vec()));
+    assert_eq!(recv().unwrap(), Some(b"PING :last".to_vec()));
+    assert_eq!(recv().unwrap(), None);
+}
+
+#[test]
+fn partial_line_2() {
+    let limits = LineLimits { body: 16, tags: 9 };
+    let long_start = [&b"PRIVMSG #c :"[..], &[b'x'; 20]].concat();
+    let chunks: &[Option<&[u8]>] = &[Some(&long_start), None, Some(b" QUIT :x\r\nPING :after\r\n")];
+    let policy = DecodingPolicy::Strict;
+
+    let mut reader = BufReader::new(ChunkedReader(chunks.iter().cloned().collect()));
+    let mut partial_line = PartialLine::default();
+    let mut recv = || {
+        recv_common::<_, OwnedMessage>(&mut reader, &mut partial_line, limits, policy)
+            .map(|msg| msg.map(OwnedMessage::into_bytes))
+    };
+
+    // The rest of the overlong line is still discarded once more of it arrives.
+    assert!(is_would_block(recv()));
+    assert_eq!(recv().unwrap(), Some(b"PING :after".to_vec()));
+    assert_eq!(recv().unwrap(), None);
+
+    let mut reader = BufReader::new(ChunkedReader(chunks.iter().cloned().collect()));
+    let mut partial_line = PartialLine::default();
+    let mut recv = || {
+        recv_with_common(&mut reader, &mut partial_line, limits, policy, |msg| {
+            msg.as_bytes().to_owned()
+        })
+    };
+
+    assert!(is_would_block(recv()));
+    assert_eq!(recv().unwrap(), Some(b"PING :after".to_vec()));
+    assert_eq!(recv().unwrap(), None);
+}
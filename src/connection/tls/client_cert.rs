@@ -0,0 +1,82 @@
+use connection::ErrorKind;
+use connection::Result;
+use rustls;
+use rustls::internal::pemfile;
+use rustls::sign::RSASigningKey;
+use std::fmt;
+
+/// A TLS client certificate chain and its private key, with which a server may identify the
+/// client, as for SASL `EXTERNAL` authentication, which is also known as CertFP.
+///
+/// The certificate is presented by a [`TlsConnection`] whose `rustls::ClientConfig` it has been
+/// added to with [`configure`]. The private key is not shown in the `Debug` representation.
+///
+/// [`TlsConnection`]: struct.TlsConnection.html
+/// [`configure`]: #method.configure
+#[derive(Clone)]
+pub struct ClientCertificate {
+    chain: Vec<rustls::Certificate>,
+    key: rustls::PrivateKey,
+}
+
+impl ClientCertificate {
+    /// Constructs a `ClientCertificate` from a chain of DER-encoded certificates, beginning with
+    /// the client's own certificate, and a DER-encoded RSA private key, in PKCS #1 or PKCS #8
+    /// format.
+    pub fn from_der(chain: Vec<Vec<u8>>, key: Vec<u8>) -> Result<Self> {
+        let chain = chain.into_iter().map(rustls::Certificate).collect::<Vec<_>>();
+        let key = rustls::PrivateKey(key);
+
+        ensure!(!chain.is_empty(), invalid("the certificate chain is empty"));
+        ensure!(
+            RSASigningKey::new(&key).is_ok(),
+            invalid("the private key is not a valid RSA private key")
+        );
+
+        Ok(ClientCertificate { chain, key })
+    }
+
+    /// Constructs a `ClientCertificate` from PEM-encoded data: a chain of certificates, beginning
+    /// with the client's own certificate, and an RSA private key, in either a `RSA PRIVATE KEY`
+    /// (PKCS #1) or a `PRIVATE KEY` (PKCS #8) section. Any other sections are ignored.
+    pub fn from_pem(chain: &[u8], key: &[u8]) -> Result<Self> {
+        let chain = pemfile::certs(&mut &chain[..])
+            .map_err(|()| invalid("the certificate chain is not valid PEM"))?;
+
+        let mut keys = pemfile::rsa_private_keys(&mut &key[..])
+            .map_err(|()| invalid("the private key is not valid PEM"))?;
+
+        if keys.is_empty() {
+            keys = pemfile::pkcs8_private_keys(&mut &key[..])
+                .map_err(|()| invalid("the private key is not valid PEM"))?;
+        }
+
+        ensure!(keys.len() <= 1, invalid("more than one private key was given"));
+
+        let key = match keys.pop() {
+            Some(key) => key,
+            None => bail!(invalid("no RSA private key was found")),
+        };
+
+        Self::from_der(chain.into_iter().map(|cert| cert.0).collect(), key.0)
+    }
+
+    /// Causes connections made with the given configuration to present this certificate to
+    /// servers that request a client certificate.
+    pub fn configure(self, config: &mut rustls::ClientConfig) {
+        config.set_single_client_cert(self.chain, self.key);
+    }
+}
+
+impl fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientCertificate")
+            .field("chain_len", &self.chain.len())
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+fn invalid(desc: &'static str) -> ErrorKind {
+    ErrorKind::InvalidClientCertificate(desc.into())
+}
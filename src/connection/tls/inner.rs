@@ -1,209 +1,178 @@
-//! Maybe this makes rustls work with mio.
+//! Non-blocking I/O through a TLS session.
 //!
-//! This code was derived from the file `examples/tlsclient.rs` in version 0.10.0 of `rustls`,
-//! which comes with the following notices:
-//!
-//! > Copyright (c) 2016, Joseph Birr-Pixton <jpixton@gmail.com>
-//! >
-//! > Permission to use, copy, modify, and/or distribute this software for
-//! > any purpose with or without fee is hereby granted, provided that the
-//! > above copyright notice and this permission notice appear in all copies.
+//! A `rustls` session does no I/O of its own: it decrypts the TLS data that is read into it and
+//! encrypts the plaintext that is written to it, and it says, with `wants_read` and `wants_write`,
+//! when it needs TLS data to be read from or written to the socket.
 
+use super::tls_error;
+use connection::Result;
 use mio;
-use mio::tcp::TcpStream;
 use rustls;
 use rustls::Session;
-use std::collections;
-use std::fs;
+use std::fmt;
 use std::io;
-use std::io::{BufReader, Read, Write};
-use std::net::SocketAddr;
-use std::process;
-use std::str;
-use std::sync::Arc;
-use std::sync::Mutex;
-
-/// This encapsulates the TCP-level connection, some connection
-/// state, and the underlying TLS-level session.
-pub struct TlsClient {
-    socket: TcpStream,
-    closing: bool,
-    clean_closure: bool,
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+
+/// A TLS session over a non-blocking TCP stream, through which plaintext can be read and written.
+///
+/// Reading returns an error of kind `WouldBlock` where no plaintext is available and the socket
+/// has no TLS data to read, and `Ok(0)` once the server has closed the TLS session or the TCP
+/// connection. Writing never blocks: TLS data that the socket will not yet accept is kept by the
+/// session and written on later reads, writes, and flushes.
+pub(super) struct TlsStream {
+    tcp_stream: mio::net::TcpStream,
     tls_session: rustls::ClientSession,
+    tls_error: Option<rustls::TLSError>,
+    is_eof: bool,
 }
 
-impl TlsClient {
-    fn ready(&mut self, poll: &mut mio::Poll, ev: &mio::Event) {
-        if ev.readiness().is_readable() {
-            self.do_read();
-        }
-
-        if ev.readiness().is_writable() {
-            self.do_write();
-        }
-
-        if self.is_closed() {
-            println!("Connection closed");
-            process::exit(if self.clean_closure { 0 } else { 1 });
+impl TlsStream {
+    /// Wraps the given TCP stream, over which the given TLS session's handshake either has been
+    /// completed or is to be continued as the stream is read and written.
+    pub(super) fn new(tcp_stream: mio::net::TcpStream, tls_session: rustls::ClientSession) -> Self {
+        TlsStream {
+            tcp_stream,
+            tls_session,
+            tls_error: None,
+            is_eof: false,
         }
-
-        self.reregister(poll);
-    }
-}
-
-/// We implement `io::Write` and pass through to the TLS session
-impl io::Write for TlsClient {
-    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
-        self.tls_session.write(bytes)
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.tls_session.flush()
+    pub(super) fn tcp_stream(&self) -> &mio::net::TcpStream {
+        &self.tcp_stream
     }
-}
 
-impl io::Read for TlsClient {
-    fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
-        self.tls_session.read(bytes)
+    /// Returns the TLS error, if any, for which reading last failed. Such a failure is reported
+    /// through `io::Read` as an error of kind `InvalidData`, which this allows to be told apart
+    /// from I/O errors.
+    pub(super) fn take_tls_error(&mut self) -> Option<rustls::TLSError> {
+        self.tls_error.take()
     }
-}
 
-impl TlsClient {
-    fn new(sock: TcpStream, hostname: &str, cfg: Arc<rustls::ClientConfig>) -> TlsClient {
-        TlsClient {
-            socket: sock,
-            closing: false,
-            clean_closure: false,
-            tls_session: rustls::ClientSession::new(&cfg, hostname),
+    /// Writes as much of the session's pending TLS data to the socket as it will accept without
+    /// blocking.
+    pub(super) fn write_tls(&mut self) -> io::Result<()> {
+        while self.tls_session.wants_write() {
+            match self.tls_session.write_tls(&mut self.tcp_stream) {
+                Ok(_) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            }
         }
-    }
 
-    fn read_source_to_end(&mut self, rd: &mut io::Read) -> io::Result<usize> {
-        let mut buf = Vec::new();
-        let len = rd.read_to_end(&mut buf)?;
-        self.tls_session.write_all(&buf).unwrap();
-        Ok(len)
+        Ok(())
     }
 
-    /// We're ready to do a read.
-    fn do_read(&mut self) {
-        // Read TLS data.  This fails if the underlying TCP connection
-        // is broken.
-        let rc = self.tls_session.read_tls(&mut self.socket);
-        if rc.is_err() {
-            println!("TLS read error: {:?}", rc);
-            self.closing = true;
-            return;
+    /// Reads TLS data from the socket into the session, and processes it.
+    fn read_tls(&mut self) -> io::Result<()> {
+        if self.tls_session.read_tls(&mut self.tcp_stream)? == 0 {
+            self.is_eof = true;
+            return Ok(());
         }
 
-        // If we're ready but there's no data: EOF.
-        if rc.unwrap() == 0 {
-            println!("EOF");
-            self.closing = true;
-            self.clean_closure = true;
-            return;
+        if let Err(err) = self.tls_session.process_new_packets() {
+            // The session will have queued an alert telling the server of the error.
+            let _ = self.write_tls();
+            let io_err = io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+            self.tls_error = Some(err);
+            return Err(io_err);
         }
 
-        // Reading some TLS data might have yielded new TLS
-        // messages to process.  Errors from this indicate
-        // TLS protocol problems and are fatal.
-        let processed = self.tls_session.process_new_packets();
-        if processed.is_err() {
-            println!("TLS error: {:?}", processed.unwrap_err());
-            self.closing = true;
-            return;
-        }
+        Ok(())
+    }
+}
 
-        // Having read some TLS data, and processed any new messages,
-        // we might have new plaintext as a result.
-        //
-        // Read it and then write it to stdout.
-        let mut plaintext = Vec::new();
-        let rc = self.tls_session.read_to_end(&mut plaintext);
-        if !plaintext.is_empty() {
-            io::stdout().write_all(&plaintext).unwrap();
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
         }
 
-        // If that fails, the peer might have started a clean TLS-level
-        // session closure.
-        if rc.is_err() {
-            let err = rc.unwrap_err();
-            println!("Plaintext read error: {:?}", err);
-            self.clean_closure = err.kind() == io::ErrorKind::ConnectionAborted;
-            self.closing = true;
-            return;
+        loop {
+            match self.tls_session.read(buf) {
+                Ok(0) => {}
+                Ok(len) => return Ok(len),
+                // This is how `rustls` reports that the server has sent a `close_notify` alert.
+                Err(ref err) if err.kind() == io::ErrorKind::ConnectionAborted => return Ok(0),
+                Err(err) => return Err(err),
+            }
+
+            if self.is_eof {
+                return Ok(0);
+            }
+
+            // The session may have TLS data to send, such as alerts, before it can read more.
+            self.write_tls()?;
+
+            if self.tls_session.wants_read() {
+                self.read_tls()?;
+            } else {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
         }
     }
+}
 
-    fn do_write(&mut self) {
-        self.tls_session.write_tls(&mut self.socket).unwrap();
+impl Write for TlsStream {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.tls_session.write(bytes)
     }
 
-    fn register(&self, poll: &mut mio::Poll) {
-        poll.register(
-            &self.socket,
-            CLIENT,
-            self.ready_interest(),
-            mio::PollOpt::level() | mio::PollOpt::oneshot(),
-        ).unwrap();
+    fn flush(&mut self) -> io::Result<()> {
+        self.tls_session.flush()?;
+        self.write_tls()
     }
+}
 
-    fn reregister(&self, poll: &mut mio::Poll) {
-        poll.reregister(
-            &self.socket,
-            CLIENT,
-            self.ready_interest(),
-            mio::PollOpt::level() | mio::PollOpt::oneshot(),
-        ).unwrap();
+impl fmt::Debug for TlsStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TlsStream")
+            .field("tcp_stream", &self.tcp_stream)
+            .field("tls_error", &self.tls_error)
+            .field("is_eof", &self.is_eof)
+            .finish()
     }
+}
 
-    // Use wants_read/wants_write to register for different mio-level
-    // IO readiness events.
-    fn ready_interest(&self) -> mio::Ready {
-        let rd = self.tls_session.wants_read();
-        let wr = self.tls_session.wants_write();
-
-        if rd && wr {
-            mio::Ready::readable() | mio::Ready::writable()
-        } else if wr {
-            mio::Ready::writable()
+/// Completes the given TLS session's handshake over the given TCP stream, which is made blocking,
+/// so that the handshake waits on the socket rather than spinning while it is not ready.
+pub(super) fn handshake<S>(tls_session: &mut S, tcp_stream: &mut TcpStream) -> Result<()>
+where
+    S: Session,
+{
+    tcp_stream.set_nonblocking(false)?;
+
+    while tls_session.is_handshaking() {
+        if tls_session.wants_write() {
+            tls_session.write_tls(tcp_stream)?;
+        } else if tls_session.wants_read() {
+            if tls_session.read_tls(tcp_stream)? == 0 {
+                bail!(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the connection was closed during the TLS handshake",
+                ));
+            }
+
+            if let Err(err) = tls_session.process_new_packets() {
+                // Tell the peer of the error with the alert that the session will have queued.
+                let _ = tls_session.write_tls(tcp_stream);
+                bail!(tls_error(err));
+            }
         } else {
-            mio::Ready::readable()
+            // Nothing could ever complete the handshake, so it would otherwise loop forever.
+            bail!(io::Error::new(
+                io::ErrorKind::Other,
+                "the TLS handshake stalled, wanting neither to read nor to write",
+            ));
         }
     }
 
-    fn is_closed(&self) -> bool {
-        self.closing
-    }
-}
-
-/// Parse some arguments, then make a TLS client connection
-/// somewhere.
-fn main() {
-    let sock = TcpStream::connect(&addr).unwrap();
-    let mut tlsclient = TlsClient::new(sock, &args.arg_hostname, config);
-
-    if args.flag_http {
-        let httpreq = format!(
-            "GET / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nAccept-Encoding: \
-             identity\r\n\r\n",
-            args.arg_hostname
-        );
-        tlsclient.write_all(httpreq.as_bytes()).unwrap();
-    } else {
-        let mut stdin = io::stdin();
-        tlsclient.read_source_to_end(&mut stdin).unwrap();
+    // The last handshake message may remain to be sent.
+    while tls_session.wants_write() {
+        tls_session.write_tls(tcp_stream)?;
     }
 
-    let mut poll = mio::Poll::new().unwrap();
-    let mut events = mio::Events::with_capacity(32);
-    tlsclient.register(&mut poll);
-
-    loop {
-        poll.poll(&mut events, None).unwrap();
-
-        for ev in events.iter() {
-            tlsclient.ready(&mut poll, &ev);
-        }
-    }
+    Ok(())
 }
@@ -1,30 +1,67 @@
+//! IRC connections secured with TLS, and the verification of servers' certificates.
+
+use super::ConfigureDecoding;
+use super::ConfigureLineLimits;
 use super::Connection;
 use super::ConnectionPrivate;
+use super::Error;
+use super::ErrorKind;
 use super::GetPeerAddr;
-use super::IRC_LINE_MAX_LEN;
+use super::LineLimits;
+use super::PartialLine;
 use super::ReceiveMessage;
 use super::Result;
 use super::SendMessage;
 use super::recv_common;
+use super::recv_with_common;
 use super::try_send_common;
 use Message;
+use message::MessageRef;
+use message::encoding::DecodingPolicy;
 use mio;
 use rustls;
-use rustls::Session as RustlsSession;
 use std::io::BufReader;
 use std::net::SocketAddr;
 use std::net::TcpStream;
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
 
+pub use self::client_cert::ClientCertificate;
+pub use self::options::TlsOptions;
+
+mod client_cert;
 mod inner;
+mod options;
+mod tests;
+mod verify;
 
+/// An IRC connection secured with TLS.
+///
+/// The TLS handshake is completed, and the server's certificate verified, when the connection is
+/// constructed, so that a certificate that fails verification is reported at once, as an error of
+/// kind [`ErrorKind::CertificateRejected`]; a connection upgraded from plaintext with
+/// [`PlaintextConnection::start_tls`] instead completes its handshake as it is used. Thereafter,
+/// the connection does not block: `recv` returns an I/O error of kind `WouldBlock` where no whole
+/// message has been received, and messages sent with `try_send` are queued in the TLS session where
+/// the socket will not yet accept them, to be written as the connection is next used.
+///
+/// [`ErrorKind::CertificateRejected`]: ../enum.ErrorKind.html#variant.CertificateRejected
+/// [`PlaintextConnection::start_tls`]: ../struct.PlaintextConnection.html#method.start_tls
 #[derive(Debug)]
 pub struct TlsConnection {
-    inner: BufReader<inner::TlsClient>,
+    inner: BufReader<inner::TlsStream>,
+    partial_line: PartialLine,
+    line_limits: LineLimits,
+    decoding_policy: DecodingPolicy,
 }
 
 impl TlsConnection {
+    /// Connects to the given server over TLS, verifying its certificate for the given hostname.
+    ///
+    /// The configuration is best made with [`TlsOptions`], which chooses the trusted root
+    /// certificates and the client certificate, if any.
+    ///
+    /// [`TlsOptions`]: struct.TlsOptions.html
     pub fn from_addr<A>(
         server_addrs: A,
         config: &Arc<rustls::ClientConfig>,
@@ -36,19 +73,92 @@ impl TlsConnection {
         Self::from_tcp_stream(TcpStream::connect(server_addrs)?, config, hostname)
     }
 
+    /// Establishes a TLS session over the given TCP stream, as with [`from_addr`].
+    ///
+    /// [`from_addr`]: #method.from_addr
     pub fn from_tcp_stream(
-        tcp_stream: TcpStream,
+        mut tcp_stream: TcpStream,
         config: &Arc<rustls::ClientConfig>,
         hostname: &str,
     ) -> Result<Self> {
+        let mut tls_session = rustls::ClientSession::new(config, hostname);
+
+        // The handshake is completed while the socket is blocking.
+        inner::handshake(&mut tls_session, &mut tcp_stream)?;
+
         let tcp_stream = mio::net::TcpStream::from_stream(tcp_stream)?;
-        let tls_client = inner::TlsClient::new(tcp_stream, hostname, config);
 
         trace!("[{}] Established TLS connection.", tcp_stream.peer_addr()?);
 
-        let inner = BufReader::with_capacity(IRC_LINE_MAX_LEN, tls_client);
+        Ok(Self::new(
+            tcp_stream,
+            tls_session,
+            LineLimits::default(),
+            DecodingPolicy::default(),
+        ))
+    }
 
-        Ok(TlsConnection { inner })
+    /// Begins a TLS session over the given non-blocking TCP stream, as to upgrade a plaintext
+    /// connection with `STARTTLS`, without blocking.
+    ///
+    /// Unlike with [`from_tcp_stream`], the handshake is completed as the connection is used, so
+    /// that a certificate that fails verification is reported by `recv`. Messages sent before then
+    /// are queued in the TLS session, to be sent once the handshake is complete.
+    ///
+    /// [`from_tcp_stream`]: #method.from_tcp_stream
+    pub(super) fn from_mio_stream(
+        tcp_stream: mio::net::TcpStream,
+        config: &Arc<rustls::ClientConfig>,
+        hostname: &str,
+        line_limits: LineLimits,
+        decoding_policy: DecodingPolicy,
+    ) -> Self {
+        let tls_session = rustls::ClientSession::new(config, hostname);
+        let mut connection = Self::new(tcp_stream, tls_session, line_limits, decoding_policy);
+
+        // The client's message is the first of the handshake. An error in sending it will recur
+        // when the connection is next used.
+        let _ = connection.inner.get_mut().write_tls();
+
+        connection
+    }
+
+    fn new(
+        tcp_stream: mio::net::TcpStream,
+        tls_session: rustls::ClientSession,
+        line_limits: LineLimits,
+        decoding_policy: DecodingPolicy,
+    ) -> Self {
+        let inner = BufReader::with_capacity(
+            line_limits.line_max_len(),
+            inner::TlsStream::new(tcp_stream, tls_session),
+        );
+
+        TlsConnection {
+            inner,
+            partial_line: PartialLine::default(),
+            line_limits,
+            decoding_policy,
+        }
+    }
+
+    /// Returns the TLS error for which an I/O error was reported by the TLS stream, if any, or
+    /// else the given error.
+    fn tls_error_or(&mut self, err: Error) -> Error {
+        match self.inner.get_mut().take_tls_error() {
+            Some(tls_err) => tls_error(tls_err),
+            None => err,
+        }
+    }
+}
+
+/// Converts the given TLS error to an `Error`, which is of kind `CertificateRejected` where a
+/// certificate was rejected, so that such failures can be told apart from others.
+fn tls_error(err: rustls::TLSError) -> Error {
+    if verify::is_certificate_error(&err) {
+        ErrorKind::CertificateRejected(err).into()
+    } else {
+        err.into()
     }
 }
 
@@ -59,15 +169,40 @@ impl ReceiveMessage for TlsConnection {
     where
         Msg: Message,
     {
-        self.complete_prior_io()?;
+        // Messages queued by `try_send` may not yet have been written.
+        self.inner.get_mut().write_tls()?;
 
-        if self.tls_session.get_ref().wants_read() {
-            self.complete_io()?;
+        let result = recv_common(
+            &mut self.inner,
+            &mut self.partial_line,
+            self.line_limits,
+            self.decoding_policy,
+        );
+
+        match result {
+            Err(err) => Err(self.tls_error_or(err)),
+            result => result,
         }
+    }
 
-        let msg = recv_common(&mut self.tls_session)?;
+    fn recv_with<F, T>(&mut self, f: F) -> Result<Option<T>>
+    where
+        F: FnOnce(MessageRef) -> T,
+    {
+        self.inner.get_mut().write_tls()?;
+
+        let result = recv_with_common(
+            &mut self.inner,
+            &mut self.partial_line,
+            self.line_limits,
+            self.decoding_policy,
+            f,
+        );
 
-        Ok(msg)
+        match result {
+            Err(err) => Err(self.tls_error_or(err)),
+            result => result,
+        }
     }
 }
 
@@ -76,32 +211,59 @@ impl SendMessage for TlsConnection {
     where
         Msg: Message,
     {
-        self.complete_prior_io()?;
+        try_send_common(self.inner.get_mut(), self.line_limits, msg)
+    }
+}
 
-        try_send_common(self.tls_session.get_mut(), msg)?;
+impl GetPeerAddr for TlsConnection {
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        self.inner.get_ref().tcp_stream().peer_addr().map_err(Into::into)
+    }
+}
 
-        self.complete_io()?;
+impl ConfigureLineLimits for TlsConnection {
+    fn line_limits(&self) -> LineLimits {
+        self.line_limits
+    }
 
-        Ok(())
+    fn set_line_limits(&mut self, limits: LineLimits) {
+        self.line_limits = limits
     }
 }
 
-impl GetPeerAddr for TlsConnection {
-    fn peer_addr(&self) -> Result<SocketAddr> {
-        Ok(self.tcp_stream.peer_addr()?)
+impl ConfigureDecoding for TlsConnection {
+    fn decoding_policy(&self) -> DecodingPolicy {
+        self.decoding_policy
+    }
+
+    fn set_decoding_policy(&mut self, policy: DecodingPolicy) {
+        self.decoding_policy = policy
     }
 }
 
 impl ConnectionPrivate for TlsConnection {
     fn mio_registerable(&self) -> &mio::event::Evented {
-        &self.tcp_stream
+        self.inner.get_ref().tcp_stream()
     }
 
     fn mio_registration_interest(&self) -> mio::Ready {
-        self.inner.ready_interest()
+        // The connection is registered once, with edge-triggered notification, so both kinds of
+        // readiness are of interest: the TLS session may need to write at any time, as to finish
+        // writing queued messages or to answer the server.
+        mio::Ready::readable() | mio::Ready::writable()
     }
 
     fn mio_poll_opts(&self) -> mio::PollOpt {
-        mio::PollOpt::level() | mio::PollOpt::oneshot()
+        mio::PollOpt::edge()
+    }
+
+    fn process_mio_event(&mut self, readiness: mio::Ready) -> Result<()> {
+        if readiness.is_writable() {
+            // The TLS session may hold queued messages or handshake data that the socket would
+            // not earlier accept.
+            self.inner.get_mut().write_tls()?;
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,241 @@
+use super::ClientCertificate;
+use super::verify::Verifier;
+use connection::ErrorKind;
+use connection::Result;
+use rustls;
+use std::borrow::Cow;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use webpki_roots;
+
+/// The files in which Unix-like systems commonly keep their bundles of trusted root certificates,
+/// in the order in which they are looked for.
+const SYSTEM_CA_BUNDLES: &[&str] = &[
+    // Debian, Ubuntu, Arch Linux, and Gentoo
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora and RHEL
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // openSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // Alpine Linux, macOS, and OpenBSD
+    "/etc/ssl/cert.pem",
+    // FreeBSD
+    "/usr/local/share/certs/ca-root-nss.crt",
+];
+
+/// A builder of the `rustls::ClientConfig` with which a [`TlsConnection`] verifies the server's
+/// certificate, and presents a client certificate, if any.
+///
+/// At least one source of trusted root certificates must be chosen; none are trusted by default.
+/// Where public keys are pinned, the server's certificate must both be verified against the trusted
+/// roots and have one of the pinned public keys.
+///
+/// A certificate that fails verification is reported by [`TlsConnection`] as an error of kind
+/// [`ErrorKind::CertificateRejected`], rather than as a network error.
+///
+/// [`TlsConnection`]: struct.TlsConnection.html
+/// [`ErrorKind::CertificateRejected`]: ../enum.ErrorKind.html#variant.CertificateRejected
+#[derive(Clone, Debug)]
+pub struct TlsOptions {
+    trust_sources: Vec<TrustSource>,
+    spki_pins: Vec<[u8; 32]>,
+    accept_invalid_certs: bool,
+    client_cert: Option<ClientCertificate>,
+}
+
+#[derive(Clone, Debug)]
+enum TrustSource {
+    WebpkiRoots,
+    SystemRoots,
+    PemBundle(Vec<u8>),
+    PemFile(PathBuf),
+}
+
+impl TlsOptions {
+    /// Constructs `TlsOptions` that trust no root certificates and pin no public keys.
+    pub fn new() -> Self {
+        TlsOptions {
+            trust_sources: Vec::new(),
+            spki_pins: Vec::new(),
+            accept_invalid_certs: false,
+            client_cert: None,
+        }
+    }
+
+    /// Trusts the root certificates of Mozilla's CA program, as bundled by the `webpki-roots`
+    /// crate.
+    pub fn webpki_roots(mut self) -> Self {
+        self.trust_sources.push(TrustSource::WebpkiRoots);
+        self
+    }
+
+    /// Trusts the root certificates of the operating system, as found in the file named by the
+    /// `SSL_CERT_FILE` environment variable or in the usual places on Unix-like systems.
+    ///
+    /// If no such file is found, [`build`] fails.
+    ///
+    /// [`build`]: #method.build
+    pub fn system_roots(mut self) -> Self {
+        self.trust_sources.push(TrustSource::SystemRoots);
+        self
+    }
+
+    /// Trusts the PEM-encoded root certificates in the given bundle, as of a network's own CA.
+    pub fn ca_bundle_pem<B>(mut self, pem: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        self.trust_sources.push(TrustSource::PemBundle(pem.into()));
+        self
+    }
+
+    /// Trusts the PEM-encoded root certificates in the file at the given path, which is read by
+    /// [`build`].
+    ///
+    /// [`build`]: #method.build
+    pub fn ca_bundle_file<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.trust_sources.push(TrustSource::PemFile(path.as_ref().to_owned()));
+        self
+    }
+
+    /// Pins the public key with the given SHA-256 hash of its DER-encoded `SubjectPublicKeyInfo`,
+    /// as in HTTP public key pinning. Where keys are pinned, the server's certificate is rejected
+    /// unless it has one of them, with the error `webpki::Error::UnknownIssuer`, as though it had
+    /// been issued by an untrusted CA.
+    ///
+    /// The hash of a PEM-encoded certificate's key can be found with OpenSSL:
+    ///
+    /// ```text
+    /// openssl x509 -in cert.pem -pubkey -noout | openssl pkey -pubin -outform der |
+    ///     openssl dgst -sha256
+    /// ```
+    pub fn pin_spki_sha256(mut self, hash: [u8; 32]) -> Self {
+        self.spki_pins.push(hash);
+        self
+    }
+
+    /// Accepts the server's certificate whether or not it is issued by a trusted root, is valid at
+    /// present, or is valid for the hostname, leaving the connection open to interception.
+    ///
+    /// This is meant only for test networks with self-signed certificates. Pinned public keys are
+    /// still required, so that pinning a self-signed certificate's key accepts that certificate
+    /// alone.
+    pub fn dangerously_accept_invalid_certs(mut self) -> Self {
+        self.accept_invalid_certs = true;
+        self
+    }
+
+    /// Presents the given certificate to servers that request a client certificate, as for SASL
+    /// `EXTERNAL` authentication.
+    pub fn client_certificate(mut self, cert: ClientCertificate) -> Self {
+        self.client_cert = Some(cert);
+        self
+    }
+
+    /// Constructs the configuration, reading and parsing the trusted root certificates.
+    ///
+    /// This fails with an error of kind [`ErrorKind::InvalidTlsOptions`] where no source of
+    /// trusted root certificates has been chosen, and invalid certificates are not to be accepted,
+    /// or where a source contains no valid certificates.
+    ///
+    /// [`ErrorKind::InvalidTlsOptions`]: ../enum.ErrorKind.html#variant.InvalidTlsOptions
+    pub fn build(self) -> Result<Arc<rustls::ClientConfig>> {
+        ensure!(
+            !self.trust_sources.is_empty() || self.accept_invalid_certs,
+            invalid("no source of trusted root certificates was chosen")
+        );
+
+        let mut config = rustls::ClientConfig::new();
+
+        for source in &self.trust_sources {
+            match *source {
+                TrustSource::WebpkiRoots => {
+                    config
+                        .root_store
+                        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+                }
+                TrustSource::SystemRoots => {
+                    let path = system_ca_bundle()?;
+                    add_pem_file(&mut config.root_store, &path)?;
+                }
+                TrustSource::PemBundle(ref pem) => add_pem(&mut config.root_store, pem)?,
+                TrustSource::PemFile(ref path) => add_pem_file(&mut config.root_store, path)?,
+            }
+        }
+
+        if self.accept_invalid_certs {
+            warn!(
+                "TLS certificate verification is disabled{}; connections may be intercepted.",
+                if self.spki_pins.is_empty() {
+                    ""
+                } else {
+                    ", except for public key pinning"
+                }
+            );
+        }
+
+        if self.accept_invalid_certs || !self.spki_pins.is_empty() {
+            config.dangerous().set_certificate_verifier(Arc::new(Verifier {
+                accept_invalid_certs: self.accept_invalid_certs,
+                spki_pins: self.spki_pins,
+            }));
+        }
+
+        if let Some(cert) = self.client_cert {
+            cert.configure(&mut config);
+        }
+
+        Ok(Arc::new(config))
+    }
+}
+
+impl Default for TlsOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the path of the operating system's bundle of trusted root certificates.
+fn system_ca_bundle() -> Result<PathBuf> {
+    if let Some(path) = env::var_os("SSL_CERT_FILE") {
+        return Ok(path.into());
+    }
+
+    match SYSTEM_CA_BUNDLES.iter().map(Path::new).find(|path| path.is_file()) {
+        Some(path) => Ok(path.to_owned()),
+        None => bail!(invalid("no bundle of the system's root certificates was found")),
+    }
+}
+
+fn add_pem_file(root_store: &mut rustls::RootCertStore, path: &Path) -> Result<()> {
+    let mut pem = Vec::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut pem))
+        .map_err(|err| {
+            invalid(format!("the CA bundle {:?} could not be read: {}", path, err))
+        })?;
+
+    add_pem(root_store, &pem)
+}
+
+fn add_pem(root_store: &mut rustls::RootCertStore, pem: &[u8]) -> Result<()> {
+    match root_store.add_pem_file(&mut &pem[..]) {
+        Ok((valid_count, _)) if valid_count > 0 => Ok(()),
+        _ => bail!(invalid("a CA bundle contains no valid PEM-encoded certificates")),
+    }
+}
+
+fn invalid<S>(desc: S) -> ErrorKind
+where
+    S: Into<Cow<'static, str>>,
+{
+    ErrorKind::InvalidTlsOptions(desc.into())
+}
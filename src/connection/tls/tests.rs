@@ -0,0 +1,535 @@
+#![cfg(test)]
+
+use super::*;
+use connection::Error;
+use connection::ErrorKind;
+use connection::GenericConnection;
+use connection::PlaintextConnection;
+use message::OwnedMessage;
+use ring;
+use rustls::Session;
+use rustls::TLSError;
+use rustls::internal::msgs::enums::AlertDescription;
+use rustls::internal::pemfile;
+use std::borrow::Cow;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+// The certificates in `testdata` were issued for these tests by a throwaway CA, `ca.pem`: the
+// server's, `server.pem`, for `localhost`, and the client's, `client.pem`, for client
+// authentication. They are valid until 2126.
+
+const CA_CERT: &[u8] = include_bytes!("testdata/ca.pem");
+const SERVER_CERT: &[u8] = include_bytes!("testdata/server.pem");
+const SERVER_KEY: &[u8] = include_bytes!("testdata/server.key");
+const CLIENT_CERT: &[u8] = include_bytes!("testdata/client.pem");
+const CLIENT_KEY: &[u8] = include_bytes!("testdata/client.key");
+
+/// The SHA-256 hash of the server's public key, as found with OpenSSL.
+const SERVER_PIN: [u8; 32] = [
+    0x98, 0xd4, 0x4b, 0x2b, 0xaf, 0xed, 0x34, 0x6c, 0xd3, 0x63, 0x85, 0x5a, 0x89, 0x35, 0xe7, 0x38,
+    0x94, 0xe4, 0x96, 0xb9, 0x83, 0x06, 0xe3, 0x59, 0xf5, 0x23, 0xcb, 0x73, 0x50, 0x6b, 0xae, 0xfe,
+];
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+fn server_config(client_auth: bool) -> Arc<rustls::ServerConfig> {
+    let mut config = rustls::ServerConfig::new();
+    let key = pemfile::rsa_private_keys(&mut &SERVER_KEY[..]).unwrap().remove(0);
+
+    config.set_single_cert(pemfile::certs(&mut &SERVER_CERT[..]).unwrap(), key);
+
+    if client_auth {
+        config.set_client_auth_roots(pemfile::certs(&mut &CA_CERT[..]).unwrap(), true);
+    }
+
+    Arc::new(config)
+}
+
+fn client_config() -> rustls::ClientConfig {
+    let mut config = rustls::ClientConfig::new();
+    config.root_store.add_pem_file(&mut &CA_CERT[..]).unwrap();
+    config
+}
+
+/// Accepts a connection on the loopback interface in another thread and, once the TLS handshake
+/// is complete, serves it with the given function.
+fn serve<F, T>(
+    config: Arc<rustls::ServerConfig>,
+    f: F,
+) -> (SocketAddr, thread::JoinHandle<Result<T>>)
+where
+    F: FnOnce(&mut rustls::ServerSession, &mut TcpStream) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut tcp_stream, _) = listener.accept()?;
+        let mut tls_session = rustls::ServerSession::new(&config);
+
+        inner::handshake(&mut tls_session, &mut tcp_stream)?;
+
+        Ok(f(&mut tls_session, &mut tcp_stream))
+    });
+
+    (addr, server)
+}
+
+fn server_send(tls_session: &mut rustls::ServerSession, tcp_stream: &mut TcpStream, bytes: &[u8]) {
+    tls_session.write_all(bytes).unwrap();
+
+    while tls_session.wants_write() {
+        tls_session.write_tls(tcp_stream).unwrap();
+    }
+}
+
+fn server_recv_line(
+    tls_session: &mut rustls::ServerSession,
+    tcp_stream: &mut TcpStream,
+) -> Vec<u8> {
+    let mut line = Vec::new();
+
+    while !line.ends_with(b"\n") {
+        let mut buf = [0; 512];
+        let len = tls_session.read(&mut buf).unwrap();
+
+        line.extend_from_slice(&buf[..len]);
+
+        if len == 0 {
+            assert!(tls_session.read_tls(tcp_stream).unwrap() > 0);
+            tls_session.process_new_packets().unwrap();
+        }
+    }
+
+    line
+}
+
+/// Accepts a plaintext connection on the loopback interface in another thread, waits for the
+/// client's `STARTTLS`, and sends the given reply; then, once the TLS handshake is complete,
+/// serves the connection with the given function.
+fn serve_starttls<F, T>(
+    reply: &'static [u8],
+    f: F,
+) -> (SocketAddr, thread::JoinHandle<Result<T>>)
+where
+    F: FnOnce(&mut rustls::ServerSession, &mut TcpStream) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut tcp_stream, _) = listener.accept()?;
+        let mut line = Vec::new();
+
+        // The line is read a byte at a time, so that none of the handshake is read with it.
+        while !line.ends_with(b"\n") {
+            let mut byte = [0];
+            tcp_stream.read_exact(&mut byte)?;
+            line.push(byte[0]);
+        }
+
+        assert_eq!(line, b"STARTTLS\r\n");
+
+        tcp_stream.write_all(reply)?;
+
+        let mut tls_session = rustls::ServerSession::new(&server_config(false));
+
+        inner::handshake(&mut tls_session, &mut tcp_stream)?;
+
+        Ok(f(&mut tls_session, &mut tcp_stream))
+    });
+
+    (addr, server)
+}
+
+/// Receives a message, waiting for one to arrive.
+fn recv<C>(connection: &mut C) -> Result<Option<OwnedMessage>>
+where
+    C: ReceiveMessage,
+{
+    loop {
+        match connection.recv() {
+            Err(Error(ErrorKind::Io(ref err), _)) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(10))
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Connects to a test server with the given options.
+fn connect_with(options: TlsOptions, hostname: &str) -> Result<()> {
+    let (addr, _server) = serve(server_config(false), |_, _| {});
+
+    TlsConnection::from_addr(addr, &options.build()?, hostname).map(|_| ())
+}
+
+fn is_certificate_rejected<T>(result: Result<T>) -> bool {
+    match result {
+        Err(Error(ErrorKind::CertificateRejected(_), _)) => true,
+        _ => false,
+    }
+}
+
+fn is_invalid_options<T>(result: Result<T>) -> bool {
+    match result {
+        Err(Error(ErrorKind::InvalidTlsOptions(_), _)) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn exchange_1() {
+    let (addr, server) = serve(server_config(false), |tls_session, tcp_stream| {
+        server_send(
+            tls_session,
+            tcp_stream,
+            b"PING :irc.example.com\r\n:irc.example.com NOTICE * :Hello\r\n",
+        );
+        server_recv_line(tls_session, tcp_stream)
+    });
+
+    let mut connection =
+        TlsConnection::from_addr(addr, &Arc::new(client_config()), "localhost").unwrap();
+
+    assert_eq!(
+        recv(&mut connection).unwrap().unwrap().as_bytes(),
+        b"PING :irc.example.com"
+    );
+    assert_eq!(
+        recv(&mut connection).unwrap().unwrap().as_bytes(),
+        &b":irc.example.com NOTICE * :Hello"[..]
+    );
+
+    connection.try_send(&msg(b"PONG :irc.example.com")).unwrap();
+
+    assert_eq!(server.join().unwrap().unwrap(), b"PONG :irc.example.com\r\n");
+}
+
+#[test]
+fn close_1() {
+    let (addr, server) = serve(server_config(false), |tls_session, tcp_stream| {
+        server_send(tls_session, tcp_stream, b"ERROR :Closing link\r\n");
+        tls_session.send_close_notify();
+        server_send(tls_session, tcp_stream, b"");
+    });
+
+    let mut connection =
+        TlsConnection::from_addr(addr, &Arc::new(client_config()), "localhost").unwrap();
+
+    server.join().unwrap().unwrap();
+
+    assert_eq!(
+        recv(&mut connection).unwrap().unwrap().as_bytes(),
+        b"ERROR :Closing link"
+    );
+    assert!(recv(&mut connection).unwrap().is_none());
+}
+
+#[test]
+fn untrusted_server_1() {
+    let (addr, _server) = serve(server_config(false), |_, _| {});
+
+    let config = Arc::new(rustls::ClientConfig::new());
+    let result = TlsConnection::from_addr(addr, &config, "localhost");
+
+    assert!(is_certificate_rejected(result));
+}
+
+#[test]
+fn wrong_hostname_1() {
+    let (addr, _server) = serve(server_config(false), |_, _| {});
+
+    let result = TlsConnection::from_addr(addr, &Arc::new(client_config()), "irc.example.com");
+
+    assert!(is_certificate_rejected(result));
+}
+
+#[test]
+fn client_certificate_1() {
+    let (addr, server) = serve(server_config(true), |tls_session, _| {
+        tls_session.get_peer_certificates().map(|chain| chain.len())
+    });
+
+    let mut config = client_config();
+
+    ClientCertificate::from_pem(CLIENT_CERT, CLIENT_KEY)
+        .unwrap()
+        .configure(&mut config);
+
+    let _connection = TlsConnection::from_addr(addr, &Arc::new(config), "localhost").unwrap();
+
+    assert_eq!(server.join().unwrap().unwrap(), Some(1));
+
+    // Without the certificate, the server rejects the client.
+    let (addr, server) = serve(server_config(true), |_, _| {});
+
+    let _ = TlsConnection::from_addr(addr, &Arc::new(client_config()), "localhost");
+
+    assert!(is_certificate_rejected(server.join().unwrap()));
+}
+
+#[test]
+fn certificate_alert_1() {
+    let alert = |description| verify::is_certificate_error(&TLSError::AlertReceived(description));
+
+    // The alerts with which a server rejects the client's certificate.
+    assert!(alert(AlertDescription::BadCertificate));
+    assert!(alert(AlertDescription::UnknownCA));
+    assert!(alert(AlertDescription::CertificateExpired));
+    assert!(!alert(AlertDescription::HandshakeFailure));
+    assert!(!alert(AlertDescription::DecodeError));
+}
+
+#[test]
+fn client_certificate_from_pem_1() {
+    let cert = ClientCertificate::from_pem(CLIENT_CERT, CLIENT_KEY).unwrap();
+    let debug = format!("{:?}", cert);
+
+    assert!(debug.contains("chain_len: 1"));
+    assert!(debug.contains("<redacted>"));
+
+    let mut config = rustls::ClientConfig::new();
+    cert.configure(&mut config);
+}
+
+#[test]
+fn client_certificate_invalid_1() {
+    for &(chain, key) in &[
+        (&b""[..], CLIENT_KEY),
+        (CLIENT_CERT, &b""[..]),
+        (CLIENT_CERT, CLIENT_CERT),
+        (CLIENT_KEY, CLIENT_KEY),
+    ] {
+        match ClientCertificate::from_pem(chain, key) {
+            Err(Error(ErrorKind::InvalidClientCertificate(_), _)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    match ClientCertificate::from_der(vec![b"not a certificate".to_vec()], b"nor a key".to_vec()) {
+        Err(Error(ErrorKind::InvalidClientCertificate(_), _)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn options_ca_bundle_1() {
+    assert!(connect_with(TlsOptions::new().ca_bundle_pem(CA_CERT), "localhost").is_ok());
+    assert!(is_certificate_rejected(
+        connect_with(TlsOptions::new().ca_bundle_pem(CA_CERT), "irc.example.com")
+    ));
+}
+
+#[test]
+fn options_webpki_roots_1() {
+    // The test CA is not among Mozilla's.
+    assert!(is_certificate_rejected(
+        connect_with(TlsOptions::new().webpki_roots(), "localhost")
+    ));
+    assert!(
+        connect_with(
+            TlsOptions::new().webpki_roots().ca_bundle_pem(CA_CERT),
+            "localhost",
+        ).is_ok()
+    );
+}
+
+#[test]
+fn options_pin_1() {
+    let mut wrong_pin = SERVER_PIN;
+    wrong_pin[0] ^= 1;
+
+    assert!(
+        connect_with(
+            TlsOptions::new()
+                .ca_bundle_pem(CA_CERT)
+                .pin_spki_sha256(wrong_pin)
+                .pin_spki_sha256(SERVER_PIN),
+            "localhost",
+        ).is_ok()
+    );
+    assert!(is_certificate_rejected(connect_with(
+        TlsOptions::new().ca_bundle_pem(CA_CERT).pin_spki_sha256(wrong_pin),
+        "localhost",
+    )));
+
+    // A pinned key does not stand in for verification against the trusted roots.
+    assert!(is_certificate_rejected(connect_with(
+        TlsOptions::new().webpki_roots().pin_spki_sha256(SERVER_PIN),
+        "localhost",
+    )));
+}
+
+#[test]
+fn options_accept_invalid_certs_1() {
+    let mut wrong_pin = SERVER_PIN;
+    wrong_pin[31] ^= 1;
+
+    assert!(
+        connect_with(
+            TlsOptions::new().dangerously_accept_invalid_certs(),
+            "irc.example.com",
+        ).is_ok()
+    );
+    assert!(
+        connect_with(
+            TlsOptions::new()
+                .dangerously_accept_invalid_certs()
+                .pin_spki_sha256(SERVER_PIN),
+            "irc.example.com",
+        ).is_ok()
+    );
+    assert!(is_certificate_rejected(connect_with(
+        TlsOptions::new()
+            .dangerously_accept_invalid_certs()
+            .pin_spki_sha256(wrong_pin),
+        "irc.example.com",
+    )));
+}
+
+#[test]
+fn options_client_certificate_1() {
+    let (addr, server) = serve(server_config(true), |tls_session, _| {
+        tls_session.get_peer_certificates().map(|chain| chain.len())
+    });
+
+    let config = TlsOptions::new()
+        .ca_bundle_pem(CA_CERT)
+        .client_certificate(ClientCertificate::from_pem(CLIENT_CERT, CLIENT_KEY).unwrap())
+        .build()
+        .unwrap();
+
+    let _connection = TlsConnection::from_addr(addr, &config, "localhost").unwrap();
+
+    assert_eq!(server.join().unwrap().unwrap(), Some(1));
+}
+
+#[test]
+fn options_invalid_1() {
+    assert!(is_invalid_options(TlsOptions::new().build()));
+    assert!(is_invalid_options(TlsOptions::new().ca_bundle_pem(&b""[..]).build()));
+    assert!(is_invalid_options(TlsOptions::new().ca_bundle_pem(CLIENT_KEY).build()));
+    assert!(is_invalid_options(
+        TlsOptions::new()
+            .ca_bundle_file("testdata/no-such-bundle.pem")
+            .build()
+    ));
+}
+
+#[test]
+fn spki_1() {
+    let server_cert = pemfile::certs(&mut &SERVER_CERT[..]).unwrap().remove(0);
+    let spki = verify::spki(&server_cert.0).unwrap();
+
+    assert_eq!(ring::digest::digest(&ring::digest::SHA256, spki).as_ref(), &SERVER_PIN[..]);
+
+    assert!(verify::spki(b"").is_none());
+    assert!(verify::spki(&server_cert.0[..server_cert.0.len() / 2]).is_none());
+    assert!(verify::spki(&[0x30, 0x84, 0xff, 0xff, 0xff, 0xff]).is_none());
+}
+
+#[test]
+fn starttls_1() {
+    let (addr, server) = serve_starttls(
+        b":irc.example.com 670 nick :STARTTLS successful, go ahead with TLS handshake\r\n",
+        |tls_session, tcp_stream| {
+            server_send(tls_session, tcp_stream, b"PING :irc.example.com\r\n");
+            server_recv_line(tls_session, tcp_stream)
+        },
+    );
+
+    let mut connection = GenericConnection::from(PlaintextConnection::from_addr(addr).unwrap());
+
+    connection.try_send(&msg(b"STARTTLS")).unwrap();
+
+    assert_eq!(
+        recv(&mut connection).unwrap().unwrap().command_bytes(),
+        b"670"
+    );
+
+    connection
+        .start_tls(&Arc::new(client_config()), "localhost")
+        .unwrap();
+
+    assert_eq!(
+        recv(&mut connection).unwrap().unwrap().as_bytes(),
+        b"PING :irc.example.com"
+    );
+
+    connection.try_send(&msg(b"PONG :irc.example.com")).unwrap();
+
+    assert_eq!(server.join().unwrap().unwrap(), b"PONG :irc.example.com\r\n");
+
+    // The connection cannot be upgraded twice.
+    match connection.start_tls(&Arc::new(client_config()), "localhost") {
+        Err(Error(ErrorKind::StartTlsFailed(_), _)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn starttls_injected_plaintext_1() {
+    let (addr, _server) = serve_starttls(
+        b":irc.example.com 670 nick :STARTTLS successful\r\n\
+          :evil!evil@example.com PRIVMSG nick :This came in plaintext\r\n",
+        |_, _| {},
+    );
+
+    let tcp_stream = TcpStream::connect(addr).unwrap();
+    let peeker = tcp_stream.try_clone().unwrap();
+    let mut connection = PlaintextConnection::from_tcp_stream(tcp_stream).unwrap();
+
+    connection.try_send(&msg(b"STARTTLS")).unwrap();
+
+    // Both lines are waited for before either is received, so that the second is read into the
+    // connection's buffer with the first. The clone shares the connection's blocking mode.
+    peeker.set_nonblocking(false).unwrap();
+
+    loop {
+        let mut buffer = [0; 256];
+        let len = peeker.peek(&mut buffer).unwrap();
+
+        if buffer[..len].iter().filter(|&&b| b == b'\n').count() == 2 {
+            break;
+        }
+    }
+
+    peeker.set_nonblocking(true).unwrap();
+
+    assert_eq!(
+        recv(&mut connection).unwrap().unwrap().command_bytes(),
+        b"670"
+    );
+
+    match connection.start_tls(&Arc::new(client_config()), "localhost") {
+        Err(Error(ErrorKind::StartTlsFailed(_), _)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn starttls_untrusted_server_1() {
+    let (addr, _server) = serve_starttls(b":irc.example.com 670 nick :Go ahead\r\n", |_, _| {});
+
+    let mut connection = PlaintextConnection::from_addr(addr).unwrap();
+
+    connection.try_send(&msg(b"STARTTLS")).unwrap();
+    recv(&mut connection).unwrap().unwrap();
+
+    let mut connection = connection
+        .start_tls(&Arc::new(rustls::ClientConfig::new()), "localhost")
+        .unwrap();
+
+    // The handshake is completed, and the certificate rejected, as the connection is used.
+    assert!(is_certificate_rejected(recv(&mut connection)));
+}
@@ -0,0 +1,159 @@
+//! Verification of servers' certificates, for the options that `rustls` does not itself provide:
+//! public key pinning, and accepting invalid certificates.
+
+use ring::constant_time;
+use ring::digest;
+use rustls;
+use rustls::internal::msgs::enums::AlertDescription;
+use std::result;
+use webpki;
+
+const SEQUENCE_TAG: u8 = 0x30;
+
+/// The tag of the version field of an X.509 certificate, which is explicitly tagged `[0]`.
+const VERSION_TAG: u8 = 0xa0;
+
+/// A verifier of servers' certificates that may require the server's public key to be pinned, and
+/// that may skip verifying the certificate against the trusted roots and the hostname.
+#[derive(Debug)]
+pub(super) struct Verifier {
+    pub(super) accept_invalid_certs: bool,
+    pub(super) spki_pins: Vec<[u8; 32]>,
+}
+
+impl rustls::ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        roots: &rustls::RootCertStore,
+        presented_certs: &[rustls::Certificate],
+        dns_name: &str,
+    ) -> result::Result<rustls::ServerCertVerified, rustls::TLSError> {
+        if !self.accept_invalid_certs {
+            rustls::WebPKIVerifier {}.verify_server_cert(roots, presented_certs, dns_name)?;
+        }
+
+        if !self.spki_pins.is_empty() {
+            let end_entity = match presented_certs.first() {
+                Some(cert) => cert,
+                None => return Err(rustls::TLSError::NoCertificatesPresented),
+            };
+
+            // A certificate that cannot be parsed has no public key that could match a pin.
+            let is_pinned = spki(&end_entity.0).map_or(false, |spki| {
+                let spki_hash = digest::digest(&digest::SHA256, spki);
+
+                self.spki_pins.iter().any(|pin| {
+                    constant_time::verify_slices_are_equal(pin, spki_hash.as_ref()).is_ok()
+                })
+            });
+
+            // A certificate without a pinned key is not trusted, as one issued by an unknown CA is
+            // not; `rustls` has no error specific to pinning.
+            if !is_pinned {
+                warn!("The public key of {:?} matches none of the pinned keys.", dns_name);
+                return Err(rustls::TLSError::WebPKIError(webpki::Error::UnknownIssuer));
+            }
+        }
+
+        Ok(rustls::ServerCertVerified::assertion())
+    }
+}
+
+/// Returns whether the given TLS error is the rejection of a certificate, whether by this end of
+/// the connection or by the other, as where the server rejects the client certificate.
+pub(super) fn is_certificate_error(err: &rustls::TLSError) -> bool {
+    match *err {
+        rustls::TLSError::NoCertificatesPresented |
+        rustls::TLSError::WebPKIError(_) => true,
+        rustls::TLSError::AlertReceived(alert) => is_certificate_alert(alert),
+        _ => false,
+    }
+}
+
+/// Returns whether the given alert is sent to reject a certificate.
+fn is_certificate_alert(alert: AlertDescription) -> bool {
+    match alert {
+        AlertDescription::BadCertificate |
+        AlertDescription::UnsupportedCertificate |
+        AlertDescription::CertificateRevoked |
+        AlertDescription::CertificateExpired |
+        AlertDescription::CertificateUnknown |
+        AlertDescription::UnknownCA => true,
+        _ => false,
+    }
+}
+
+/// Returns the DER-encoded `SubjectPublicKeyInfo` of the given DER-encoded X.509 certificate, as
+/// is hashed to make a public key pin, or `None` where the certificate cannot be parsed.
+pub(super) fn spki(cert: &[u8]) -> Option<&[u8]> {
+    DerElements(cert)
+        .next()
+        .and_then(|certificate| DerElements(certificate.contents).next())
+        .and_then(|tbs_certificate| {
+            // The public key follows the optional version, the serial number, the signature
+            // algorithm, the issuer, the validity period, and the subject.
+            DerElements(tbs_certificate.contents)
+                .filter(|field| field.tag != VERSION_TAG)
+                .nth(5)
+        })
+        .and_then(|spki| if spki.tag == SEQUENCE_TAG {
+            Some(spki.encoding)
+        } else {
+            None
+        })
+}
+
+/// An element of DER-encoded data.
+struct DerElement<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    encoding: &'a [u8],
+}
+
+/// An iterator over the consecutive DER elements in some data, which ends at the first element
+/// that cannot be parsed.
+struct DerElements<'a>(&'a [u8]);
+
+impl<'a> Iterator for DerElements<'a> {
+    type Item = DerElement<'a>;
+
+    fn next(&mut self) -> Option<DerElement<'a>> {
+        let data = self.0;
+
+        if data.len() < 2 {
+            return None;
+        }
+
+        let (len, header_len) = match data[1] {
+            len @ 0...0x7f => (len as usize, 2),
+            len_len @ 0x81...0x84 => {
+                let header_len = 2 + (len_len & 0x7f) as usize;
+
+                if data.len() < header_len {
+                    return None;
+                }
+
+                let len = data[2..header_len]
+                    .iter()
+                    .fold(0, |len, &byte| len << 8 | byte as usize);
+
+                (len, header_len)
+            }
+            _ => return None,
+        };
+
+        if data.len() - header_len < len {
+            return None;
+        }
+
+        let (encoding, rest) = data.split_at(header_len + len);
+
+        self.0 = rest;
+
+        Some(DerElement {
+            tag: data[0],
+            contents: &encoding[header_len..],
+            encoding,
+        })
+    }
+}
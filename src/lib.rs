@@ -1,9 +1,13 @@
+extern crate base64;
 extern crate mio;
 extern crate parking_lot;
+extern crate ring;
 extern crate rustls;
 extern crate smallvec;
 extern crate string_cache;
 extern crate uuid;
+extern crate webpki;
+extern crate webpki_roots;
 
 #[macro_use]
 extern crate error_chain;
@@ -22,6 +26,7 @@ extern crate pircolate;
 extern crate quickcheck;
 
 pub use self::message::Message;
+pub use self::message::OwnedMessage;
 
 pub mod connection;
 pub mod client;
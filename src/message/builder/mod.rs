@@ -0,0 +1,197 @@
+use super::Command;
+use super::ErrorKind;
+use super::Message;
+use super::Result;
+use super::layout::is_valid_command;
+use super::tags;
+use std::borrow::Cow;
+
+mod tests;
+
+/// The maximum number of parameters a message may have, per RFC 2812, section 2.3.
+const MAX_PARAMS: usize = 15;
+
+/// A builder of outgoing IRC messages.
+///
+/// The builder checks the components of the message when [`build`] is called, and refuses to
+/// build a message that would not parse as intended. In particular, no component may contain a
+/// NUL, carriage return, or line feed character, so a string from an untrusted source cannot be
+/// used to inject additional lines into the connection.
+///
+/// The last parameter is introduced with a colon if it needs one, i.e., if it is empty, contains
+/// a space, or starts with a colon. Every other parameter must be non-empty and must not contain
+/// a space or start with a colon.
+///
+/// [`build`]: #method.build
+#[derive(Clone, Debug)]
+pub struct MessageBuilder {
+    tags: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    prefix: Option<Vec<u8>>,
+    command: Vec<u8>,
+    params: Vec<Vec<u8>>,
+}
+
+impl MessageBuilder {
+    pub fn new(command: Command) -> Self {
+        MessageBuilder {
+            tags: Vec::new(),
+            prefix: None,
+            command: command.to_bytes().into_owned(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Adds an IRCv3 tag with the given key and value. The value should not be escaped; it will
+    /// be escaped as the message is built.
+    pub fn tag<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.tags.push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    /// Adds an IRCv3 tag with the given key and no value.
+    pub fn valueless_tag<K>(mut self, key: K) -> Self
+    where
+        K: AsRef<[u8]>,
+    {
+        self.tags.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    /// Sets the message's prefix, which should not include the leading `:`.
+    pub fn prefix<P>(mut self, prefix: P) -> Self
+    where
+        P: AsRef<[u8]>,
+    {
+        self.prefix = Some(prefix.as_ref().to_owned());
+        self
+    }
+
+    /// Appends a parameter.
+    pub fn param<P>(mut self, param: P) -> Self
+    where
+        P: AsRef<[u8]>,
+    {
+        self.params.push(param.as_ref().to_owned());
+        self
+    }
+
+    /// Appends each of the given parameters, in order.
+    pub fn params<I>(mut self, params: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        self.params
+            .extend(params.into_iter().map(|p| p.as_ref().to_owned()));
+        self
+    }
+
+    /// Builds the message, without the terminating CR-LF sequence.
+    pub fn build<Msg>(&self) -> Result<Msg>
+    where
+        Msg: Message,
+    {
+        Msg::try_from(Cow::Owned(self.build_bytes()?))
+    }
+
+    fn build_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        if !self.tags.is_empty() {
+            let section = tags::serialize(self.tags.iter().map(|&(ref key, ref value)| {
+                (&key[..], value.as_ref().map(|v| &v[..]))
+            }))?;
+
+            bytes.push(b'@');
+            bytes.extend_from_slice(&section);
+            bytes.push(b' ');
+        }
+
+        if let Some(ref prefix) = self.prefix {
+            check_forbidden_chars("prefix", prefix)?;
+            ensure!(
+                !prefix.is_empty() && !prefix.contains(&b' '),
+                invalid("prefix", prefix, "must be non-empty and contain no spaces")
+            );
+
+            bytes.push(b':');
+            bytes.extend_from_slice(prefix);
+            bytes.push(b' ');
+        }
+
+        ensure!(
+            !self.command.is_empty() && is_valid_command(&self.command),
+            invalid(
+                "command",
+                &self.command,
+                "must be a word of ASCII letters or a three-digit number",
+            )
+        );
+
+        bytes.extend_from_slice(&self.command);
+
+        ensure!(
+            self.params.len() <= MAX_PARAMS,
+            ErrorKind::InvalidComponent(
+                "parameter list",
+                Vec::new(),
+                format!(
+                    "has {} parameters, but at most {} are allowed",
+                    self.params.len(),
+                    MAX_PARAMS
+                ).into(),
+            )
+        );
+
+        if let Some((last, middles)) = self.params.split_last() {
+            for middle in middles {
+                check_forbidden_chars("parameter", middle)?;
+                ensure!(
+                    !middle.is_empty() && !middle.contains(&b' ') && !middle.starts_with(b":"),
+                    invalid(
+                        "parameter",
+                        middle,
+                        "must be non-empty, contain no spaces, and not start with a colon, as it \
+                         is not the last parameter",
+                    )
+                );
+
+                bytes.push(b' ');
+                bytes.extend_from_slice(middle);
+            }
+
+            check_forbidden_chars("parameter", last)?;
+
+            bytes.push(b' ');
+
+            if last.is_empty() || last.contains(&b' ') || last.starts_with(b":") {
+                bytes.push(b':');
+            }
+
+            bytes.extend_from_slice(last);
+        }
+
+        Ok(bytes)
+    }
+}
+
+fn invalid(component: &'static str, bytes: &[u8], desc: &'static str) -> ErrorKind {
+    ErrorKind::InvalidComponent(component, bytes.to_owned(), desc.into())
+}
+
+fn check_forbidden_chars(component: &'static str, bytes: &[u8]) -> Result<()> {
+    ensure!(
+        !bytes.iter().any(|&b| b == b'\0' || b == b'\r' || b == b'\n'),
+        invalid(
+            component,
+            bytes,
+            "must not contain NUL, carriage return, or line feed characters",
+        )
+    );
+
+    Ok(())
+}
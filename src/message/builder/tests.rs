@@ -0,0 +1,101 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use quickcheck::TestResult;
+
+fn build(builder: MessageBuilder) -> Result<Vec<u8>> {
+    builder.build::<OwnedMessage>().map(OwnedMessage::into_bytes)
+}
+
+#[test]
+fn build_1() {
+    assert_eq!(
+        build(
+            MessageBuilder::new(Command::Privmsg)
+                .tag("+example.com/reply", "a;b")
+                .valueless_tag("+draft/typing")
+                .prefix("nick!user@host")
+                .params(&["#chan", "Hello, world!"]),
+        ).unwrap(),
+        &b"@+example.com/reply=a\\:b;+draft/typing :nick!user@host PRIVMSG #chan :Hello, world!"[..]
+    );
+}
+
+#[test]
+fn build_trailing_colon_1() {
+    for &(last, expected) in &[
+        ("word", &b"TOPIC #chan word"[..]),
+        ("two words", b"TOPIC #chan :two words"),
+        ("", b"TOPIC #chan :"),
+        (":colon", b"TOPIC #chan ::colon"),
+        ("in:side", b"TOPIC #chan in:side"),
+    ] {
+        assert_eq!(
+            build(MessageBuilder::new(Command::Topic).param("#chan").param(last)).unwrap(),
+            expected
+        );
+    }
+
+    assert_eq!(build(MessageBuilder::new(Command::Quit)).unwrap(), &b"QUIT"[..]);
+    assert_eq!(
+        build(MessageBuilder::new(Command::Other(b"cap")).params(&["LS", "302"])).unwrap(),
+        &b"cap LS 302"[..]
+    );
+}
+
+#[test]
+fn reject_invalid_1() {
+    let privmsg = || MessageBuilder::new(Command::Privmsg);
+
+    for builder in vec![
+        privmsg().params(&["#chan", "hi\r\nQUIT :injected"]),
+        privmsg().params(&["#chan", "hi\nQUIT"]),
+        privmsg().params(&["#chan", "nul\0"]),
+        privmsg().params(&["#chan\r\n", "hi"]),
+        privmsg().params(&["#chan one", "hi"]),
+        privmsg().params(&["", "hi"]),
+        privmsg().params(&[":chan", "hi"]),
+        privmsg().prefix("").param("hi"),
+        privmsg().prefix("nick name").param("hi"),
+        privmsg().prefix("nick\n").param("hi"),
+        privmsg().tag("bad key", "value").param("hi"),
+        privmsg().tag("key", "nul\0").param("hi"),
+        privmsg().params(vec!["x"; MAX_PARAMS + 1]),
+        MessageBuilder::new(Command::Other(b"TWO WORDS")),
+        MessageBuilder::new(Command::Other(b"")),
+    ] {
+        assert!(
+            build(builder.clone()).is_err(),
+            "Built invalid message from {:?}",
+            builder
+        );
+    }
+}
+
+quickcheck! {
+    fn build_roundtrip_1(params: Vec<String>) -> TestResult {
+        if params.len() > MAX_PARAMS {
+            return TestResult::discard();
+        }
+
+        let has_forbidden_chars =
+            params.iter().any(|p| p.contains(|c| c == '\0' || c == '\r' || c == '\n'));
+        let has_invalid_middle = params.iter().rev().skip(1).any(|p| {
+            p.is_empty() || p.contains(' ') || p.starts_with(':')
+        });
+
+        let result = MessageBuilder::new(Command::Privmsg)
+            .params(&params)
+            .build::<OwnedMessage>();
+
+        match result {
+            Ok(msg) => TestResult::from_bool(
+                !has_forbidden_chars && !has_invalid_middle &&
+                    msg.params().collect::<Vec<_>>() ==
+                        params.iter().map(|p| p.as_bytes()).collect::<Vec<_>>()
+            ),
+            Err(_) => TestResult::from_bool(has_forbidden_chars || has_invalid_middle),
+        }
+    }
+}
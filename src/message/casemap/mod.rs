@@ -0,0 +1,202 @@
+//! Case-insensitive comparison of nicknames and channel names.
+//!
+//! IRC servers compare nicknames and channel names without regard to case, under a case mapping
+//! that the server advertises with the `CASEMAPPING` token of `RPL_ISUPPORT`. Under the
+//! traditional mappings, certain punctuation characters are considered the lower-case forms of
+//! others, following the Scandinavian origins of IRC; thus, for example, `[foo]` and `{FOO}` are
+//! the same nickname under the `rfc1459` mapping.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash;
+use string_cache::DefaultAtom as CachedString;
+
+mod tests;
+
+/// A case mapping, which determines which nicknames and channel names are considered equal.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CaseMapping {
+    /// Only the ASCII letters are case-folded.
+    Ascii,
+
+    /// The ASCII letters are case-folded, and `[`, `]`, `\`, and `^` are considered the
+    /// upper-case forms of `{`, `}`, `|`, and `~`, respectively. This is the mapping specified by
+    /// RFC 1459, and is the default.
+    Rfc1459,
+
+    /// Like `Rfc1459`, but without considering `~` and `^` equivalent.
+    StrictRfc1459,
+}
+
+impl Default for CaseMapping {
+    fn default() -> Self {
+        CaseMapping::Rfc1459
+    }
+}
+
+impl CaseMapping {
+    /// Identifies the case mapping with the given name, as given in the `CASEMAPPING` token of
+    /// `RPL_ISUPPORT`. Returns `None` for unknown mappings, such as `rfc7613`.
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"ascii" => Some(CaseMapping::Ascii),
+            b"rfc1459" => Some(CaseMapping::Rfc1459),
+            b"strict-rfc1459" => Some(CaseMapping::StrictRfc1459),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the case mapping, as given in the `CASEMAPPING` token of
+    /// `RPL_ISUPPORT`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CaseMapping::Ascii => "ascii",
+            CaseMapping::Rfc1459 => "rfc1459",
+            CaseMapping::StrictRfc1459 => "strict-rfc1459",
+        }
+    }
+
+    /// Returns the lower-case form of the given byte under this case mapping.
+    pub fn fold_byte(&self, b: u8) -> u8 {
+        if b.is_ascii_uppercase() {
+            return b.to_ascii_lowercase();
+        }
+
+        match (*self, b) {
+            (CaseMapping::Ascii, _) => b,
+            (_, b'[') => b'{',
+            (_, b']') => b'}',
+            (_, b'\\') => b'|',
+            (CaseMapping::Rfc1459, b'^') => b'~',
+            _ => b,
+        }
+    }
+
+    /// Returns the lower-case form of the given string under this case mapping. Only ASCII
+    /// characters are affected, so the result is valid UTF-8 if the input is.
+    pub fn fold<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        if s.bytes().all(|b| self.fold_byte(b) == b) {
+            return Cow::Borrowed(s);
+        }
+
+        let folded = s.bytes().map(|b| self.fold_byte(b)).collect();
+
+        // Only ASCII bytes are changed, and they are changed to ASCII bytes, so the result is
+        // valid UTF-8.
+        Cow::Owned(String::from_utf8(folded).unwrap())
+    }
+
+    /// Returns whether the given byte strings are equal under this case mapping.
+    pub fn eq_ignore_case(&self, a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() &&
+            a.iter()
+                .zip(b)
+                .all(|(&x, &y)| self.fold_byte(x) == self.fold_byte(y))
+    }
+}
+
+macro_rules! casemapped_names {
+    ($($(#[$attr:meta])* pub struct $name:ident;)*) => {$(
+        $(#[$attr])*
+        ///
+        /// Names are compared, ordered, and hashed by their lower-case forms under the case
+        /// mapping with which they were constructed. Names constructed under different case
+        /// mappings should not be compared.
+        #[derive(Clone)]
+        pub struct $name {
+            name: CachedString,
+            folded: CachedString,
+            casemapping: CaseMapping,
+        }
+
+        impl $name {
+            pub fn new<S>(name: S, casemapping: CaseMapping) -> Self
+            where
+                S: Into<CachedString>,
+            {
+                let name = name.into();
+                let folded = casemapping.fold(&name).into_owned().into();
+
+                $name {
+                    name,
+                    folded,
+                    casemapping,
+                }
+            }
+
+            /// Returns the name as it was given.
+            pub fn as_str(&self) -> &str {
+                &self.name
+            }
+
+            /// Returns the lower-case form of the name, under which it is compared.
+            pub fn folded(&self) -> &str {
+                &self.folded
+            }
+
+            pub fn casemapping(&self) -> CaseMapping {
+                self.casemapping
+            }
+
+            /// Returns whether this name is equal to the given name under this name's case
+            /// mapping.
+            pub fn matches(&self, other: &str) -> bool {
+                self.casemapping
+                    .eq_ignore_case(self.name.as_bytes(), other.as_bytes())
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.folded == other.folded
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.folded().cmp(other.folded())
+            }
+        }
+
+        impl hash::Hash for $name {
+            fn hash<H>(&self, state: &mut H)
+            where
+                H: hash::Hasher,
+            {
+                self.folded.hash(state)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_tuple(stringify!($name))
+                    .field(&self.as_str())
+                    .field(&self.casemapping)
+                    .finish()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    )*};
+}
+
+casemapped_names! {
+    /// A nickname.
+    pub struct Nickname;
+
+    /// A channel name, including its leading channel type character, such as `#`.
+    pub struct ChannelName;
+}
@@ -0,0 +1,74 @@
+#![cfg(test)]
+
+use super::*;
+use std::collections::HashSet;
+
+#[test]
+fn fold_1() {
+    assert_eq!(CaseMapping::Ascii.fold("[Foo]^\\"), "[foo]^\\");
+    assert_eq!(CaseMapping::Rfc1459.fold("[Foo]^\\"), "{foo}~|");
+    assert_eq!(CaseMapping::StrictRfc1459.fold("[Foo]^\\"), "{foo}^|");
+    assert_eq!(CaseMapping::Rfc1459.fold("ÀÉ"), "ÀÉ");
+
+    match CaseMapping::Rfc1459.fold("{foo}") {
+        Cow::Borrowed(_) => {}
+        Cow::Owned(_) => panic!("Copied a string that was already in lower case"),
+    }
+}
+
+#[test]
+fn casemapping_names_1() {
+    for &mapping in &[
+        CaseMapping::Ascii,
+        CaseMapping::Rfc1459,
+        CaseMapping::StrictRfc1459,
+    ] {
+        assert_eq!(CaseMapping::from_name(mapping.name().as_bytes()), Some(mapping));
+    }
+
+    assert_eq!(CaseMapping::from_name(b"rfc7613"), None);
+    assert_eq!(CaseMapping::default(), CaseMapping::Rfc1459);
+}
+
+#[test]
+fn nickname_eq_1() {
+    let nick = |name| Nickname::new(name, CaseMapping::Rfc1459);
+
+    assert_eq!(nick("[Alice]"), nick("{alice}"));
+    assert_eq!(nick("Bob^"), nick("bob~"));
+    assert_ne!(nick("Alice"), nick("Alicia"));
+    assert_ne!(
+        Nickname::new("Bob^", CaseMapping::StrictRfc1459),
+        Nickname::new("bob~", CaseMapping::StrictRfc1459)
+    );
+    assert_eq!(nick("[Alice]").as_str(), "[Alice]");
+    assert!(nick("[Alice]").matches("{ALICE}"));
+
+    let ignored = vec![nick("[Spammer]"), nick("Troll\\")]
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    assert!(ignored.contains(&nick("{spammer}")));
+    assert!(ignored.contains(&nick("TROLL|")));
+    assert!(!ignored.contains(&nick("friend")));
+}
+
+#[test]
+fn channel_name_eq_1() {
+    let ascii = |name| ChannelName::new(name, CaseMapping::Ascii);
+
+    assert_eq!(ascii("#Rust"), ascii("#rust"));
+    assert_ne!(ascii("#[rust]"), ascii("#{rust}"));
+}
+
+quickcheck! {
+    fn eq_ignore_case_matches_fold_1(a: String, b: String) -> bool {
+        [CaseMapping::Ascii, CaseMapping::Rfc1459, CaseMapping::StrictRfc1459]
+            .iter()
+            .all(|mapping| {
+                mapping.eq_ignore_case(a.as_bytes(), b.as_bytes()) ==
+                    (mapping.fold(&a) == mapping.fold(&b)) &&
+                    mapping.eq_ignore_case(a.as_bytes(), mapping.fold(&a).as_bytes())
+            })
+    }
+}
@@ -0,0 +1,302 @@
+//! Constructors of common outgoing messages.
+//!
+//! Each function here builds a message with a [`MessageBuilder`], and so shares its checks. In
+//! addition, arguments that name things, such as nicknames, channels, and modes, must be single
+//! words: they must be non-empty, must not contain spaces, and must not start with colons.
+//! Arguments that are lists of names must not contain commas in their elements. Free-form text,
+//! such as the text of a `PRIVMSG` or the reason given in a `QUIT`, is not so restricted.
+//!
+//! [`MessageBuilder`]: ../struct.MessageBuilder.html
+
+use super::BODY_MAX_LEN;
+use super::Command;
+use super::ErrorKind;
+use super::Message;
+use super::MessageBuilder;
+use super::Result;
+
+mod tests;
+
+/// The maximum length of a hostname, as a domain name: RFC 1035, section 2.3.4, limits domain names
+/// to 255 octets, and only their labels to 63. RFC 2812 would limit hostnames to 63 characters, but
+/// notes that hostnames can be longer, and servers show such hostnames whole.
+pub const HOST_MAX_LEN: usize = 255;
+
+pub fn privmsg<Msg>(target: &str, text: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Privmsg)
+        .param(word("target", target)?)
+        .param(text)
+        .build()
+}
+
+pub fn notice<Msg>(target: &str, text: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Notice)
+        .param(word("target", target)?)
+        .param(text)
+        .build()
+}
+
+/// Constructs as many `PRIVMSG` messages as are needed to send the given text to the given target
+/// without exceeding the length limit of the IRC protocol. See [`split_text`] for how the text is
+/// divided.
+///
+/// The limit applies to the message as the server will relay it to the target, which includes the
+/// sender's prefix. `sender_prefix_len` should be the length of the sender's `nick!user@host`
+/// prefix; where the sender's hostname is not known, [`HOST_MAX_LEN`] may be used in its place.
+///
+/// [`split_text`]: fn.split_text.html
+/// [`HOST_MAX_LEN`]: constant.HOST_MAX_LEN.html
+pub fn privmsg_split<Msg>(sender_prefix_len: usize, target: &str, text: &str) -> Result<Vec<Msg>>
+where
+    Msg: Message,
+{
+    split_into(Command::Privmsg, sender_prefix_len, target, text)
+}
+
+/// Like [`privmsg_split`], but constructs `NOTICE` messages.
+///
+/// [`privmsg_split`]: fn.privmsg_split.html
+pub fn notice_split<Msg>(sender_prefix_len: usize, target: &str, text: &str) -> Result<Vec<Msg>>
+where
+    Msg: Message,
+{
+    split_into(Command::Notice, sender_prefix_len, target, text)
+}
+
+/// Constructs a message joining the given channels, with the given keys. Keys are matched to
+/// channels in order, so channels that need keys should be listed first.
+pub fn join<Msg>(channels: &[&str], keys: &[&str]) -> Result<Msg>
+where
+    Msg: Message,
+{
+    ensure!(
+        keys.len() <= channels.len(),
+        ErrorKind::InvalidComponent(
+            "key list",
+            keys.join(",").into_bytes(),
+            "has more keys than there are channels".into(),
+        )
+    );
+
+    let mut builder = MessageBuilder::new(Command::Join).param(comma_list("channel", channels)?);
+
+    if !keys.is_empty() {
+        builder = builder.param(comma_list("key", keys)?);
+    }
+
+    builder.build()
+}
+
+pub fn part<Msg>(channels: &[&str], reason: Option<&str>) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Part)
+        .param(comma_list("channel", channels)?)
+        .params(reason)
+        .build()
+}
+
+pub fn quit<Msg>(reason: Option<&str>) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Quit).params(reason).build()
+}
+
+pub fn nick<Msg>(nickname: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Nick)
+        .param(word("nickname", nickname)?)
+        .build()
+}
+
+/// Constructs a `USER` message, which registers a connection's username and real name.
+pub fn user<Msg>(username: &str, realname: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::User)
+        .params(&[word("username", username)?, "8", "*", realname])
+        .build()
+}
+
+/// Constructs a `MODE` message setting the given modes, such as `"+ov"`, with the given mode
+/// arguments, on the given channel or user.
+pub fn mode<Msg>(target: &str, modes: &str, args: &[&str]) -> Result<Msg>
+where
+    Msg: Message,
+{
+    let mut builder = MessageBuilder::new(Command::Mode)
+        .param(word("target", target)?)
+        .param(word("mode string", modes)?);
+
+    for arg in args {
+        builder = builder.param(word("mode argument", arg)?);
+    }
+
+    builder.build()
+}
+
+/// Constructs a `TOPIC` message. If `topic` is `None`, the message queries the channel's topic;
+/// otherwise, it sets the topic, and an empty topic clears it.
+pub fn topic<Msg>(channel: &str, topic: Option<&str>) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Topic)
+        .param(word("channel", channel)?)
+        .params(topic)
+        .build()
+}
+
+pub fn kick<Msg>(channel: &str, nickname: &str, reason: Option<&str>) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Kick)
+        .param(word("channel", channel)?)
+        .param(word("nickname", nickname)?)
+        .params(reason)
+        .build()
+}
+
+pub fn invite<Msg>(nickname: &str, channel: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Invite)
+        .param(word("nickname", nickname)?)
+        .param(word("channel", channel)?)
+        .build()
+}
+
+pub fn who<Msg>(mask: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    MessageBuilder::new(Command::Who)
+        .param(word("mask", mask)?)
+        .build()
+}
+
+/// Divides text into pieces no longer than `max_len` bytes, for sending in separate messages.
+///
+/// The text is first divided at line breaks, which cannot be sent within a message; empty lines
+/// are dropped. Each line that is too long is then divided at the last space that leaves a piece
+/// short enough, which space is dropped, or, where there is no such space, at the last character
+/// boundary that does. A UTF-8 sequence is never divided.
+pub fn split_text(text: &str, max_len: usize) -> Result<Vec<&str>> {
+    let mut pieces = Vec::new();
+
+    for line in text.split(|c| c == '\r' || c == '\n') {
+        let mut rest = line;
+
+        while rest.len() > max_len {
+            let mut end = max_len;
+
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            ensure!(
+                end > 0,
+                ErrorKind::InvalidComponent(
+                    "text",
+                    text.as_bytes().to_owned(),
+                    format!("cannot be divided into pieces of at most {} bytes", max_len).into(),
+                )
+            );
+
+            let (piece, next) = if rest[end..].starts_with(' ') {
+                (&rest[..end], &rest[end + 1..])
+            } else {
+                match rest[..end].rfind(' ') {
+                    Some(i) if i > 0 => (&rest[..i], &rest[i + 1..]),
+                    _ => (&rest[..end], &rest[end..]),
+                }
+            };
+
+            pieces.push(piece);
+            rest = next;
+        }
+
+        if !rest.is_empty() {
+            pieces.push(rest);
+        }
+    }
+
+    Ok(pieces)
+}
+
+fn split_into<Msg>(
+    command: Command,
+    sender_prefix_len: usize,
+    target: &str,
+    text: &str,
+) -> Result<Vec<Msg>>
+where
+    Msg: Message,
+{
+    // The message as relayed will be `:<prefix> <command> <target> :<text>\r\n`.
+    let overhead =
+        1 + sender_prefix_len + 1 + command.to_bytes().len() + 1 + target.len() + 2 + 2;
+
+    ensure!(
+        overhead < BODY_MAX_LEN,
+        ErrorKind::InvalidComponent(
+            "target",
+            target.as_bytes().to_owned(),
+            "leaves no room for text in a message".into(),
+        )
+    );
+
+    let builder = MessageBuilder::new(command).param(word("target", target)?);
+
+    split_text(text, BODY_MAX_LEN - overhead)?
+        .into_iter()
+        .map(|piece| builder.clone().param(piece).build())
+        .collect()
+}
+
+fn word<'a>(component: &'static str, s: &'a str) -> Result<&'a str> {
+    ensure!(
+        !s.is_empty() && !s.contains(' ') && !s.starts_with(':'),
+        ErrorKind::InvalidComponent(
+            component,
+            s.as_bytes().to_owned(),
+            "must be non-empty, contain no spaces, and not start with a colon".into(),
+        )
+    );
+
+    Ok(s)
+}
+
+fn comma_list(component: &'static str, items: &[&str]) -> Result<String> {
+    ensure!(
+        !items.is_empty(),
+        ErrorKind::InvalidComponent(component, Vec::new(), "list must not be empty".into())
+    );
+
+    for item in items {
+        word(component, item)?;
+        ensure!(
+            !item.contains(','),
+            ErrorKind::InvalidComponent(
+                component,
+                item.as_bytes().to_owned(),
+                "must not contain a comma, as it is an element of a list".into(),
+            )
+        );
+    }
+
+    Ok(items.join(","))
+}
@@ -0,0 +1,97 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+
+fn bytes(result: Result<OwnedMessage>) -> Vec<u8> {
+    result.unwrap().into_bytes()
+}
+
+#[test]
+fn cmd_1() {
+    assert_eq!(bytes(privmsg("#chan", "Hello, world!")), &b"PRIVMSG #chan :Hello, world!"[..]);
+    assert_eq!(bytes(notice("nick", "hi")), &b"NOTICE nick hi"[..]);
+    assert_eq!(bytes(join(&["#a", "#b", "#c"], &["key"])), &b"JOIN #a,#b,#c key"[..]);
+    assert_eq!(bytes(join(&["#a"], &[])), &b"JOIN #a"[..]);
+    assert_eq!(bytes(part(&["#a", "#b"], Some("Gone"))), &b"PART #a,#b Gone"[..]);
+    assert_eq!(bytes(part(&["#a"], None)), &b"PART #a"[..]);
+    assert_eq!(bytes(quit(Some("Bye now"))), &b"QUIT :Bye now"[..]);
+    assert_eq!(bytes(quit(None)), &b"QUIT"[..]);
+    assert_eq!(bytes(nick("yak")), &b"NICK yak"[..]);
+    assert_eq!(bytes(user("yak", "A Yak")), &b"USER yak 8 * :A Yak"[..]);
+    assert_eq!(bytes(mode("#chan", "+ov", &["alice", "bob"])), &b"MODE #chan +ov alice bob"[..]);
+    assert_eq!(bytes(topic("#chan", None)), &b"TOPIC #chan"[..]);
+    assert_eq!(bytes(topic("#chan", Some(""))), &b"TOPIC #chan :"[..]);
+    assert_eq!(bytes(kick("#chan", "nick", Some("Out!"))), &b"KICK #chan nick Out!"[..]);
+    assert_eq!(bytes(invite("nick", "#chan")), &b"INVITE nick #chan"[..]);
+    assert_eq!(bytes(who("#chan")), &b"WHO #chan"[..]);
+}
+
+#[test]
+fn cmd_reject_invalid_1() {
+    let results: Vec<Result<OwnedMessage>> = vec![
+        privmsg("#chan\r\nQUIT", "hi"),
+        privmsg("#chan", "hi\r\nQUIT"),
+        privmsg("two words", "hi"),
+        privmsg("", "hi"),
+        join(&["#a,#b"], &[]),
+        join(&[], &[]),
+        join(&["#a"], &["k1", "k2"]),
+        nick("nick name"),
+        nick(":nick"),
+        user("user name", "A Yak"),
+        mode("#chan", "+b", &["two words"]),
+        kick("#chan", "", None),
+        who("a b"),
+    ];
+
+    for (i, result) in results.into_iter().enumerate() {
+        assert!(result.is_err(), "Case {} unexpectedly succeeded: {:?}", i, result);
+    }
+}
+
+#[test]
+fn split_text_1() {
+    assert_eq!(split_text("one two three", 7).unwrap(), ["one two", "three"]);
+    assert_eq!(split_text("one two three", 8).unwrap(), ["one two", "three"]);
+    assert_eq!(split_text("onetwothree", 5).unwrap(), ["onetw", "othre", "e"]);
+    assert_eq!(split_text("line one\r\n\nline two", 100).unwrap(), ["line one", "line two"]);
+    assert_eq!(split_text("", 100).unwrap(), Vec::<&str>::new());
+
+    // "é" is two bytes long in UTF-8, and must not be divided.
+    assert_eq!(split_text("ééé", 3).unwrap(), ["é", "é", "é"]);
+    assert!(split_text("é", 1).is_err());
+}
+
+#[test]
+fn privmsg_split_1() {
+    let prefix_len = "nick!user@".len() + HOST_MAX_LEN;
+    let text = "word ".repeat(300);
+    let msgs = privmsg_split::<OwnedMessage>(prefix_len, "#chan", &text).unwrap();
+
+    assert!(msgs.len() > 1);
+
+    for msg in &msgs {
+        assert!(1 + prefix_len + 1 + msg.as_bytes().len() + 2 <= BODY_MAX_LEN);
+        assert_eq!(msg.command(), Command::Privmsg);
+        assert_eq!(msg.param(0), Some(&b"#chan"[..]));
+    }
+
+    assert_eq!(
+        msgs.iter().map(|msg| msg.trailing().unwrap()).collect::<Vec<_>>().join(&b' '),
+        text.as_bytes()
+    );
+
+    assert!(privmsg_split::<OwnedMessage>(BODY_MAX_LEN, "#chan", "hi").is_err());
+}
+
+quickcheck! {
+    fn split_text_preserves_text_1(text: String, max_len: usize) -> bool {
+        let max_len = max_len % 64 + 4;
+        let pieces = split_text(&text, max_len).unwrap();
+        let significant = |s: &str| s.chars().filter(|&c| !" \r\n".contains(c)).collect::<String>();
+
+        pieces.iter().all(|piece| !piece.is_empty() && piece.len() <= max_len) &&
+            significant(&pieces.concat()) == significant(&text)
+    }
+}
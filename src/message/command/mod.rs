@@ -0,0 +1,158 @@
+use super::numeric::Numeric;
+use std::borrow::Cow;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+mod tests;
+
+macro_rules! commands {
+    ($($variant:ident: $name:expr,)*) => {
+        /// An IRC command, as named in a message.
+        ///
+        /// This covers the commands of [RFC 2812], section 3, and the commands introduced by
+        /// commonly supported IRCv3 extensions. Commands are matched without regard to ASCII case,
+        /// as servers are required to do. Numeric replies are represented by the `Numeric`
+        /// variant, and any other command by the `Other` variant, which holds the command exactly
+        /// as it appeared in the message, but is likewise compared and hashed without regard to
+        /// ASCII case.
+        ///
+        /// [RFC 2812]: <https://tools.ietf.org/html/rfc2812#section-3>
+        #[derive(Clone, Copy, Debug, Eq)]
+        pub enum Command<'a> {
+            $($variant,)*
+            Numeric(Numeric),
+            Other(&'a [u8]),
+        }
+
+        impl<'a> Command<'a> {
+            /// Identifies the command named by the given bytes.
+            pub fn from_bytes(bytes: &'a [u8]) -> Self {
+                $(if bytes.eq_ignore_ascii_case($name) {
+                    return Command::$variant;
+                })*
+
+                if let Some(numeric) = Numeric::from_bytes(bytes) {
+                    return Command::Numeric(numeric);
+                }
+
+                Command::Other(bytes)
+            }
+
+            /// Returns the command's name as it should appear in a message. Named commands are
+            /// given in upper case, and numeric replies as three digits.
+            pub fn to_bytes(&self) -> Cow<'a, [u8]> {
+                match *self {
+                    $(Command::$variant => Cow::Borrowed($name),)*
+                    Command::Numeric(numeric) => Cow::Owned(numeric.to_bytes()),
+                    Command::Other(bytes) => Cow::Borrowed(bytes),
+                }
+            }
+        }
+
+        impl<'a> PartialEq for Command<'a> {
+            fn eq(&self, other: &Self) -> bool {
+                match (*self, *other) {
+                    $((Command::$variant, Command::$variant) => true,)*
+                    (Command::Numeric(a), Command::Numeric(b)) => a == b,
+                    (Command::Other(a), Command::Other(b)) => a.eq_ignore_ascii_case(b),
+                    _ => false,
+                }
+            }
+        }
+
+        impl<'a> Hash for Command<'a> {
+            fn hash<H>(&self, state: &mut H)
+            where
+                H: Hasher,
+            {
+                match *self {
+                    $(Command::$variant => $name.hash(state),)*
+                    Command::Numeric(numeric) => numeric.hash(state),
+                    Command::Other(bytes) => {
+                        state.write_usize(bytes.len());
+
+                        for b in bytes {
+                            state.write_u8(b.to_ascii_uppercase());
+                        }
+                    }
+                }
+            }
+        }
+
+        #[cfg(test)]
+        const NAMED_COMMANDS: &[Command<'static>] = &[$(Command::$variant,)*];
+    };
+}
+
+commands! {
+    // RFC 2812, section 3.1: Connection registration
+    Pass: b"PASS",
+    Nick: b"NICK",
+    User: b"USER",
+    Oper: b"OPER",
+    Mode: b"MODE",
+    Service: b"SERVICE",
+    Quit: b"QUIT",
+    Squit: b"SQUIT",
+
+    // RFC 2812, section 3.2: Channel operations
+    Join: b"JOIN",
+    Part: b"PART",
+    Topic: b"TOPIC",
+    Names: b"NAMES",
+    List: b"LIST",
+    Invite: b"INVITE",
+    Kick: b"KICK",
+
+    // RFC 2812, sections 3.3 through 3.7: Messaging, queries, and miscellany
+    Privmsg: b"PRIVMSG",
+    Notice: b"NOTICE",
+    Motd: b"MOTD",
+    Lusers: b"LUSERS",
+    Version: b"VERSION",
+    Stats: b"STATS",
+    Links: b"LINKS",
+    Time: b"TIME",
+    Connect: b"CONNECT",
+    Trace: b"TRACE",
+    Admin: b"ADMIN",
+    Info: b"INFO",
+    Servlist: b"SERVLIST",
+    Squery: b"SQUERY",
+    Who: b"WHO",
+    Whois: b"WHOIS",
+    Whowas: b"WHOWAS",
+    Kill: b"KILL",
+    Ping: b"PING",
+    Pong: b"PONG",
+    Error: b"ERROR",
+
+    // RFC 2812, section 4: Optional features
+    Away: b"AWAY",
+    Rehash: b"REHASH",
+    Die: b"DIE",
+    Restart: b"RESTART",
+    Summon: b"SUMMON",
+    Users: b"USERS",
+    Wallops: b"WALLOPS",
+    Userhost: b"USERHOST",
+    Ison: b"ISON",
+
+    // IRCv3
+    Cap: b"CAP",
+    Authenticate: b"AUTHENTICATE",
+    Account: b"ACCOUNT",
+    Batch: b"BATCH",
+    Chghost: b"CHGHOST",
+    Monitor: b"MONITOR",
+    Setname: b"SETNAME",
+    Starttls: b"STARTTLS",
+    Tagmsg: b"TAGMSG",
+}
+
+impl<'a> fmt::Display for Command<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
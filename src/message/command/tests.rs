@@ -0,0 +1,71 @@
+#![cfg(test)]
+
+use super::*;
+use message::Message;
+use message::OwnedMessage;
+use message::numeric::*;
+use std::collections::hash_map::DefaultHasher;
+
+#[test]
+fn named_command_roundtrip_1() {
+    for &cmd in NAMED_COMMANDS {
+        let bytes = cmd.to_bytes();
+
+        assert_eq!(Command::from_bytes(&bytes), cmd);
+        assert_eq!(
+            Command::from_bytes(&bytes.to_ascii_lowercase()),
+            cmd,
+            "command: {}",
+            cmd
+        );
+    }
+}
+
+#[test]
+fn numeric_command_1() {
+    assert_eq!(Command::from_bytes(b"001"), Command::Numeric(RPL_WELCOME));
+    assert_eq!(Command::from_bytes(b"433"), Command::Numeric(ERR_NICKNAMEINUSE));
+    assert_eq!(Command::Numeric(RPL_ISUPPORT).to_bytes(), &b"005"[..]);
+    assert_eq!(Command::from_bytes(b"1"), Command::Other(b"1"));
+    assert_eq!(Command::from_bytes(b"0001"), Command::Other(b"0001"));
+}
+
+#[test]
+fn message_command_1() {
+    let msg = OwnedMessage::try_from(Cow::Borrowed(&b":nick privmsg #chan :hi"[..])).unwrap();
+
+    match msg.command() {
+        Command::Privmsg => {}
+        other => panic!("Unexpected command: {:?}", other),
+    }
+
+    let msg = OwnedMessage::try_from(Cow::Borrowed(&b"FROBNICATE x"[..])).unwrap();
+
+    assert_eq!(msg.command(), Command::Other(b"FROBNICATE"));
+    assert_eq!(msg.command().to_bytes(), &b"FROBNICATE"[..]);
+}
+
+#[test]
+fn other_command_case_1() {
+    let hash = |cmd: Command| {
+        let mut hasher = DefaultHasher::new();
+        cmd.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    assert_eq!(Command::from_bytes(b"frobnicate"), Command::from_bytes(b"FROBNICATE"));
+    assert_eq!(hash(Command::Other(b"frobnicate")), hash(Command::Other(b"FrobNicate")));
+    assert_ne!(Command::Other(b"frobnicate"), Command::Other(b"frobnicated"));
+    assert_ne!(Command::Other(b"PRIVMSG"), Command::Privmsg);
+
+    // The command is still given exactly as it appeared.
+    assert_eq!(Command::from_bytes(b"frobnicate").to_bytes(), &b"frobnicate"[..]);
+}
+
+quickcheck! {
+    fn numeric_roundtrip_1(code: u16) -> bool {
+        let numeric = Numeric::from_code(code % 1000).unwrap();
+
+        Command::from_bytes(&Command::Numeric(numeric).to_bytes()) == Command::Numeric(numeric)
+    }
+}
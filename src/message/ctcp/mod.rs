@@ -0,0 +1,253 @@
+//! The Client-To-Client Protocol (CTCP).
+//!
+//! A CTCP message is a `PRIVMSG` or `NOTICE` whose text is delimited by `\x01` characters, as in
+//! `PRIVMSG #chan :\x01ACTION waves\x01`. The text consists of a command, optionally followed by
+//! a space and parameters. A CTCP message sent in a `PRIVMSG` is a query, and one sent in a
+//! `NOTICE` is a reply; see the [CTCP specification] for the commands in common use.
+//!
+//! The obsolete "low-level quoting" and the embedding of CTCP messages within other text are not
+//! supported, as no modern client uses them.
+//!
+//! [CTCP specification]: <https://modern.ircdocs.horse/ctcp.html>
+
+use super::Command;
+use super::ErrorKind;
+use super::Message;
+use super::MessageBuilder;
+use super::Result;
+use std::str;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+mod tests;
+
+const DELIMITER: u8 = b'\x01';
+
+/// The commands to which [`standard_reply`] replies, as listed in reply to a `CLIENTINFO` query.
+///
+/// [`standard_reply`]: fn.standard_reply.html
+pub const STANDARD_COMMANDS: &str = "ACTION CLIENTINFO PING TIME VERSION";
+
+/// Whether a CTCP message is a query or a reply.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Kind {
+    /// A query, sent in a `PRIVMSG`.
+    Query,
+
+    /// A reply, sent in a `NOTICE`.
+    Reply,
+}
+
+/// A CTCP message, borrowed from the text of a `PRIVMSG` or `NOTICE`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Ctcp<'a> {
+    kind: Kind,
+    command: &'a [u8],
+    params: Option<&'a [u8]>,
+}
+
+impl<'a> Ctcp<'a> {
+    /// Returns the CTCP message carried by the given message, if it carries one.
+    pub fn from_message<Msg>(msg: &'a Msg) -> Option<Self>
+    where
+        Msg: Message,
+    {
+        let kind = match msg.command() {
+            Command::Privmsg => Kind::Query,
+            Command::Notice => Kind::Reply,
+            _ => return None,
+        };
+
+        Self::parse(kind, msg.param(1)?)
+    }
+
+    /// Parses the given text of a `PRIVMSG` or `NOTICE` as a CTCP message, returning `None` if it
+    /// is not one. The closing delimiter is optional, as some clients omit it.
+    pub fn parse(kind: Kind, text: &'a [u8]) -> Option<Self> {
+        if !text.starts_with(&[DELIMITER]) {
+            return None;
+        }
+
+        let text = &text[1..];
+        let text = if text.ends_with(&[DELIMITER]) {
+            &text[..text.len() - 1]
+        } else {
+            text
+        };
+
+        let (command, params) = match text.iter().position(|&b| b == b' ') {
+            Some(i) => (&text[..i], Some(&text[i + 1..])),
+            None => (text, None),
+        };
+
+        if command.is_empty() || command.contains(&DELIMITER) {
+            return None;
+        }
+
+        Some(Ctcp {
+            kind,
+            command,
+            params,
+        })
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Returns the CTCP command, such as `ACTION` or `VERSION`, as it appeared in the message.
+    pub fn command(&self) -> &'a [u8] {
+        self.command
+    }
+
+    /// Returns the text following the command and the space after it, if there is such a space.
+    pub fn params(&self) -> Option<&'a [u8]> {
+        self.params
+    }
+
+    /// Returns whether this message's command is the given command, ignoring ASCII case.
+    pub fn is(&self, command: &str) -> bool {
+        self.command.eq_ignore_ascii_case(command.as_bytes())
+    }
+}
+
+/// Constructs a `PRIVMSG` message carrying a CTCP query.
+pub fn query<Msg>(target: &str, command: &str, params: Option<&str>) -> Result<Msg>
+where
+    Msg: Message,
+{
+    build(Command::Privmsg, target, command, params)
+}
+
+/// Constructs a `NOTICE` message carrying a CTCP reply.
+pub fn reply<Msg>(target: &str, command: &str, params: Option<&str>) -> Result<Msg>
+where
+    Msg: Message,
+{
+    build(Command::Notice, target, command, params)
+}
+
+/// Constructs a CTCP `ACTION` query, which describes an action of the sender, as with the `/me`
+/// command of many clients.
+pub fn action<Msg>(target: &str, text: &str) -> Result<Msg>
+where
+    Msg: Message,
+{
+    query(target, "ACTION", Some(text))
+}
+
+/// Constructs the reply that a client conventionally makes automatically to the given message, if
+/// it is a CTCP query to which such a reply is made. Replies are made to `CLIENTINFO`, `PING`,
+/// `TIME`, and `VERSION` queries, the last with the given version string.
+///
+/// Returns `None` if the message is not such a query, or if it has no sender to reply to.
+pub fn standard_reply<Msg>(msg: &Msg, version: &str, now: SystemTime) -> Option<Result<Msg>>
+where
+    Msg: Message,
+{
+    let ctcp = match Ctcp::from_message(msg) {
+        Some(ctcp @ Ctcp { kind: Kind::Query, .. }) => ctcp,
+        _ => return None,
+    };
+
+    let sender = str::from_utf8(msg.nick()?).ok()?;
+
+    let (command, params) = if ctcp.is("CLIENTINFO") {
+        ("CLIENTINFO", Some(STANDARD_COMMANDS.to_owned()))
+    } else if ctcp.is("PING") {
+        // The parameters of a `PING` query, typically a timestamp, are echoed back.
+        ("PING", ctcp.params().map(|p| String::from_utf8_lossy(p).into_owned()))
+    } else if ctcp.is("TIME") {
+        ("TIME", Some(format_time(now)))
+    } else if ctcp.is("VERSION") {
+        ("VERSION", Some(version.to_owned()))
+    } else {
+        return None;
+    };
+
+    Some(reply(sender, command, params.as_ref().map(|p| &p[..])))
+}
+
+fn build<Msg>(
+    command: Command,
+    target: &str,
+    ctcp_command: &str,
+    params: Option<&str>,
+) -> Result<Msg>
+where
+    Msg: Message,
+{
+    ensure!(
+        !ctcp_command.is_empty() &&
+            !ctcp_command.contains(|c| c == ' ' || c == DELIMITER as char),
+        ErrorKind::InvalidComponent(
+            "CTCP command",
+            ctcp_command.as_bytes().to_owned(),
+            "must be non-empty and contain no spaces or `\\x01` characters".into(),
+        )
+    );
+
+    let mut text = Vec::with_capacity(ctcp_command.len() + params.map_or(0, str::len) + 3);
+
+    text.push(DELIMITER);
+    text.extend_from_slice(ctcp_command.as_bytes());
+
+    if let Some(params) = params {
+        ensure!(
+            !params.contains(DELIMITER as char),
+            ErrorKind::InvalidComponent(
+                "CTCP parameters",
+                params.as_bytes().to_owned(),
+                "must not contain `\\x01` characters".into(),
+            )
+        );
+
+        text.push(b' ');
+        text.extend_from_slice(params.as_bytes());
+    }
+
+    text.push(DELIMITER);
+
+    MessageBuilder::new(command).param(target).param(text).build()
+}
+
+/// Formats the given time in the style of RFC 2822, in UTC, as in `Thu, 01 Jan 1970 00:00:00
+/// +0000`. Times before the UNIX epoch are formatted as the epoch.
+fn format_time(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let days = secs / 86400;
+    let secs_of_day = secs % 86400;
+
+    // This converts a count of days since the epoch to a date in the proleptic Gregorian
+    // calendar, per <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
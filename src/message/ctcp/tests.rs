@@ -0,0 +1,110 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use std::borrow::Cow;
+use std::time::Duration;
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+#[test]
+fn parse_1() {
+    let action = msg(b":nick!user@host PRIVMSG #chan :\x01ACTION waves hello\x01");
+    let ctcp = Ctcp::from_message(&action).unwrap();
+
+    assert_eq!(ctcp.kind(), Kind::Query);
+    assert_eq!(ctcp.command(), b"ACTION");
+    assert_eq!(ctcp.params(), Some(&b"waves hello"[..]));
+    assert!(ctcp.is("action"));
+
+    let version = msg(b":nick NOTICE me :\x01VERSION Some Client 1.0");
+    let ctcp = Ctcp::from_message(&version).unwrap();
+
+    assert_eq!(ctcp.kind(), Kind::Reply);
+    assert_eq!(ctcp.command(), b"VERSION");
+    assert_eq!(ctcp.params(), Some(&b"Some Client 1.0"[..]));
+
+    let ping = msg(b"PRIVMSG me \x01PING\x01");
+
+    assert_eq!(Ctcp::from_message(&ping).unwrap().params(), None);
+}
+
+#[test]
+fn parse_not_ctcp_1() {
+    for &bytes in &[
+        &b":nick PRIVMSG #chan :hello"[..],
+        b":nick PRIVMSG #chan :\x01\x01",
+        b":nick PRIVMSG #chan : \x01ACTION waves\x01",
+        b":nick TOPIC #chan :\x01ACTION waves\x01",
+        b":nick PRIVMSG #chan",
+    ] {
+        assert_eq!(Ctcp::from_message(&msg(bytes)), None, "{:?}", msg(bytes));
+    }
+}
+
+#[test]
+fn build_1() {
+    assert_eq!(
+        action::<OwnedMessage>("#chan", "waves").unwrap(),
+        msg(b"PRIVMSG #chan :\x01ACTION waves\x01")
+    );
+    assert_eq!(
+        query::<OwnedMessage>("nick", "VERSION", None).unwrap(),
+        msg(b"PRIVMSG nick \x01VERSION\x01")
+    );
+    assert_eq!(
+        reply::<OwnedMessage>("nick", "PING", Some("12345")).unwrap(),
+        msg(b"NOTICE nick :\x01PING 12345\x01")
+    );
+
+    assert!(query::<OwnedMessage>("nick", "TWO WORDS", None).is_err());
+    assert!(query::<OwnedMessage>("nick", "", None).is_err());
+    assert!(action::<OwnedMessage>("#chan", "nested \x01").is_err());
+    assert!(action::<OwnedMessage>("#chan", "line\r\nbreak").is_err());
+}
+
+#[test]
+fn standard_reply_1() {
+    let now = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    let reply_to = |bytes| {
+        standard_reply::<OwnedMessage>(&msg(bytes), "Client 1.0", now).map(Result::unwrap)
+    };
+
+    assert_eq!(
+        reply_to(b":nick!user@host PRIVMSG me :\x01VERSION\x01"),
+        Some(msg(b"NOTICE nick :\x01VERSION Client 1.0\x01"))
+    );
+    assert_eq!(
+        reply_to(b":nick!user@host PRIVMSG #chan :\x01PING 123 456\x01"),
+        Some(msg(b"NOTICE nick :\x01PING 123 456\x01"))
+    );
+    assert_eq!(
+        reply_to(b":nick!user@host PRIVMSG me :\x01time\x01"),
+        Some(msg(b"NOTICE nick :\x01TIME Fri, 14 Jul 2017 02:40:00 +0000\x01"))
+    );
+    assert_eq!(
+        reply_to(b":nick!user@host PRIVMSG me :\x01CLIENTINFO\x01"),
+        Some(msg(b"NOTICE nick :\x01CLIENTINFO ACTION CLIENTINFO PING TIME VERSION\x01"))
+    );
+
+    assert_eq!(reply_to(b":nick!user@host PRIVMSG me :\x01ACTION waves\x01"), None);
+    assert_eq!(reply_to(b":nick!user@host NOTICE me :\x01VERSION\x01"), None);
+    assert_eq!(reply_to(b":irc.example.net PRIVMSG me :\x01VERSION\x01"), None);
+    assert_eq!(reply_to(b"PRIVMSG me :\x01VERSION\x01"), None);
+}
+
+#[test]
+fn format_time_1() {
+    let at = |secs| format_time(UNIX_EPOCH + Duration::from_secs(secs));
+
+    assert_eq!(at(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+    assert_eq!(at(951_782_400), "Tue, 29 Feb 2000 00:00:00 +0000");
+    assert_eq!(at(1_500_000_000), "Fri, 14 Jul 2017 02:40:00 +0000");
+    assert_eq!(at(4_102_444_799), "Thu, 31 Dec 2099 23:59:59 +0000");
+    assert_eq!(
+        format_time(UNIX_EPOCH - Duration::from_secs(1)),
+        "Thu, 01 Jan 1970 00:00:00 +0000"
+    );
+}
@@ -0,0 +1,125 @@
+//! Decoding of received messages to UTF-8.
+//!
+//! IRC does not specify a text encoding, and although most clients now send UTF-8, clients on
+//! older networks commonly send text in a legacy single-byte encoding, most often Windows code
+//! page 1252 or its subset ISO 8859-1 ("Latin-1"). A connection decodes each message it receives
+//! according to its [`DecodingPolicy`], and records the [`Decoding`] it used on the message.
+//!
+//! The structure of a message is not affected by decoding, as each encoding handled here encodes
+//! ASCII characters as UTF-8 does.
+//!
+//! [`Decoding`]: enum.Decoding.html
+//! [`DecodingPolicy`]: enum.DecodingPolicy.html
+
+use super::ErrorKind;
+use super::Result;
+use std::borrow::Cow;
+use std::str;
+
+mod tests;
+
+/// The way in which a message was decoded to UTF-8.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Decoding {
+    /// The message was valid UTF-8, and was not changed.
+    Utf8,
+
+    /// The message was not valid UTF-8, and its invalid byte sequences were replaced with the
+    /// replacement character, U+FFFD.
+    Utf8Lossy,
+
+    /// The message was not valid UTF-8, and was decoded as ISO 8859-1.
+    Latin1,
+
+    /// The message was not valid UTF-8, and was decoded as Windows code page 1252.
+    Cp1252,
+}
+
+/// How a connection decodes the messages it receives.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DecodingPolicy {
+    /// Messages must be valid UTF-8; any other message is rejected with an error, which includes
+    /// the message's bytes. This is the default.
+    Strict,
+
+    /// Messages are decoded as UTF-8, with invalid byte sequences replaced.
+    Lossy,
+
+    /// Messages are decoded as UTF-8 if they are valid UTF-8, and as ISO 8859-1 otherwise.
+    FallbackLatin1,
+
+    /// Messages are decoded as UTF-8 if they are valid UTF-8, and as Windows code page 1252
+    /// otherwise.
+    FallbackCp1252,
+}
+
+/// The characters that Windows code page 1252 assigns to the bytes 0x80 through 0x9F. The bytes
+/// that it leaves unassigned are taken to stand for the corresponding C1 control characters, as
+/// in ISO 8859-1.
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+impl Default for DecodingPolicy {
+    fn default() -> Self {
+        DecodingPolicy::Strict
+    }
+}
+
+impl DecodingPolicy {
+    /// Decodes the given bytes according to this policy. Bytes that are valid UTF-8 are borrowed
+    /// rather than copied.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<(Cow<'a, str>, Decoding)> {
+        match str::from_utf8(bytes) {
+            Ok(s) => Ok((Cow::Borrowed(s), Decoding::Utf8)),
+            Err(err) => self.decode_invalid(bytes, err).map(|(s, d)| (Cow::Owned(s), d)),
+        }
+    }
+
+    /// Like [`decode`], but takes ownership of the bytes, so that bytes that are valid UTF-8 need
+    /// not be copied.
+    ///
+    /// [`decode`]: #method.decode
+    pub fn decode_owned(&self, bytes: Vec<u8>) -> Result<(String, Decoding)> {
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok((s, Decoding::Utf8)),
+            Err(err) => {
+                let utf8_error = err.utf8_error();
+                self.decode_invalid(err.as_bytes(), utf8_error)
+            }
+        }
+    }
+
+    fn decode_invalid(&self, bytes: &[u8], err: str::Utf8Error) -> Result<(String, Decoding)> {
+        match *self {
+            DecodingPolicy::Strict => bail!(ErrorKind::InvalidUtf8(bytes.to_owned(), err)),
+            DecodingPolicy::Lossy => Ok((
+                String::from_utf8_lossy(bytes).into_owned(),
+                Decoding::Utf8Lossy,
+            )),
+            DecodingPolicy::FallbackLatin1 => Ok((decode_latin1(bytes), Decoding::Latin1)),
+            DecodingPolicy::FallbackCp1252 => Ok((decode_cp1252(bytes), Decoding::Cp1252)),
+        }
+    }
+}
+
+/// Decodes the given bytes as ISO 8859-1, in which each byte stands for the Unicode code point of
+/// the same value.
+pub fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Decodes the given bytes as Windows code page 1252.
+pub fn decode_cp1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| if b >= 0x80 && b < 0xA0 {
+            CP1252_HIGH[(b - 0x80) as usize]
+        } else {
+            b as char
+        })
+        .collect()
+}
@@ -0,0 +1,65 @@
+#![cfg(test)]
+
+use super::*;
+use message::Error;
+
+const POLICIES: &[DecodingPolicy] = &[
+    DecodingPolicy::Strict,
+    DecodingPolicy::Lossy,
+    DecodingPolicy::FallbackLatin1,
+    DecodingPolicy::FallbackCp1252,
+];
+
+#[test]
+fn decode_invalid_1() {
+    let bytes = b"na\xEFve \x93quoted\x94 \x81";
+
+    assert_eq!(
+        DecodingPolicy::Lossy.decode(bytes).unwrap(),
+        (Cow::Borrowed("na\u{FFFD}ve \u{FFFD}quoted\u{FFFD} \u{FFFD}"), Decoding::Utf8Lossy)
+    );
+    assert_eq!(
+        DecodingPolicy::FallbackLatin1.decode(bytes).unwrap(),
+        (Cow::Borrowed("na\u{EF}ve \u{93}quoted\u{94} \u{81}"), Decoding::Latin1)
+    );
+    assert_eq!(
+        DecodingPolicy::FallbackCp1252.decode(bytes).unwrap(),
+        (Cow::Borrowed("na\u{EF}ve \u{201C}quoted\u{201D} \u{81}"), Decoding::Cp1252)
+    );
+
+    match DecodingPolicy::Strict.decode(bytes) {
+        Err(Error(ErrorKind::InvalidUtf8(ref invalid, _), _)) => assert_eq!(invalid, bytes),
+        other => panic!("Expected invalid UTF-8; got {:?}", other),
+    }
+}
+
+#[test]
+fn cp1252_1() {
+    let decoded = decode_cp1252(&(0x80..0xA0).collect::<Vec<u8>>());
+
+    assert_eq!(decoded.chars().collect::<Vec<_>>(), CP1252_HIGH);
+    assert_eq!(decode_cp1252(b"\xA0\xE9\xFF"), "\u{A0}\u{E9}\u{FF}");
+}
+
+quickcheck! {
+    fn decode_utf8_unchanged_1(text: String) -> bool {
+        POLICIES.iter().all(|policy| {
+            let decoded = policy.decode(text.as_bytes()).unwrap();
+            let decoded_owned = policy.decode_owned(text.clone().into_bytes()).unwrap();
+
+            decoded == (Cow::Borrowed(&text[..]), Decoding::Utf8) &&
+                decoded_owned == (text.clone(), Decoding::Utf8)
+        })
+    }
+
+    fn decode_single_byte_1(bytes: Vec<u8>) -> bool {
+        let latin1 = decode_latin1(&bytes);
+        let cp1252 = decode_cp1252(&bytes);
+
+        let is_cp1252_high = |b| b >= 0x80 && b < 0xA0;
+
+        latin1.chars().count() == bytes.len() && cp1252.chars().count() == bytes.len() &&
+            latin1.chars().zip(&bytes).all(|(c, &b)| c as u32 == b as u32) &&
+            cp1252.chars().zip(&bytes).all(|(c, &b)| is_cp1252_high(b) || c as u32 == b as u32)
+    }
+}
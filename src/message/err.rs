@@ -1,5 +1,6 @@
 #[cfg(feature = "pircolate")]
 use pircolate;
+use std::borrow::Cow;
 use std::error;
 use std::str;
 use std::string;
@@ -16,6 +17,27 @@ error_chain! {
     }
 
     errors {
+        InvalidMessage(bytes: Vec<u8>, desc: Cow<'static, str>) {
+            description("a string of bytes could not be parsed as an IRC message")
+            display("A string of bytes could not be parsed as an IRC message ({}): {:?}",
+                    desc, String::from_utf8_lossy(&bytes))
+        }
+        InvalidComponent(component: &'static str, bytes: Vec<u8>, desc: Cow<'static, str>) {
+            description("an IRC message could not be built because one of its components was \
+                         invalid")
+            display("An IRC message could not be built because its {} {:?} was invalid: it {}",
+                    component, String::from_utf8_lossy(&bytes), desc)
+        }
+        InvalidTag(key: Vec<u8>, desc: Cow<'static, str>) {
+            description("an IRCv3 message tag was invalid")
+            display("The IRCv3 message tag with key {:?} was invalid: {}",
+                    String::from_utf8_lossy(&key), desc)
+        }
+        InvalidUtf8(bytes: Vec<u8>, error: str::Utf8Error) {
+            description("a received IRC message was not valid UTF-8")
+            display("A received IRC message was not valid UTF-8 ({}): {:?}",
+                    error, String::from_utf8_lossy(&bytes))
+        }
         Other(inner: Box<error::Error + Send>) {
             description("there was an unspecified problem with an IRC message")
             display("{}", inner)
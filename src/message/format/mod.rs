@@ -0,0 +1,343 @@
+//! mIRC-style text formatting.
+//!
+//! Text in IRC messages may contain control characters that style the text following them, as
+//! described in the [formatting reference]. This module handles the following:
+//!
+//! - `\x02`, `\x1D`, `\x1F`, `\x1E`, `\x11`, and `\x16`, which toggle bold, italics, underlining,
+//!   strikethrough, monospace, and reverse video, respectively;
+//!
+//! - `\x03`, which sets the foreground and background colours to numbered colours from a palette,
+//!   as in `\x0304` or `\x0304,12`, or, if no number follows it, resets both colours;
+//!
+//! - `\x04`, which likewise sets colours, but gives them as hexadecimal RGB triples, as in
+//!   `\x04FF0000` or `\x04FF0000,000080`; and
+//!
+//! - `\x0F`, which resets all formatting.
+//!
+//! [formatting reference]: <https://modern.ircdocs.horse/formatting.html>
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+mod tests;
+
+/// A colour, as set by a colour formatting code.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Color {
+    /// A colour from the palette of colours numbered 0 through 98. The palette colour 99, which
+    /// stands for the client's default colour, is represented by the absence of a colour, and
+    /// higher numbers are rendered as 99.
+    Palette(u8),
+
+    /// A colour given by its red, green, and blue components.
+    Rgb(u8, u8, u8),
+}
+
+/// The formatting in effect at some point in a text.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub monospace: bool,
+    pub reverse: bool,
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+}
+
+/// A run of text with a single style.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub style: Style,
+}
+
+/// An iterator over the styled spans of a text. See [`spans`].
+///
+/// [`spans`]: fn.spans.html
+#[derive(Clone, Debug)]
+pub struct Spans<'a> {
+    remaining: &'a str,
+    style: Style,
+}
+
+const BOLD: char = '\x02';
+const COLOR: char = '\x03';
+const HEX_COLOR: char = '\x04';
+const RESET: char = '\x0F';
+const MONOSPACE: char = '\x11';
+const REVERSE: char = '\x16';
+const ITALIC: char = '\x1D';
+const STRIKETHROUGH: char = '\x1E';
+const UNDERLINE: char = '\x1F';
+
+/// The palette colour that stands for the client's default colour.
+const DEFAULT_PALETTE_COLOR: u8 = 99;
+
+impl Style {
+    /// Returns whether this is the style of unformatted text.
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+}
+
+/// Returns an iterator over the styled spans of the given text. Every formatting code is
+/// consumed, and no span is empty; a span is not necessarily styled differently from the one
+/// before it.
+pub fn spans(text: &str) -> Spans {
+    Spans {
+        remaining: text,
+        style: Style::default(),
+    }
+}
+
+impl<'a> Iterator for Spans<'a> {
+    type Item = Span<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut chars = self.remaining.chars();
+
+            let code = match chars.next() {
+                Some(c) if is_format_char(c) => c,
+                Some(_) => break,
+                None => return None,
+            };
+
+            self.remaining = chars.as_str();
+
+            match code {
+                BOLD => self.style.bold = !self.style.bold,
+                ITALIC => self.style.italic = !self.style.italic,
+                UNDERLINE => self.style.underline = !self.style.underline,
+                STRIKETHROUGH => self.style.strikethrough = !self.style.strikethrough,
+                MONOSPACE => self.style.monospace = !self.style.monospace,
+                REVERSE => self.style.reverse = !self.style.reverse,
+                RESET => self.style = Style::default(),
+                COLOR => self.parse_colors(parse_palette_color),
+                HEX_COLOR => self.parse_colors(parse_rgb_color),
+                _ => unreachable!(),
+            }
+        }
+
+        let end = self.remaining
+            .find(is_format_char)
+            .unwrap_or(self.remaining.len());
+        let (text, rest) = self.remaining.split_at(end);
+
+        self.remaining = rest;
+
+        Some(Span {
+            text,
+            style: self.style,
+        })
+    }
+}
+
+impl<'a> Spans<'a> {
+    /// Parses the arguments of a colour code, which has already been consumed, using the given
+    /// function to parse each colour.
+    fn parse_colors<F>(&mut self, parse_color: F)
+    where
+        F: Fn(&str) -> Option<(Option<Color>, usize)>,
+    {
+        let (foreground, len) = match parse_color(self.remaining) {
+            Some(result) => result,
+            None => {
+                self.style.foreground = None;
+                self.style.background = None;
+                return;
+            }
+        };
+
+        self.style.foreground = foreground;
+        self.remaining = &self.remaining[len..];
+
+        if self.remaining.starts_with(',') {
+            if let Some((background, len)) = parse_color(&self.remaining[1..]) {
+                self.style.background = background;
+                self.remaining = &self.remaining[1 + len..];
+            }
+        }
+    }
+}
+
+/// Returns the given text with all formatting codes removed.
+pub fn strip(text: &str) -> Cow<str> {
+    if !text.contains(is_format_char) {
+        return Cow::Borrowed(text);
+    }
+
+    Cow::Owned(spans(text).map(|span| span.text).collect())
+}
+
+/// Renders the given spans as text with formatting codes.
+///
+/// Any formatting codes in the spans' text are stripped, so that the text appears with exactly
+/// the given styles. If the last span is not plain, the formatting is reset at the end of the text,
+/// so that it does not carry over into any text that might be appended.
+pub fn render<'a, I>(spans: I) -> String
+where
+    I: IntoIterator<Item = Span<'a>>,
+{
+    let mut output = String::new();
+    let mut current = Style::default();
+
+    for span in spans {
+        let text = strip(span.text);
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let codes_start = output.len();
+
+        write_transition(&mut output, &current, &span.style);
+
+        // A colour code must not be followed by text that could be mistaken for its arguments,
+        // so such text is separated from it by a pair of bold codes, which have no net effect.
+        let needs_guard = match output[codes_start..].chars().next_back() {
+            Some(COLOR) => text.starts_with(|c: char| c.is_ascii_digit()),
+            Some(HEX_COLOR) => text.starts_with(|c: char| c.is_ascii_hexdigit()),
+            Some(c) if c.is_ascii_hexdigit() => text.starts_with(','),
+            _ => false,
+        };
+
+        if needs_guard {
+            output.push(BOLD);
+            output.push(BOLD);
+        }
+
+        output.push_str(&text);
+        current = span.style;
+    }
+
+    if !current.is_plain() {
+        output.push(RESET);
+    }
+
+    output
+}
+
+fn write_transition(output: &mut String, from: &Style, to: &Style) {
+    if from == to {
+        return;
+    }
+
+    if to.is_plain() {
+        output.push(RESET);
+        return;
+    }
+
+    for &(code, was_set, is_set) in &[
+        (BOLD, from.bold, to.bold),
+        (ITALIC, from.italic, to.italic),
+        (UNDERLINE, from.underline, to.underline),
+        (STRIKETHROUGH, from.strikethrough, to.strikethrough),
+        (MONOSPACE, from.monospace, to.monospace),
+        (REVERSE, from.reverse, to.reverse),
+    ] {
+        if was_set != is_set {
+            output.push(code);
+        }
+    }
+
+    if from.foreground == to.foreground && from.background == to.background {
+        return;
+    }
+
+    // A colour code that sets only a foreground colour leaves the background colour as it was, so
+    // a background colour can be removed only by resetting both colours.
+    if (to.background.is_none() && from.background.is_some()) ||
+        (to.foreground.is_none() && to.background.is_none())
+    {
+        output.push(COLOR);
+    }
+
+    match (to.foreground, to.background) {
+        (None, None) => {}
+        (Some(Color::Rgb(r, g, b)), None) => write_rgb_code(output, (r, g, b), None),
+        (foreground, None) => write_palette_code(output, foreground, None),
+        (Some(Color::Rgb(r, g, b)), Some(Color::Rgb(br, bg, bb))) => {
+            write_rgb_code(output, (r, g, b), Some((br, bg, bb)))
+        }
+        (foreground, Some(Color::Rgb(br, bg, bb))) => {
+            // The hexadecimal colour code cannot set only a background colour, so a placeholder
+            // foreground colour is given, and then replaced.
+            write_rgb_code(output, (0, 0, 0), Some((br, bg, bb)));
+            write_palette_code(output, foreground, None);
+        }
+        (Some(Color::Rgb(r, g, b)), Some(Color::Palette(background))) => {
+            write_palette_code(output, None, Some(background));
+            write_rgb_code(output, (r, g, b), None);
+        }
+        (foreground, Some(Color::Palette(background))) => {
+            write_palette_code(output, foreground, Some(background))
+        }
+    }
+}
+
+fn write_palette_code(output: &mut String, foreground: Option<Color>, background: Option<u8>) {
+    let foreground = match foreground {
+        Some(Color::Palette(n)) if n < DEFAULT_PALETTE_COLOR => n,
+        _ => DEFAULT_PALETTE_COLOR,
+    };
+
+    output.push(COLOR);
+    write!(output, "{:02}", foreground).unwrap();
+
+    if let Some(background) = background {
+        write!(output, ",{:02}", background.min(DEFAULT_PALETTE_COLOR)).unwrap();
+    }
+}
+
+fn write_rgb_code(output: &mut String, foreground: (u8, u8, u8), background: Option<(u8, u8, u8)>) {
+    output.push(HEX_COLOR);
+    write!(output, "{:02X}{:02X}{:02X}", foreground.0, foreground.1, foreground.2).unwrap();
+
+    if let Some((r, g, b)) = background {
+        write!(output, ",{:02X}{:02X}{:02X}", r, g, b).unwrap();
+    }
+}
+
+/// Parses a palette colour of one or two digits from the start of the given text, returning the
+/// colour and the number of bytes it occupies.
+fn parse_palette_color(text: &str) -> Option<(Option<Color>, usize)> {
+    let len = text.bytes().take(2).take_while(u8::is_ascii_digit).count();
+
+    if len == 0 {
+        return None;
+    }
+
+    let n = text[..len].parse().unwrap();
+
+    Some((
+        if n == DEFAULT_PALETTE_COLOR {
+            None
+        } else {
+            Some(Color::Palette(n))
+        },
+        len,
+    ))
+}
+
+/// Parses an RGB colour of six hexadecimal digits from the start of the given text, returning the
+/// colour and the number of bytes it occupies.
+fn parse_rgb_color(text: &str) -> Option<(Option<Color>, usize)> {
+    if text.len() < 6 || !text.bytes().take(6).all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let component = |i| u8::from_str_radix(&text[i..i + 2], 16).unwrap();
+
+    Some((Some(Color::Rgb(component(0), component(2), component(4))), 6))
+}
+
+fn is_format_char(c: char) -> bool {
+    match c {
+        BOLD | COLOR | HEX_COLOR | RESET | MONOSPACE | REVERSE | ITALIC | STRIKETHROUGH |
+        UNDERLINE => true,
+        _ => false,
+    }
+}
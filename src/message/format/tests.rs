@@ -0,0 +1,142 @@
+#![cfg(test)]
+
+use super::*;
+
+fn styled(text: &str) -> Vec<(char, Style)> {
+    spans(text)
+        .flat_map(|span| span.text.chars().map(move |c| (c, span.style)))
+        .collect()
+}
+
+#[test]
+fn spans_1() {
+    let bold = Style {
+        bold: true,
+        ..Style::default()
+    };
+    let red_on_blue = Style {
+        foreground: Some(Color::Palette(4)),
+        background: Some(Color::Palette(12)),
+        ..Style::default()
+    };
+
+    assert_eq!(
+        spans("plain \x02bold\x02 \x0304,12red on blue\x03 plain").collect::<Vec<_>>(),
+        [
+            Span {
+                text: "plain ",
+                style: Style::default(),
+            },
+            Span {
+                text: "bold",
+                style: bold,
+            },
+            Span {
+                text: " ",
+                style: Style::default(),
+            },
+            Span {
+                text: "red on blue",
+                style: red_on_blue,
+            },
+            Span {
+                text: " plain",
+                style: Style::default(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn colors_1() {
+    let colors = |text| {
+        spans(text)
+            .map(|span| (span.text, span.style.foreground, span.style.background))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(colors("\x034x"), [("x", Some(Color::Palette(4)), None)]);
+    assert_eq!(colors("\x03045"), [("5", Some(Color::Palette(4)), None)]);
+    assert_eq!(colors("\x034,x"), [(",x", Some(Color::Palette(4)), None)]);
+    assert_eq!(colors("\x03,4x"), [(",4x", None, None)]);
+    assert_eq!(colors("\x0399,1x"), [("x", None, Some(Color::Palette(1)))]);
+    assert_eq!(
+        colors("\x04FF8000,000080x"),
+        [("x", Some(Color::Rgb(255, 128, 0)), Some(Color::Rgb(0, 0, 128)))]
+    );
+    assert_eq!(colors("\x04FF80x"), [("FF80x", None, None)]);
+    assert_eq!(
+        colors("\x031,2a\x033b\x03c"),
+        [
+            ("a", Some(Color::Palette(1)), Some(Color::Palette(2))),
+            ("b", Some(Color::Palette(3)), Some(Color::Palette(2))),
+            ("c", None, None),
+        ]
+    );
+}
+
+#[test]
+fn strip_1() {
+    assert_eq!(strip("no formatting"), "no formatting");
+    assert_eq!(
+        strip("\x02\x1Dstyled\x0F \x0304,12red\x03, \x04FF0000hex\x04 \x16\x1E\x1F\x11end"),
+        "styled red, hex end"
+    );
+    assert_eq!(strip("\x0312,345"), "5");
+}
+
+#[test]
+fn render_1() {
+    let style = |bold, foreground| Style {
+        bold,
+        foreground,
+        ..Style::default()
+    };
+
+    assert_eq!(
+        render(vec![
+            Span {
+                text: "a",
+                style: style(true, Some(Color::Palette(4))),
+            },
+            Span {
+                text: "1",
+                style: style(true, None),
+            },
+            Span {
+                text: ",b\x02",
+                style: style(false, Some(Color::Rgb(0, 255, 0))),
+            },
+        ]),
+        "\x02\x0304a\x03\x02\x021\x02\x0400FF00\x02\x02,b\x0F"
+    );
+}
+
+quickcheck! {
+    fn render_roundtrip_1(text: String) -> bool {
+        styled(&render(spans(&text))) == styled(&text)
+    }
+
+    fn render_roundtrip_2(parts: Vec<(String, u8, Option<(u8, u8, u8)>, bool)>) -> bool {
+        let spans = parts
+            .iter()
+            .map(|&(ref text, fg, bg, bold)| {
+                Span {
+                    text,
+                    style: Style {
+                        bold,
+                        foreground: if fg < 99 { Some(Color::Palette(fg)) } else { None },
+                        background: bg.map(|(r, g, b)| Color::Rgb(r, g, b)),
+                        ..Style::default()
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        let expected = spans
+            .iter()
+            .flat_map(|span| strip(span.text).chars().map(|c| (c, span.style)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        styled(&render(spans.iter().cloned())) == expected
+    }
+}
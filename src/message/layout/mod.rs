@@ -0,0 +1,153 @@
+//! Locating the components of a raw IRC message.
+//!
+//! The grammar accepted here is that of [RFC 1459], section 2.3.1, as amended by [RFC 2812],
+//! section 2.3.1, with the optional leading tag section of [IRCv3 message tags]. The parser is
+//! deliberately lenient about spacing: runs of spaces between components are accepted, as are
+//! trailing spaces at the end of a line.
+//!
+//! [RFC 1459]: <https://tools.ietf.org/html/rfc1459#section-2.3.1>
+//! [RFC 2812]: <https://tools.ietf.org/html/rfc2812#section-2.3.1>
+//! [IRCv3 message tags]: <http://ircv3.net/specs/core/message-tags-3.2.html>
+
+use super::ErrorKind;
+use super::Result;
+use smallvec::SmallVec;
+
+/// The number of parameters an IRC message may have under RFC 2812; more are accepted, but spill
+/// onto the heap.
+const PARAMS_INLINE_CAPACITY: usize = 15;
+
+pub(crate) type SpanArray = [Span; PARAMS_INLINE_CAPACITY];
+
+pub(crate) type Spans = SmallVec<SpanArray>;
+
+/// A range of byte indices into a raw message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The positions of the components of a raw IRC message.
+///
+/// The spans exclude the syntactic markers of their components; e.g., the span of the tag section
+/// excludes the leading `@`, and the span of the prefix excludes the leading `:`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Layout {
+    pub tags: Option<Span>,
+    pub prefix: Option<Span>,
+    pub command: Span,
+    pub params: Spans,
+
+    /// Whether the last parameter was introduced with a colon, i.e., was a "trailing" parameter in
+    /// the terms of RFC 2812.
+    pub has_trailing: bool,
+}
+
+impl Span {
+    pub fn of<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
+        &bytes[self.start..self.end]
+    }
+}
+
+impl Layout {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let invalid = |desc: &'static str| ErrorKind::InvalidMessage(bytes.to_owned(), desc.into());
+
+        if let Some(&byte) = bytes.iter().find(|&&b| b == b'\0' || b == b'\r' || b == b'\n') {
+            bail!(invalid(match byte {
+                b'\0' => "message contains a NUL character",
+                _ => "message contains a line break",
+            }))
+        }
+
+        let mut pos = 0;
+
+        let tags = if bytes.first() == Some(&b'@') {
+            let span = word_at(bytes, 1);
+            ensure!(span.start != span.end, invalid("message has an empty tag section"));
+            pos = span.end;
+            Some(span)
+        } else {
+            None
+        };
+
+        pos = skip_spaces(bytes, pos);
+
+        let prefix = if bytes.get(pos) == Some(&b':') {
+            let span = word_at(bytes, pos + 1);
+            ensure!(span.start != span.end, invalid("message has an empty prefix"));
+            pos = span.end;
+            Some(span)
+        } else {
+            None
+        };
+
+        pos = skip_spaces(bytes, pos);
+
+        let command = word_at(bytes, pos);
+        pos = command.end;
+
+        ensure!(command.start != command.end, invalid("message has no command"));
+        ensure!(
+            is_valid_command(command.of(bytes)),
+            invalid("message command is neither a word nor a three-digit number")
+        );
+
+        let mut params = SmallVec::new();
+        let mut has_trailing = false;
+
+        loop {
+            pos = skip_spaces(bytes, pos);
+
+            match bytes.get(pos) {
+                None => break,
+                Some(&b':') => {
+                    params.push(Span {
+                        start: pos + 1,
+                        end: bytes.len(),
+                    });
+                    has_trailing = true;
+                    break;
+                }
+                Some(_) => {
+                    let span = word_at(bytes, pos);
+                    pos = span.end;
+                    params.push(span);
+                }
+            }
+        }
+
+        Ok(Layout {
+            tags,
+            prefix,
+            command,
+            params,
+            has_trailing,
+        })
+    }
+}
+
+/// Returns the span of the run of non-space bytes starting at `start`.
+fn word_at(bytes: &[u8], start: usize) -> Span {
+    let end = bytes[start..]
+        .iter()
+        .position(|&b| b == b' ')
+        .map(|len| start + len)
+        .unwrap_or(bytes.len());
+
+    Span { start, end }
+}
+
+fn skip_spaces(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos) == Some(&b' ') {
+        pos += 1;
+    }
+
+    pos
+}
+
+pub(crate) fn is_valid_command(command: &[u8]) -> bool {
+    command.iter().all(|b| b.is_ascii_alphabetic()) ||
+        (command.len() == 3 && command.iter().all(|b| b.is_ascii_digit()))
+}
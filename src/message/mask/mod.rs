@@ -0,0 +1,302 @@
+//! User hostmasks and the wildcard masks that match them.
+//!
+//! A user is identified on IRC by a hostmask of the form `nick!user@host`, as found in the prefix
+//! of each message the user sends. Bans, ban exceptions, invite exceptions, and client-side
+//! ignore lists are given as masks of the same form in which `*` stands for any sequence of bytes
+//! and `?` stands for any single byte, as in `*!*@*.example.com`. Masks are compared without regard
+//! to case, under the server's [case mapping].
+//!
+//! Many servers also accept "extended bans", which match users by something other than their
+//! hostmasks, such as their accounts, as in `$a:alice` or `~a:alice`. The syntax and meaning of
+//! extended bans differ between servers, so they are recognized and exposed as [`ExtBan`]s, but
+//! never matched against hostmasks.
+//!
+//! [case mapping]: ../casemap/enum.CaseMapping.html
+//! [`ExtBan`]: struct.ExtBan.html
+
+use super::Message;
+use super::casemap::CaseMapping;
+use super::split_prefix;
+use std::fmt;
+
+mod tests;
+
+/// A user's `nick!user@host` identity, borrowed from a message prefix.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Hostmask<'a> {
+    nick: &'a [u8],
+    user: Option<&'a [u8]>,
+    host: Option<&'a [u8]>,
+}
+
+/// A mask that matches hostmasks, such as a ban mask, or an extended ban.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Mask {
+    pattern: Vec<u8>,
+}
+
+/// An extended ban, borrowed from a [`Mask`].
+///
+/// Extended bans take one of the following forms, where `x` is the ban's type, which may be
+/// longer than one character on some servers:
+///
+/// - `$x` or `$x:arg`, as on Charybdis and its descendants, where `$~x` negates the ban;
+///
+/// - `~x:arg`, as on UnrealIRCd; and
+///
+/// - `x:arg`, as on InspIRCd, which is distinguished from a hostmask by the colon, as nicknames
+///   cannot contain colons.
+///
+/// [`Mask`]: struct.Mask.html
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ExtBan<'a> {
+    prefix: Option<u8>,
+    negated: bool,
+    kind: &'a [u8],
+    arg: Option<&'a [u8]>,
+}
+
+impl<'a> Hostmask<'a> {
+    /// Returns the hostmask of the sender of the given message, if its prefix names a user rather
+    /// than a server.
+    pub fn from_message<Msg>(msg: &'a Msg) -> Option<Self>
+    where
+        Msg: Message,
+    {
+        Self::parse(msg.prefix()?)
+    }
+
+    /// Parses a message prefix, excluding its leading `:`, as a hostmask, returning `None` if it
+    /// names a server rather than a user. The username and hostname may be absent, as servers omit
+    /// them in some messages.
+    pub fn parse(prefix: &'a [u8]) -> Option<Self> {
+        match split_prefix(prefix) {
+            (Some(nick), user, host) => Some(Hostmask { nick, user, host }),
+            _ => None,
+        }
+    }
+
+    pub fn nick(&self) -> &'a [u8] {
+        self.nick
+    }
+
+    pub fn user(&self) -> Option<&'a [u8]> {
+        self.user
+    }
+
+    pub fn host(&self) -> Option<&'a [u8]> {
+        self.host
+    }
+
+    /// Returns the hostmask in its `nick!user@host` form, omitting the parts that are absent.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            self.nick.len() + self.user.map_or(0, |u| u.len() + 1) +
+                self.host.map_or(0, |h| h.len() + 1),
+        );
+
+        bytes.extend_from_slice(self.nick);
+
+        if let Some(user) = self.user {
+            bytes.push(b'!');
+            bytes.extend_from_slice(user);
+        }
+
+        if let Some(host) = self.host {
+            bytes.push(b'@');
+            bytes.extend_from_slice(host);
+        }
+
+        bytes
+    }
+}
+
+impl<'a> fmt::Display for Hostmask<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+impl Mask {
+    /// Constructs a mask from the given pattern, as it would appear in a `MODE` message or ban
+    /// list, without normalizing it.
+    pub fn new<B>(pattern: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        Mask { pattern: pattern.into() }
+    }
+
+    /// Constructs a mask from the given pattern, completing it to the `nick!user@host` form in the
+    /// way that servers do: `nick` becomes `nick!*@*`, `user@host` becomes `*!user@host`, and
+    /// `nick!user` becomes `nick!user@*`, while a lone name containing a period, such as
+    /// `*.example.com`, is taken to be a hostname and becomes `*!*@*.example.com`. Extended bans
+    /// are left as they are.
+    pub fn normalized<B>(pattern: B) -> Self
+    where
+        B: Into<Vec<u8>>,
+    {
+        let mask = Self::new(pattern);
+
+        if mask.is_extban() || mask.pattern.is_empty() {
+            return mask;
+        }
+
+        let has_user = mask.pattern.contains(&b'!');
+        let has_host = mask.pattern.contains(&b'@');
+
+        let mut pattern = Vec::with_capacity(mask.pattern.len() + 6);
+
+        match (has_user, has_host) {
+            (false, false) if mask.pattern.contains(&b'.') => {
+                pattern.extend_from_slice(b"*!*@");
+                pattern.extend_from_slice(&mask.pattern);
+            }
+            (false, false) => {
+                pattern.extend_from_slice(&mask.pattern);
+                pattern.extend_from_slice(b"!*@*");
+            }
+            (false, true) => {
+                pattern.extend_from_slice(b"*!");
+                pattern.extend_from_slice(&mask.pattern);
+            }
+            (true, false) => {
+                pattern.extend_from_slice(&mask.pattern);
+                pattern.extend_from_slice(b"@*");
+            }
+            (true, true) => return mask,
+        }
+
+        Mask { pattern }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.pattern
+    }
+
+    /// Returns whether this mask is an extended ban.
+    pub fn is_extban(&self) -> bool {
+        self.extban().is_some()
+    }
+
+    /// Returns the extended ban that this mask expresses, if it is one.
+    pub fn extban(&self) -> Option<ExtBan> {
+        ExtBan::parse(&self.pattern)
+    }
+
+    /// Returns whether this mask matches the given hostmask under the given case mapping. An
+    /// extended ban never matches, as its meaning depends on the server.
+    pub fn matches(&self, hostmask: &Hostmask, casemapping: CaseMapping) -> bool {
+        self.matches_bytes(&hostmask.to_bytes(), casemapping)
+    }
+
+    /// Returns whether this mask matches the given bytes, such as a hostmask given in the
+    /// `nick!user@host` form, under the given case mapping. An extended ban never matches.
+    pub fn matches_bytes(&self, subject: &[u8], casemapping: CaseMapping) -> bool {
+        !self.is_extban() && glob_match(&self.pattern, subject, casemapping)
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.pattern))
+    }
+}
+
+impl<'a> ExtBan<'a> {
+    /// Parses the given mask as an extended ban, returning `None` if it is not one.
+    pub fn parse(mask: &'a [u8]) -> Option<Self> {
+        let (prefix, rest) = match mask.first() {
+            Some(&b'$') => (Some(b'$'), &mask[1..]),
+            Some(&b'~') => (Some(b'~'), &mask[1..]),
+            _ => (None, mask),
+        };
+
+        let (negated, rest) = if prefix == Some(b'$') && rest.starts_with(b"~") {
+            (true, &rest[1..])
+        } else {
+            (false, rest)
+        };
+
+        let (kind, arg) = match rest.iter().position(|&b| b == b':') {
+            Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+            // Only the `$` form may omit the argument.
+            None if prefix == Some(b'$') => (rest, None),
+            None => return None,
+        };
+
+        if kind.is_empty() || !kind.iter().all(u8::is_ascii_alphanumeric) {
+            return None;
+        }
+
+        Some(ExtBan {
+            prefix,
+            negated,
+            kind,
+            arg,
+        })
+    }
+
+    /// Returns the character that introduced the extended ban, `$` or `~`, if there was one.
+    pub fn prefix(&self) -> Option<u8> {
+        self.prefix
+    }
+
+    /// Returns whether the extended ban was negated, as in `$~a`.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Returns the extended ban's type, such as `a` or `account`.
+    pub fn kind(&self) -> &'a [u8] {
+        self.kind
+    }
+
+    /// Returns the text following the colon after the extended ban's type, if there is a colon.
+    pub fn arg(&self) -> Option<&'a [u8]> {
+        self.arg
+    }
+}
+
+/// Returns whether the given pattern, in which `*` matches any sequence of bytes and `?` matches
+/// any single byte, matches the whole of the given subject.
+fn glob_match(pattern: &[u8], subject: &[u8], casemapping: CaseMapping) -> bool {
+    let (mut p, mut s) = (0, 0);
+
+    // The position in the pattern just after the last `*` seen, and the position in the subject
+    // from which that `*` is currently taken to match, for backtracking.
+    let mut backtrack = None;
+
+    while s < subject.len() {
+        match pattern.get(p) {
+            Some(&b'*') => {
+                p += 1;
+                backtrack = Some((p, s));
+                continue;
+            }
+            Some(&b'?') => {
+                p += 1;
+                s += 1;
+                continue;
+            }
+            Some(&b) if casemapping.fold_byte(b) == casemapping.fold_byte(subject[s]) => {
+                p += 1;
+                s += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        match backtrack {
+            Some((star_p, star_s)) => {
+                // Let the last `*` match one more byte, and try again from there.
+                p = star_p;
+                s = star_s + 1;
+                backtrack = Some((star_p, s));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&b| b == b'*')
+}
@@ -0,0 +1,156 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use quickcheck::TestResult;
+use std::borrow::Cow;
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+#[test]
+fn hostmask_parse_1() {
+    let hostmask = Hostmask::parse(b"nick!~user@host.example.com").unwrap();
+
+    assert_eq!(hostmask.nick(), b"nick");
+    assert_eq!(hostmask.user(), Some(&b"~user"[..]));
+    assert_eq!(hostmask.host(), Some(&b"host.example.com"[..]));
+    assert_eq!(hostmask.to_string(), "nick!~user@host.example.com");
+
+    let hostmask = Hostmask::parse(b"nick").unwrap();
+
+    assert_eq!(hostmask.nick(), b"nick");
+    assert_eq!(hostmask.user(), None);
+    assert_eq!(hostmask.host(), None);
+
+    assert_eq!(Hostmask::parse(b"irc.example.com"), None);
+}
+
+#[test]
+fn hostmask_from_message_1() {
+    let message = msg(b":nick!user@host PRIVMSG #chan :hi");
+
+    assert_eq!(
+        Hostmask::from_message(&message),
+        Hostmask::parse(b"nick!user@host")
+    );
+
+    let message = msg(b":irc.example.com NOTICE * :hi");
+
+    assert_eq!(Hostmask::from_message(&message), None);
+
+    let message = msg(b"PING :irc.example.com");
+
+    assert_eq!(Hostmask::from_message(&message), None);
+}
+
+#[test]
+fn mask_matches_1() {
+    let hostmask = Hostmask::parse(b"Nick[a]!~user@host.example.com").unwrap();
+
+    for &(pattern, expected) in &[
+        (&b"*!*@*"[..], true),
+        (b"*", true),
+        (b"nick[a]!*@*", true),
+        (b"NICK{A}!*@*", true),
+        (b"*!~user@*.example.com", true),
+        (b"*!?user@*", true),
+        (b"*!??user@*", false),
+        (b"*!*@*.example.org", false),
+        (b"nick!*@*", false),
+        (b"*a*a*a*", false),
+        (b"", false),
+    ] {
+        assert_eq!(
+            Mask::new(pattern).matches(&hostmask, CaseMapping::Rfc1459),
+            expected,
+            "{:?}",
+            String::from_utf8_lossy(pattern)
+        );
+    }
+
+    assert!(!Mask::new("NICK{A}!*@*").matches(&hostmask, CaseMapping::Ascii));
+}
+
+#[test]
+fn mask_normalized_1() {
+    for &(pattern, expected) in &[
+        ("nick", "nick!*@*"),
+        ("user@host", "*!user@host"),
+        ("nick!user", "nick!user@*"),
+        ("*.example.com", "*!*@*.example.com"),
+        ("nick!user@host", "nick!user@host"),
+        ("$a:account", "$a:account"),
+        ("", ""),
+    ] {
+        assert_eq!(Mask::normalized(pattern).as_bytes(), expected.as_bytes());
+    }
+}
+
+#[test]
+fn extban_1() {
+    let mask = Mask::new("$a:alice");
+    let extban = mask.extban().unwrap();
+
+    assert_eq!(extban.prefix(), Some(b'$'));
+    assert!(!extban.is_negated());
+    assert_eq!(extban.kind(), b"a");
+    assert_eq!(extban.arg(), Some(&b"alice"[..]));
+
+    let extban = ExtBan::parse(b"$~a").unwrap();
+
+    assert!(extban.is_negated());
+    assert_eq!(extban.kind(), b"a");
+    assert_eq!(extban.arg(), None);
+
+    let extban = ExtBan::parse(b"~q:*!*@*.example.com").unwrap();
+
+    assert_eq!(extban.prefix(), Some(b'~'));
+    assert_eq!(extban.kind(), b"q");
+    assert_eq!(extban.arg(), Some(&b"*!*@*.example.com"[..]));
+
+    let extban = ExtBan::parse(b"account:alice").unwrap();
+
+    assert_eq!(extban.prefix(), None);
+    assert_eq!(extban.kind(), b"account");
+
+    for mask in &[
+        &b"*!*@*"[..],
+        b"~user@host",
+        b"*!*@2001:db8::1",
+        b"~",
+        b":foo",
+    ] {
+        assert_eq!(ExtBan::parse(mask), None, "{:?}", String::from_utf8_lossy(mask));
+    }
+
+    let hostmask = Hostmask::parse(b"alice!alice@host").unwrap();
+
+    assert!(!mask.matches(&hostmask, CaseMapping::Rfc1459));
+}
+
+quickcheck! {
+    fn mask_matches_itself_1(subject: String) -> TestResult {
+        if subject.contains(|c| c == '*' || c == '?') || ExtBan::parse(subject.as_bytes()).is_some()
+        {
+            return TestResult::discard();
+        }
+
+        TestResult::from_bool(
+            Mask::new(subject.clone()).matches_bytes(subject.as_bytes(), CaseMapping::Rfc1459),
+        )
+    }
+
+    fn mask_star_matches_all_1(prefix: String, subject: String) -> TestResult {
+        if prefix.contains(|c| c == '*' || c == '?') || ExtBan::parse(prefix.as_bytes()).is_some()
+        {
+            return TestResult::discard();
+        }
+
+        let mask = Mask::new(format!("{}*", prefix));
+        let subject = format!("{}{}", prefix, subject);
+
+        TestResult::from_bool(mask.matches_bytes(subject.as_bytes(), CaseMapping::Ascii))
+    }
+}
@@ -1,17 +1,69 @@
+pub use self::builder::MessageBuilder;
+pub use self::command::Command;
 pub use self::err::*;
+pub use self::msg_ref::MessageRef;
+pub use self::owned::OwnedMessage;
+use self::encoding::Decoding;
+use self::layout::SpanArray;
+use self::layout::Spans;
+use self::numeric::Numeric;
+use self::tags::Tags;
 #[cfg(feature = "pircolate")]
 use pircolate;
+use smallvec;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::str;
 
+mod builder;
+mod command;
 mod err;
+mod layout;
+mod msg_ref;
+mod owned;
+
+pub mod casemap;
+pub mod cmd;
+pub mod ctcp;
+pub mod encoding;
+pub mod format;
+pub mod mask;
+pub mod modes;
+pub mod numeric;
+pub mod tags;
+
+/// The maximum length of an IRC message, excluding any IRCv3 tags but including the terminating
+/// CR-LF sequence, per RFC 2812, section 2.3.
+pub const BODY_MAX_LEN: usize = 512;
+
+/// The maximum length of the IRCv3 tag section of a message, including its leading `@` and the
+/// space that follows it, per the IRCv3 message tags specification.
+pub const TAGS_MAX_LEN: usize = 8191;
 
 pub trait Message: Clone + fmt::Debug {
     fn try_from<'a>(Cow<'a, [u8]>) -> Result<Self>
     where
         Self: Sized;
 
+    /// Constructs a message from bytes that have been decoded to UTF-8 in the given way, as by a
+    /// connection's [`DecodingPolicy`]. The default implementation disregards the decoding.
+    ///
+    /// [`DecodingPolicy`]: encoding/enum.DecodingPolicy.html
+    fn try_from_decoded<'a>(input: Cow<'a, [u8]>, decoding: Decoding) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let _ = decoding;
+        Self::try_from(input)
+    }
+
+    /// Returns the way in which the message was decoded upon receipt, if the message was received
+    /// and its type records this.
+    fn decoding(&self) -> Option<Decoding> {
+        None
+    }
+
     fn as_bytes(&self) -> &[u8];
 
     fn as_str(&self) -> Option<&str> {
@@ -33,6 +85,137 @@ pub trait Message: Clone + fmt::Debug {
     }
 
     fn command_bytes(&self) -> &[u8];
+
+    /// Returns the message's command.
+    fn command(&self) -> Command {
+        Command::from_bytes(self.command_bytes())
+    }
+
+    /// Returns the message's numeric reply, if its command is one.
+    fn numeric(&self) -> Option<Numeric> {
+        Numeric::from_bytes(self.command_bytes())
+    }
+
+    /// Returns the message's IRCv3 tag section, excluding the leading `@`, if the message has one.
+    fn tags_bytes(&self) -> Option<&[u8]> {
+        MessageRef::parse(self.as_bytes()).ok().and_then(|msg| msg.tags_bytes())
+    }
+
+    /// Returns an iterator over the message's IRCv3 tags, in the order in which they appear.
+    fn tags(&self) -> Tags {
+        Tags::new(self.tags_bytes().unwrap_or(b""))
+    }
+
+    /// Returns the unescaped value of the IRCv3 tag with the given key, including any client-only
+    /// marker and vendor prefix, if the message has such a tag. A tag that is present but has no
+    /// value yields an empty value. If the key occurs more than once, the last occurrence is used,
+    /// per the specification.
+    fn tag(&self, key: &[u8]) -> Option<Cow<[u8]>> {
+        self.tags().filter(|tag| tag.key() == key).last().map(|tag| tag.value())
+    }
+
+    /// Returns a map from the keys of the message's IRCv3 tags to their unescaped values.
+    fn tag_map(&self) -> BTreeMap<&[u8], Cow<[u8]>> {
+        self.tags().map(|tag| (tag.key(), tag.value())).collect()
+    }
+
+    /// Returns the message's prefix, excluding the leading `:`, if the message has one.
+    fn prefix(&self) -> Option<&[u8]> {
+        MessageRef::parse(self.as_bytes()).ok().and_then(|msg| msg.prefix())
+    }
+
+    /// Returns the nickname from the message's prefix, if the prefix names a user rather than a
+    /// server.
+    fn nick(&self) -> Option<&[u8]> {
+        self.prefix().and_then(|prefix| split_prefix(prefix).0)
+    }
+
+    /// Returns the username from the message's prefix, if the prefix includes one.
+    fn user(&self) -> Option<&[u8]> {
+        self.prefix().and_then(|prefix| split_prefix(prefix).1)
+    }
+
+    /// Returns the hostname from the message's prefix, if the prefix includes one.
+    fn host(&self) -> Option<&[u8]> {
+        self.prefix().and_then(|prefix| split_prefix(prefix).2)
+    }
+
+    /// Returns an iterator over the message's parameters, in order. The colon introducing a
+    /// trailing parameter is not included in that parameter.
+    fn params(&self) -> Params {
+        match MessageRef::parse(self.as_bytes()) {
+            Ok(msg) => msg.params(),
+            Err(_) => Params::new(self.as_bytes(), Spans::new()),
+        }
+    }
+
+    /// Returns the parameter at the given zero-based index, if there is one.
+    fn param(&self, index: usize) -> Option<&[u8]> {
+        self.params().nth(index)
+    }
+
+    /// Returns the message's final parameter, which is usually the free-form text of a message
+    /// such as a `PRIVMSG`, regardless of whether it was introduced with a colon.
+    fn trailing(&self) -> Option<&[u8]> {
+        self.params().next_back()
+    }
+}
+
+/// Splits a message prefix into its nickname, username, and hostname parts.
+///
+/// A prefix consisting of a single name that contains a period is taken to be a server name, as
+/// nicknames cannot contain periods; for such a prefix, all three parts are `None`.
+fn split_prefix(prefix: &[u8]) -> (Option<&[u8]>, Option<&[u8]>, Option<&[u8]>) {
+    let (rest, host) = match prefix.iter().position(|&b| b == b'@') {
+        Some(i) => (&prefix[..i], Some(&prefix[i + 1..])),
+        None => (prefix, None),
+    };
+
+    let (name, user) = match rest.iter().position(|&b| b == b'!') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+
+    if user.is_none() && host.is_none() && name.contains(&b'.') {
+        (None, None, None)
+    } else {
+        (Some(name), user, host)
+    }
+}
+
+/// An iterator over the parameters of an IRC message.
+pub struct Params<'a> {
+    bytes: &'a [u8],
+    spans: smallvec::IntoIter<SpanArray>,
+}
+
+impl<'a> Params<'a> {
+    fn new(bytes: &'a [u8], spans: Spans) -> Self {
+        Params {
+            bytes,
+            spans: spans.into_iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for Params<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.bytes;
+        self.spans.next().map(|span| span.of(bytes))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.spans.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Params<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let bytes = self.bytes;
+        self.spans.next_back().map(|span| span.of(bytes))
+    }
 }
 
 #[cfg(feature = "pircolate")]
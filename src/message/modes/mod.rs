@@ -0,0 +1,448 @@
+//! Parsing and construction of `MODE` messages.
+//!
+//! A `MODE` message changes a channel's or user's modes with a mode string, such as `+ov-k`,
+//! followed by the arguments of those modes that take arguments, as in `MODE #chan +ov-k alice bob
+//! key`. Which channel modes take arguments differs between servers, and is advertised in the
+//! `CHANMODES` and `PREFIX` tokens of `RPL_ISUPPORT`; a [`ModeSpec`] records this. User modes are
+//! taken to take no arguments.
+//!
+//! [`ModeSpec`]: struct.ModeSpec.html
+
+use super::BODY_MAX_LEN;
+use super::Command;
+use super::ErrorKind;
+use super::Message;
+use super::Result;
+use super::cmd;
+use super::numeric::RPL_CHANNELMODEIS;
+use super::numeric::RPL_UMODEIS;
+use std::str;
+
+mod tests;
+
+/// Whether a mode is being set or unset.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+/// The kind of a channel mode, which determines when it takes an argument.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ModeType {
+    /// A mode that adds an entry to or removes one from a list, such as the ban list, and always
+    /// takes an argument. Given in the first group of `CHANMODES`.
+    List,
+
+    /// A setting that always takes an argument, such as the channel key. Given in the second group
+    /// of `CHANMODES`.
+    ParamAlways,
+
+    /// A setting that takes an argument only when it is set, such as the user limit. Given in the
+    /// third group of `CHANMODES`.
+    ParamWhenSet,
+
+    /// A setting that never takes an argument. Given in the fourth group of `CHANMODES`, and
+    /// assumed of modes that the server did not advertise.
+    NoParam,
+
+    /// A mode that grants or revokes a channel membership prefix, such as operator status, and
+    /// always takes a nickname as its argument. Given in `PREFIX`.
+    Prefix,
+}
+
+/// A description of the channel modes that a server supports.
+///
+/// The default description is that of RFC 2811, as commonly advertised in `RPL_ISUPPORT`:
+/// `CHANMODES=beI,k,l,imnpst`, `PREFIX=(ov)@+`, `MODES=3`, and `CHANTYPES=#&`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ModeSpec {
+    list: Vec<u8>,
+    param_always: Vec<u8>,
+    param_when_set: Vec<u8>,
+    no_param: Vec<u8>,
+    prefix_modes: Vec<u8>,
+    prefix_symbols: Vec<u8>,
+    max_modes: Option<usize>,
+    chantypes: Vec<u8>,
+}
+
+/// A single change to a mode, as parsed from a `MODE` message.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ModeChange<'a> {
+    pub sign: Sign,
+    pub mode: u8,
+    pub arg: Option<&'a [u8]>,
+}
+
+/// Builds as many `MODE` messages as are needed to make a sequence of mode changes to one channel
+/// or user, without exceeding the server's limit on the number of modes with arguments in each
+/// message (`MODES`) or the length limit of the IRC protocol.
+#[derive(Clone, Debug)]
+pub struct ModeBuilder {
+    target: String,
+    changes: Vec<(Sign, u8, Option<String>)>,
+}
+
+impl Sign {
+    /// Returns the character that introduces modes of this sign in a mode string.
+    pub fn as_byte(&self) -> u8 {
+        match *self {
+            Sign::Plus => b'+',
+            Sign::Minus => b'-',
+        }
+    }
+}
+
+impl Default for ModeSpec {
+    fn default() -> Self {
+        ModeSpec {
+            list: b"beI".to_vec(),
+            param_always: b"k".to_vec(),
+            param_when_set: b"l".to_vec(),
+            no_param: b"imnpst".to_vec(),
+            prefix_modes: b"ov".to_vec(),
+            prefix_symbols: b"@+".to_vec(),
+            max_modes: Some(3),
+            chantypes: b"#&".to_vec(),
+        }
+    }
+}
+
+impl ModeSpec {
+    /// Replaces the channel modes of this description with those given in the value of a
+    /// `CHANMODES` token, such as `beI,k,l,imnpst`. Groups beyond the fourth are ignored, per the
+    /// specification, and their modes are assumed to take no arguments.
+    pub fn with_chanmodes(mut self, value: &[u8]) -> Result<Self> {
+        let groups = value.split(|&b| b == b',').collect::<Vec<_>>();
+
+        ensure!(
+            groups.len() >= 4,
+            ErrorKind::InvalidComponent(
+                "CHANMODES value",
+                value.to_owned(),
+                "must have at least four comma-separated groups".into(),
+            )
+        );
+
+        self.list = groups[0].to_vec();
+        self.param_always = groups[1].to_vec();
+        self.param_when_set = groups[2].to_vec();
+        self.no_param = groups[3].to_vec();
+
+        Ok(self)
+    }
+
+    /// Replaces the membership prefixes of this description with those given in the value of a
+    /// `PREFIX` token, such as `(ov)@+`. An empty value means that there are no prefixes.
+    pub fn with_prefix(mut self, value: &[u8]) -> Result<Self> {
+        let (modes, symbols) = match (value.first(), value.iter().position(|&b| b == b')')) {
+            (None, _) => (value, value),
+            (Some(&b'('), Some(i)) => (&value[1..i], &value[i + 1..]),
+            _ => bail!(invalid_prefix(value)),
+        };
+
+        ensure!(modes.len() == symbols.len(), invalid_prefix(value));
+
+        self.prefix_modes = modes.to_vec();
+        self.prefix_symbols = symbols.to_vec();
+
+        Ok(self)
+    }
+
+    /// Sets the maximum number of modes with arguments that may be changed in one message, as
+    /// given in the `MODES` token. `None` means that there is no limit.
+    pub fn with_max_modes(mut self, max_modes: Option<usize>) -> Self {
+        self.max_modes = max_modes;
+        self
+    }
+
+    /// Replaces the channel type characters of this description, which distinguish channel names
+    /// from nicknames, with those given in the value of a `CHANTYPES` token, such as `#&`.
+    pub fn with_chantypes(mut self, value: &[u8]) -> Self {
+        self.chantypes = value.to_vec();
+        self
+    }
+
+    /// Returns the type of the given channel mode. Modes that this description does not mention
+    /// are taken to take no arguments.
+    pub fn mode_type(&self, mode: u8) -> ModeType {
+        if self.prefix_modes.contains(&mode) {
+            ModeType::Prefix
+        } else if self.list.contains(&mode) {
+            ModeType::List
+        } else if self.param_always.contains(&mode) {
+            ModeType::ParamAlways
+        } else if self.param_when_set.contains(&mode) {
+            ModeType::ParamWhenSet
+        } else {
+            ModeType::NoParam
+        }
+    }
+
+    /// Returns whether a change of the given sign to the given channel mode takes an argument.
+    pub fn takes_arg(&self, sign: Sign, mode: u8) -> bool {
+        match self.mode_type(mode) {
+            ModeType::List | ModeType::ParamAlways | ModeType::Prefix => true,
+            ModeType::ParamWhenSet => sign == Sign::Plus,
+            ModeType::NoParam => false,
+        }
+    }
+
+    /// Returns the membership prefix symbol, such as `@`, that the given channel mode grants, if it
+    /// grants one.
+    pub fn prefix_symbol(&self, mode: u8) -> Option<u8> {
+        self.prefix_modes
+            .iter()
+            .position(|&m| m == mode)
+            .map(|i| self.prefix_symbols[i])
+    }
+
+    /// Returns the channel mode that grants the given membership prefix symbol, if there is one.
+    pub fn prefix_mode(&self, symbol: u8) -> Option<u8> {
+        self.prefix_symbols
+            .iter()
+            .position(|&s| s == symbol)
+            .map(|i| self.prefix_modes[i])
+    }
+
+    pub fn max_modes(&self) -> Option<usize> {
+        self.max_modes
+    }
+
+    pub fn chantypes(&self) -> &[u8] {
+        &self.chantypes
+    }
+
+    /// Returns whether the given target of a `MODE` message names a channel.
+    pub fn is_channel(&self, target: &[u8]) -> bool {
+        target.first().map_or(false, |b| self.chantypes.contains(b))
+    }
+}
+
+/// Parses the mode changes made or reported by the given message, which may be a `MODE` message,
+/// an `RPL_CHANNELMODEIS` reply, or an `RPL_UMODEIS` reply. Returns `None` if the message is not
+/// one of these, or lacks a mode string.
+pub fn parse<'a, Msg>(msg: &'a Msg, spec: &ModeSpec) -> Option<Vec<ModeChange<'a>>>
+where
+    Msg: Message,
+{
+    let mut params = msg.params();
+
+    let is_channel = match (msg.command(), msg.numeric()) {
+        (Command::Mode, _) => spec.is_channel(params.next()?),
+        (_, Some(RPL_CHANNELMODEIS)) => {
+            params.next()?;
+            params.next()?;
+            true
+        }
+        (_, Some(RPL_UMODEIS)) => {
+            params.next()?;
+            false
+        }
+        _ => return None,
+    };
+
+    let modes = params.next()?;
+
+    Some(if is_channel {
+        parse_channel_modes(modes, params, spec)
+    } else {
+        parse_user_modes(modes)
+    })
+}
+
+/// Parses the given channel mode string, taking arguments from the given iterator for the modes
+/// that take them according to the given description. A mode that takes an argument but lacks one,
+/// because the arguments ran out, is given none. A mode string that does not start with a sign is
+/// taken to set modes.
+pub fn parse_channel_modes<'a, I>(modes: &[u8], args: I, spec: &ModeSpec) -> Vec<ModeChange<'a>>
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let mut args = args.into_iter();
+
+    parse_mode_string(modes, |sign, mode| if spec.takes_arg(sign, mode) {
+        args.next()
+    } else {
+        None
+    })
+}
+
+/// Parses the given user mode string. User modes are taken to take no arguments.
+pub fn parse_user_modes<'a>(modes: &[u8]) -> Vec<ModeChange<'a>> {
+    parse_mode_string(modes, |_, _| None)
+}
+
+fn parse_mode_string<'a, F>(modes: &[u8], mut take_arg: F) -> Vec<ModeChange<'a>>
+where
+    F: FnMut(Sign, u8) -> Option<&'a [u8]>,
+{
+    let mut sign = Sign::Plus;
+    let mut changes = Vec::with_capacity(modes.len());
+
+    for &b in modes {
+        match b {
+            b'+' => sign = Sign::Plus,
+            b'-' => sign = Sign::Minus,
+            mode => {
+                changes.push(ModeChange {
+                    sign,
+                    mode,
+                    arg: take_arg(sign, mode),
+                })
+            }
+        }
+    }
+
+    changes
+}
+
+impl ModeBuilder {
+    /// Creates a builder of `MODE` messages to the given channel or user.
+    pub fn new<S>(target: S) -> Self
+    where
+        S: Into<String>,
+    {
+        ModeBuilder {
+            target: target.into(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Adds a change to the given mode, with the given argument.
+    pub fn change(mut self, sign: Sign, mode: u8, arg: Option<&str>) -> Self {
+        self.changes.push((sign, mode, arg.map(Into::into)));
+        self
+    }
+
+    /// Adds a change setting the given mode, with the given argument.
+    pub fn plus(self, mode: u8, arg: Option<&str>) -> Self {
+        self.change(Sign::Plus, mode, arg)
+    }
+
+    /// Adds a change unsetting the given mode, with the given argument.
+    pub fn minus(self, mode: u8, arg: Option<&str>) -> Self {
+        self.change(Sign::Minus, mode, arg)
+    }
+
+    /// Builds the messages, checking each change against the given description, and dividing the
+    /// changes among messages so that none has more than `spec.max_modes()` changes with arguments
+    /// or exceeds the length limit of the IRC protocol. The changes are made in the order in which
+    /// they were added.
+    ///
+    /// The description is consulted only if the target is a channel; changes to user modes must
+    /// not have arguments.
+    pub fn build<Msg>(self, spec: &ModeSpec) -> Result<Vec<Msg>>
+    where
+        Msg: Message,
+    {
+        let is_channel = spec.is_channel(self.target.as_bytes());
+
+        for &(sign, mode, ref arg) in &self.changes {
+            ensure!(
+                mode.is_ascii_alphabetic(),
+                ErrorKind::InvalidComponent(
+                    "mode",
+                    vec![mode],
+                    "must be an ASCII letter".into(),
+                )
+            );
+
+            let takes_arg = is_channel && spec.takes_arg(sign, mode);
+
+            ensure!(
+                arg.is_some() == takes_arg,
+                ErrorKind::InvalidComponent(
+                    "mode",
+                    vec![sign.as_byte(), mode],
+                    if takes_arg {
+                        "takes an argument, but was given none".into()
+                    } else {
+                        "takes no argument, but was given one".into()
+                    },
+                )
+            );
+        }
+
+        // A message is `MODE <target> <modes> <args>\r\n`; the space before each argument is
+        // counted with the argument.
+        let overhead = "MODE ".len() + self.target.len() + " ".len() + "\r\n".len();
+        let max_arg_modes = spec.max_modes().unwrap_or(usize::max_value());
+
+        let mut messages = Vec::new();
+        let mut batch = Batch::default();
+
+        for (sign, mode, arg) in self.changes {
+            let arg_len = arg.as_ref().map_or(0, |arg| 1 + arg.len());
+            let would_overflow = overhead + batch.len() + 2 + arg_len > BODY_MAX_LEN;
+            let too_many_args = arg.is_some() && batch.arg_count() >= max_arg_modes;
+
+            if !batch.is_empty() && (would_overflow || too_many_args) {
+                messages.push(batch.build(&self.target)?);
+                batch = Batch::default();
+            }
+
+            batch.push(sign, mode, arg);
+        }
+
+        if !batch.is_empty() {
+            messages.push(batch.build(&self.target)?);
+        }
+
+        Ok(messages)
+    }
+}
+
+fn invalid_prefix(value: &[u8]) -> ErrorKind {
+    ErrorKind::InvalidComponent(
+        "PREFIX value",
+        value.to_owned(),
+        "must be of the form `(modes)symbols`, with as many modes as symbols".into(),
+    )
+}
+
+/// The mode changes that are to be sent in one message.
+#[derive(Default)]
+struct Batch {
+    modes: Vec<u8>,
+    args: Vec<String>,
+    sign: Option<Sign>,
+}
+
+impl Batch {
+    fn push(&mut self, sign: Sign, mode: u8, arg: Option<String>) {
+        if self.sign != Some(sign) {
+            self.modes.push(sign.as_byte());
+            self.sign = Some(sign);
+        }
+
+        self.modes.push(mode);
+        self.args.extend(arg);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.modes.is_empty()
+    }
+
+    fn arg_count(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Returns the length of the mode string and arguments, with the spaces that precede the
+    /// arguments.
+    fn len(&self) -> usize {
+        self.modes.len() + self.args.iter().map(|arg| 1 + arg.len()).sum::<usize>()
+    }
+
+    fn build<Msg>(self, target: &str) -> Result<Msg>
+    where
+        Msg: Message,
+    {
+        // The mode string consists of ASCII letters and signs, as checked by the caller.
+        let modes = str::from_utf8(&self.modes).unwrap();
+        let args = self.args.iter().map(|arg| &arg[..]).collect::<Vec<_>>();
+
+        cmd::mode(target, modes, &args)
+    }
+}
@@ -0,0 +1,218 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use std::borrow::Cow;
+
+fn msg(bytes: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(bytes)).unwrap()
+}
+
+fn change(sign: Sign, mode: u8, arg: Option<&[u8]>) -> ModeChange {
+    ModeChange { sign, mode, arg }
+}
+
+#[test]
+fn parse_1() {
+    let message = msg(b":op!op@host MODE #chan +ov-k+lm alice bob key 10");
+
+    assert_eq!(
+        parse(&message, &ModeSpec::default()).unwrap(),
+        [
+            change(Sign::Plus, b'o', Some(b"alice")),
+            change(Sign::Plus, b'v', Some(b"bob")),
+            change(Sign::Minus, b'k', Some(b"key")),
+            change(Sign::Plus, b'l', Some(b"10")),
+            change(Sign::Plus, b'm', None),
+        ]
+    );
+
+    let message = msg(b":op!op@host MODE #chan -l+b-b *!*@a *!*@b");
+
+    assert_eq!(
+        parse(&message, &ModeSpec::default()).unwrap(),
+        [
+            change(Sign::Minus, b'l', None),
+            change(Sign::Plus, b'b', Some(b"*!*@a")),
+            change(Sign::Minus, b'b', Some(b"*!*@b")),
+        ]
+    );
+}
+
+#[test]
+fn parse_missing_args_1() {
+    let message = msg(b":op!op@host MODE #chan +oo alice");
+
+    assert_eq!(
+        parse(&message, &ModeSpec::default()).unwrap(),
+        [
+            change(Sign::Plus, b'o', Some(b"alice")),
+            change(Sign::Plus, b'o', None),
+        ]
+    );
+}
+
+#[test]
+fn parse_user_modes_1() {
+    let message = msg(b":nick MODE nick :+iw-o");
+
+    assert_eq!(
+        parse(&message, &ModeSpec::default()).unwrap(),
+        [
+            change(Sign::Plus, b'i', None),
+            change(Sign::Plus, b'w', None),
+            change(Sign::Minus, b'o', None),
+        ]
+    );
+
+    let message = msg(b":irc.example.com 221 nick +iw");
+
+    assert_eq!(
+        parse(&message, &ModeSpec::default()).unwrap(),
+        [change(Sign::Plus, b'i', None), change(Sign::Plus, b'w', None)]
+    );
+}
+
+#[test]
+fn parse_channelmodeis_1() {
+    let message = msg(b":irc.example.com 324 nick #chan +ntk key");
+
+    assert_eq!(
+        parse(&message, &ModeSpec::default()).unwrap(),
+        [
+            change(Sign::Plus, b'n', None),
+            change(Sign::Plus, b't', None),
+            change(Sign::Plus, b'k', Some(b"key")),
+        ]
+    );
+}
+
+#[test]
+fn parse_not_mode_1() {
+    for bytes in &[
+        &b":nick!user@host PRIVMSG #chan :+o alice"[..],
+        b":nick!user@host MODE #chan",
+        b":irc.example.com 324 nick #chan",
+    ] {
+        assert_eq!(parse(&msg(bytes), &ModeSpec::default()), None);
+    }
+}
+
+#[test]
+fn spec_1() {
+    let spec = ModeSpec::default()
+        .with_chanmodes(b"beIq,k,flj,CFLMPQScgimnprstz,extra")
+        .unwrap()
+        .with_prefix(b"(qaohv)~&@%+")
+        .unwrap()
+        .with_chantypes(b"#");
+
+    assert_eq!(spec.mode_type(b'q'), ModeType::Prefix);
+    assert_eq!(spec.mode_type(b'I'), ModeType::List);
+    assert_eq!(spec.mode_type(b'k'), ModeType::ParamAlways);
+    assert_eq!(spec.mode_type(b'j'), ModeType::ParamWhenSet);
+    assert_eq!(spec.mode_type(b'z'), ModeType::NoParam);
+    assert_eq!(spec.mode_type(b'x'), ModeType::NoParam);
+    assert_eq!(spec.prefix_symbol(b'h'), Some(b'%'));
+    assert_eq!(spec.prefix_mode(b'~'), Some(b'q'));
+    assert_eq!(spec.prefix_symbol(b'b'), None);
+    assert!(spec.is_channel(b"#chan"));
+    assert!(!spec.is_channel(b"&chan"));
+
+    let spec = ModeSpec::default().with_prefix(b"").unwrap();
+
+    assert_eq!(spec.mode_type(b'o'), ModeType::NoParam);
+
+    assert!(ModeSpec::default().with_chanmodes(b"b,k,l").is_err());
+    assert!(ModeSpec::default().with_prefix(b"(ov)@").is_err());
+    assert!(ModeSpec::default().with_prefix(b"@+").is_err());
+}
+
+#[test]
+fn build_1() {
+    let messages: Vec<OwnedMessage> = ModeBuilder::new("#chan")
+        .plus(b'o', Some("alice"))
+        .plus(b'o', Some("bob"))
+        .minus(b'v', Some("carol"))
+        .plus(b'm', None)
+        .plus(b'v', Some("dave"))
+        .minus(b'l', None)
+        .build(&ModeSpec::default())
+        .unwrap();
+
+    let messages = messages
+        .into_iter()
+        .map(OwnedMessage::into_bytes)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        messages,
+        [
+            &b"MODE #chan +oo-v+m alice bob carol"[..],
+            b"MODE #chan +v-l dave",
+        ]
+    );
+
+    let messages: Vec<OwnedMessage> = ModeBuilder::new("#chan")
+        .plus(b'o', Some("alice"))
+        .plus(b'o', Some("bob"))
+        .build(&ModeSpec::default().with_max_modes(Some(1)))
+        .unwrap();
+
+    assert_eq!(messages.len(), 2);
+
+    let messages: Vec<OwnedMessage> = ModeBuilder::new("nick")
+        .plus(b'i', None)
+        .minus(b'w', None)
+        .build(&ModeSpec::default())
+        .unwrap();
+
+    assert_eq!(messages[0].as_bytes(), b"MODE nick +i-w");
+}
+
+#[test]
+fn build_line_limit_1() {
+    let mask = format!("*!*@{}", "x".repeat(100));
+    let mut builder = ModeBuilder::new("#chan");
+
+    for _ in 0..12 {
+        builder = builder.plus(b'b', Some(&mask));
+    }
+
+    let messages: Vec<OwnedMessage> = builder
+        .build(&ModeSpec::default().with_max_modes(None))
+        .unwrap();
+
+    assert!(messages.len() > 1);
+
+    for message in &messages {
+        assert!(message.as_bytes().len() + 2 <= BODY_MAX_LEN);
+    }
+
+    let count = messages
+        .iter()
+        .flat_map(|message| parse(message, &ModeSpec::default()).unwrap())
+        .count();
+
+    assert_eq!(count, 12);
+}
+
+#[test]
+fn build_invalid_1() {
+    let spec = ModeSpec::default();
+    let results: Vec<Result<Vec<OwnedMessage>>> = vec![
+        ModeBuilder::new("#chan").plus(b'o', None).build(&spec),
+        ModeBuilder::new("#chan").plus(b'm', Some("arg")).build(&spec),
+        ModeBuilder::new("#chan").plus(b'+', None).build(&spec),
+        ModeBuilder::new("nick").plus(b'o', Some("arg")).build(&spec),
+        ModeBuilder::new("#chan").plus(b'b', Some("two words")).build(&spec),
+    ];
+
+    for result in results {
+        assert!(result.is_err(), "{:?}", result);
+    }
+
+    let messages: Vec<OwnedMessage> = ModeBuilder::new("#chan").build(&spec).unwrap();
+
+    assert!(messages.is_empty());
+}
@@ -0,0 +1,148 @@
+use super::Command;
+use super::Message;
+use super::Params;
+use super::Result;
+use super::encoding::Decoding;
+use super::layout::Layout;
+use super::split_prefix;
+use super::tags::Tags;
+use std::borrow::Cow;
+use std::fmt;
+
+/// An IRC message borrowed from a buffer owned elsewhere.
+///
+/// Parsing a `MessageRef` locates the components of the message as byte ranges into the borrowed
+/// buffer; no part of the message is copied. This makes it suitable for inspecting messages that
+/// will mostly be discarded, such as via [`ReceiveMessage::recv_with`]. A message that is to be
+/// kept can be copied out with [`to_message`].
+///
+/// [`ReceiveMessage::recv_with`]: ../connection/trait.ReceiveMessage.html#tymethod.recv_with
+/// [`to_message`]: #method.to_message
+#[derive(Clone)]
+pub struct MessageRef<'a> {
+    bytes: &'a [u8],
+    layout: Layout,
+    decoding: Option<Decoding>,
+}
+
+impl<'a> MessageRef<'a> {
+    /// Parses the given bytes as an IRC message. The bytes should not include the terminating
+    /// CR-LF sequence.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let layout = Layout::parse(bytes)?;
+
+        Ok(MessageRef {
+            bytes,
+            layout,
+            decoding: None,
+        })
+    }
+
+    /// Like `parse`, but records that the bytes were decoded to UTF-8 in the given way.
+    pub(crate) fn parse_decoded(bytes: &'a [u8], decoding: Decoding) -> Result<Self> {
+        let mut msg = Self::parse(bytes)?;
+        msg.decoding = Some(decoding);
+
+        Ok(msg)
+    }
+
+    /// Returns the way in which the message was decoded upon receipt, if it was received.
+    pub fn decoding(&self) -> Option<Decoding> {
+        self.decoding
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn command_bytes(&self) -> &'a [u8] {
+        self.layout.command.of(self.bytes)
+    }
+
+    /// Returns the message's command.
+    pub fn command(&self) -> Command<'a> {
+        Command::from_bytes(self.command_bytes())
+    }
+
+    /// Returns the message's tag section, excluding the leading `@`, if the message has one.
+    pub fn tags_bytes(&self) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        self.layout.tags.map(|span| span.of(bytes))
+    }
+
+    /// Returns an iterator over the message's IRCv3 tags, in the order in which they appear.
+    pub fn tags(&self) -> Tags<'a> {
+        Tags::new(self.tags_bytes().unwrap_or(b""))
+    }
+
+    /// Returns the message's prefix, excluding the leading `:`, if the message has one.
+    pub fn prefix(&self) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        self.layout.prefix.map(|span| span.of(bytes))
+    }
+
+    /// Returns an iterator over the message's parameters, in order. The colon introducing a
+    /// trailing parameter is not included in that parameter.
+    pub fn params(&self) -> Params<'a> {
+        Params::new(self.bytes, self.layout.params.clone())
+    }
+
+    /// Returns the nickname from the message's prefix, if the prefix names a user rather than a
+    /// server.
+    pub fn nick(&self) -> Option<&'a [u8]> {
+        self.prefix().and_then(|prefix| split_prefix(prefix).0)
+    }
+
+    /// Returns the username from the message's prefix, if the prefix includes one.
+    pub fn user(&self) -> Option<&'a [u8]> {
+        self.prefix().and_then(|prefix| split_prefix(prefix).1)
+    }
+
+    /// Returns the hostname from the message's prefix, if the prefix includes one.
+    pub fn host(&self) -> Option<&'a [u8]> {
+        self.prefix().and_then(|prefix| split_prefix(prefix).2)
+    }
+
+    /// Returns the parameter at the given zero-based index, if there is one.
+    pub fn param(&self, index: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        self.layout.params.get(index).map(|span| span.of(bytes))
+    }
+
+    /// Returns the message's final parameter, regardless of whether it was introduced with a
+    /// colon.
+    pub fn trailing(&self) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        self.layout.params.last().map(|span| span.of(bytes))
+    }
+
+    /// Returns the part of the message following the command, with leading spaces removed, as it
+    /// appears in the message.
+    pub(crate) fn raw_params(&self) -> &'a [u8] {
+        let rest = &self.bytes[self.layout.command.end..];
+        let start = rest.iter().position(|&b| b != b' ').unwrap_or(rest.len());
+
+        &rest[start..]
+    }
+
+    /// Copies the message into a new value of a type implementing [`Message`].
+    ///
+    /// [`Message`]: trait.Message.html
+    pub fn to_message<Msg>(&self) -> Result<Msg>
+    where
+        Msg: Message,
+    {
+        match self.decoding {
+            Some(decoding) => Msg::try_from_decoded(Cow::Borrowed(self.bytes), decoding),
+            None => Msg::try_from(Cow::Borrowed(self.bytes)),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for MessageRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple(stringify!(MessageRef))
+            .field(&String::from_utf8_lossy(self.bytes))
+            .finish()
+    }
+}
@@ -0,0 +1,45 @@
+#![cfg(test)]
+
+use super::*;
+use message::Message;
+use message::OwnedMessage;
+use std::borrow::Cow;
+
+#[test]
+fn numeric_names_1() {
+    assert_eq!(RPL_WELCOME.name(), Some("RPL_WELCOME"));
+    assert_eq!(ERR_NICKNAMEINUSE.code(), 433);
+    assert_eq!(Numeric::from_code(999).unwrap().name(), None);
+    assert_eq!(Numeric::from_code(1000), None);
+    assert_eq!(RPL_ISUPPORT.to_string(), "005");
+}
+
+#[test]
+fn numeric_classification_1() {
+    for &numeric in &[ERR_NICKNAMEINUSE, ERR_STARTTLS, ERR_SASLFAIL, ERR_MONLISTFULL] {
+        assert!(numeric.is_error(), "{:?}", numeric.name());
+    }
+
+    for &numeric in &[RPL_WELCOME, RPL_NAMREPLY, RPL_STARTTLS, RPL_SASLSUCCESS] {
+        assert!(numeric.is_reply(), "{:?}", numeric.name());
+    }
+
+    assert!(Numeric::from_code(499).unwrap().is_error());
+    assert!(Numeric::from_code(999).unwrap().is_reply());
+}
+
+#[test]
+fn message_numeric_1() {
+    let msg = OwnedMessage::try_from(Cow::Borrowed(
+        &b":irc.example.net 433 * nick :Nickname is already in use"[..],
+    )).unwrap();
+
+    match msg.numeric() {
+        Some(ERR_NICKNAMEINUSE) => {}
+        other => panic!("Unexpected numeric: {:?}", other),
+    }
+
+    let msg = OwnedMessage::try_from(Cow::Borrowed(&b"PING :x"[..])).unwrap();
+
+    assert_eq!(msg.numeric(), None);
+}
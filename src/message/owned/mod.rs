@@ -0,0 +1,111 @@
+use super::Message;
+use super::Params;
+use super::Result;
+use super::encoding::Decoding;
+use super::layout::Layout;
+use std::borrow::Cow;
+use std::fmt;
+use std::hash;
+
+mod tests;
+
+/// An IRC message that owns its bytes.
+///
+/// This is the crate's own implementation of [`Message`], which does not depend on any optional
+/// features. The message is parsed once, upon construction; thereafter, its components are
+/// located by stored byte indices rather than by re-parsing.
+///
+/// Messages are compared and hashed by their bytes alone, without regard to how they were
+/// decoded.
+///
+/// [`Message`]: trait.Message.html
+#[derive(Clone)]
+pub struct OwnedMessage {
+    bytes: Vec<u8>,
+    layout: Layout,
+    decoding: Option<Decoding>,
+}
+
+impl OwnedMessage {
+    /// Consumes the message, returning its bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl Message for OwnedMessage {
+    fn try_from<'a>(input: Cow<'a, [u8]>) -> Result<Self> {
+        let bytes = input.into_owned();
+        let layout = Layout::parse(&bytes)?;
+
+        Ok(OwnedMessage {
+            bytes,
+            layout,
+            decoding: None,
+        })
+    }
+
+    fn try_from_decoded<'a>(input: Cow<'a, [u8]>, decoding: Decoding) -> Result<Self> {
+        let mut msg = Self::try_from(input)?;
+        msg.decoding = Some(decoding);
+
+        Ok(msg)
+    }
+
+    fn decoding(&self) -> Option<Decoding> {
+        self.decoding
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn command_bytes(&self) -> &[u8] {
+        self.layout.command.of(&self.bytes)
+    }
+
+    fn tags_bytes(&self) -> Option<&[u8]> {
+        self.layout.tags.map(|span| span.of(&self.bytes))
+    }
+
+    fn prefix(&self) -> Option<&[u8]> {
+        self.layout.prefix.map(|span| span.of(&self.bytes))
+    }
+
+    fn params(&self) -> Params {
+        Params::new(&self.bytes, self.layout.params.clone())
+    }
+
+    fn param(&self, index: usize) -> Option<&[u8]> {
+        self.layout.params.get(index).map(|span| span.of(&self.bytes))
+    }
+
+    fn trailing(&self) -> Option<&[u8]> {
+        self.layout.params.last().map(|span| span.of(&self.bytes))
+    }
+}
+
+impl fmt::Debug for OwnedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple(stringify!(OwnedMessage))
+            .field(&String::from_utf8_lossy(&self.bytes))
+            .finish()
+    }
+}
+
+impl PartialEq for OwnedMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for OwnedMessage {}
+
+impl hash::Hash for OwnedMessage {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: hash::Hasher,
+    {
+        self.bytes.hash(state)
+    }
+}
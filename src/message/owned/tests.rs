@@ -0,0 +1,193 @@
+#![cfg(test)]
+
+use super::*;
+use quickcheck::TestResult;
+
+fn parse(line: &str) -> Result<OwnedMessage> {
+    OwnedMessage::try_from(Cow::Borrowed(line.as_bytes()))
+}
+
+/// Makes a string usable as a space-delimited message component by replacing any bytes that
+/// cannot appear in one.
+fn mk_word(s: &str) -> String {
+    s.trim_left_matches(':')
+        .replace(|c| c == ' ' || c == '\0' || c == '\r' || c == '\n', "_")
+}
+
+fn mk_trailing(s: &str) -> String {
+    s.replace(|c| c == '\0' || c == '\r' || c == '\n', "_")
+}
+
+#[test]
+fn parse_full_message_1() {
+    let msg = parse("@aaa=bbb;ccc :nick!user@host PRIVMSG #chan :Hello, world!").unwrap();
+
+    assert_eq!(msg.tags_bytes(), Some(&b"aaa=bbb;ccc"[..]));
+    assert_eq!(msg.prefix(), Some(&b"nick!user@host"[..]));
+    assert_eq!(msg.command_bytes(), b"PRIVMSG");
+    assert_eq!(
+        msg.params().collect::<Vec<_>>(),
+        [&b"#chan"[..], &b"Hello, world!"[..]]
+    );
+}
+
+#[test]
+fn parse_irregular_spacing_1() {
+    let msg = parse(":server  001   nick  :  spaced  out ").unwrap();
+
+    assert_eq!(msg.prefix(), Some(&b"server"[..]));
+    assert_eq!(msg.command_bytes(), b"001");
+    assert_eq!(
+        msg.params().collect::<Vec<_>>(),
+        [&b"nick"[..], &b"  spaced  out "[..]]
+    );
+}
+
+#[test]
+fn parse_no_params_1() {
+    let msg = parse("QUIT   ").unwrap();
+
+    assert_eq!(msg.prefix(), None);
+    assert_eq!(msg.command_bytes(), b"QUIT");
+    assert_eq!(msg.params().count(), 0);
+}
+
+#[test]
+fn parse_empty_trailing_1() {
+    let msg = parse("TOPIC #chan :").unwrap();
+
+    assert_eq!(msg.params().collect::<Vec<_>>(), [&b"#chan"[..], &b""[..]]);
+}
+
+#[test]
+fn prefix_parts_1() {
+    let msg = parse(":nick!~user@host.example PRIVMSG #chan :text").unwrap();
+
+    assert_eq!(msg.nick(), Some(&b"nick"[..]));
+    assert_eq!(msg.user(), Some(&b"~user"[..]));
+    assert_eq!(msg.host(), Some(&b"host.example"[..]));
+    assert_eq!(msg.param(0), Some(&b"#chan"[..]));
+    assert_eq!(msg.param(2), None);
+    assert_eq!(msg.trailing(), Some(&b"text"[..]));
+
+    let msg = parse(":nick@host MODE nick +i").unwrap();
+
+    assert_eq!(msg.nick(), Some(&b"nick"[..]));
+    assert_eq!(msg.user(), None);
+    assert_eq!(msg.host(), Some(&b"host"[..]));
+    assert_eq!(msg.trailing(), Some(&b"+i"[..]));
+
+    let msg = parse(":irc.example.net NOTICE * :*** Looking up your hostname").unwrap();
+
+    assert_eq!(msg.prefix(), Some(&b"irc.example.net"[..]));
+    assert_eq!(msg.nick(), None);
+    assert_eq!(msg.host(), None);
+}
+
+#[test]
+fn reject_malformed_1() {
+    for line in &[
+        "",
+        "   ",
+        ":prefix-only",
+        "@tags-only",
+        "@ PING",
+        ": PING",
+        "PING\r\nPONG",
+        "PRIV\0MSG",
+        "12 x",
+        "P1NG",
+    ] {
+        assert!(parse(line).is_err(), "Parsed malformed message {:?}", line);
+    }
+}
+
+quickcheck! {
+    fn parse_roundtrip_1(
+        prefix: Option<String>,
+        command_is_numeric: bool,
+        command_seed: u16,
+        middles: Vec<String>,
+        trailing: Option<String>
+    ) -> TestResult {
+        let prefix = prefix.map(|s| mk_word(&s));
+        let middles = middles.iter().map(|s| mk_word(s)).collect::<Vec<_>>();
+        let trailing = trailing.map(|s| mk_trailing(&s));
+
+        if prefix.as_ref().map_or(false, String::is_empty) || middles.iter().any(String::is_empty) {
+            return TestResult::discard();
+        }
+
+        let command = if command_is_numeric {
+            format!("{:03}", command_seed % 1000)
+        } else {
+            ["PRIVMSG", "NOTICE", "JOIN", "mode", "Cap"][command_seed as usize % 5].to_owned()
+        };
+
+        let mut line = String::new();
+
+        if let Some(ref prefix) = prefix {
+            line += ":";
+            line += prefix;
+            line += " ";
+        }
+
+        line += &command;
+
+        for middle in &middles {
+            line += " ";
+            line += middle;
+        }
+
+        if let Some(ref trailing) = trailing {
+            line += " :";
+            line += trailing;
+        }
+
+        let msg = match parse(&line) {
+            Ok(msg) => msg,
+            Err(err) => return TestResult::error(format!("{}", err)),
+        };
+
+        let expected_params = middles
+            .iter()
+            .chain(trailing.iter())
+            .map(|s| s.as_bytes())
+            .collect::<Vec<_>>();
+
+        TestResult::from_bool(
+            msg.as_bytes() == line.as_bytes() &&
+                msg.prefix() == prefix.as_ref().map(|s| s.as_bytes()) &&
+                msg.command_bytes() == command.as_bytes() &&
+                msg.params().collect::<Vec<_>>() == expected_params
+        )
+    }
+
+    fn parse_roundtrip_2(tags: Vec<(String, String)>, middle: String) -> TestResult {
+        let tags = tags.iter()
+            .map(|&(ref k, ref v)| {
+                format!("{}={}", mk_word(k), mk_word(v)).replace(';', "_").replace('@', "_")
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        let middle = mk_word(&middle);
+
+        if tags.is_empty() || middle.is_empty() {
+            return TestResult::discard();
+        }
+
+        let line = format!("@{} PING {}", tags, middle);
+
+        let msg = match parse(&line) {
+            Ok(msg) => msg,
+            Err(err) => return TestResult::error(format!("{}", err)),
+        };
+
+        TestResult::from_bool(
+            msg.tags_bytes() == Some(tags.as_bytes()) &&
+                msg.prefix().is_none() &&
+                msg.command_bytes() == b"PING" &&
+                msg.params().collect::<Vec<_>>() == [middle.as_bytes()]
+        )
+    }
+}
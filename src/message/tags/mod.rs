@@ -0,0 +1,241 @@
+//! IRCv3 message tags.
+//!
+//! See the [message tags specification] for the syntax handled here. In brief, a tag section is
+//! a `;`-separated list of tags, each of which has a key and an optional value. A key consists of
+//! an optional `+`, marking a client-only tag; an optional vendor prefix, which is a hostname
+//! followed by a `/`; and a name. Values are escaped so that they contain no semicolons, spaces,
+//! or line breaks.
+//!
+//! [message tags specification]: <http://ircv3.net/specs/core/message-tags-3.2.html>
+
+use super::ErrorKind;
+use super::Result;
+use std::borrow::Cow;
+
+mod tests;
+
+/// A single message tag, borrowed from a message's tag section.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Tag<'a> {
+    key: &'a [u8],
+    raw_value: Option<&'a [u8]>,
+}
+
+/// An iterator over the tags in a message's tag section.
+#[derive(Clone, Debug)]
+pub struct Tags<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Tag<'a> {
+    /// Returns the tag's whole key, including any client-only marker and vendor prefix.
+    pub fn key(&self) -> &'a [u8] {
+        self.key
+    }
+
+    /// Returns whether the tag is a client-only tag, i.e., whether its key starts with `+`.
+    pub fn is_client_only(&self) -> bool {
+        self.key.starts_with(b"+")
+    }
+
+    /// Returns the tag's vendor prefix, excluding the separating `/`, if the tag has one.
+    pub fn vendor(&self) -> Option<&'a [u8]> {
+        let key = self.key_sans_marker();
+
+        key.iter().rposition(|&b| b == b'/').map(|i| &key[..i])
+    }
+
+    /// Returns the tag's name, i.e., its key without any client-only marker or vendor prefix.
+    pub fn name(&self) -> &'a [u8] {
+        let key = self.key_sans_marker();
+
+        match key.iter().rposition(|&b| b == b'/') {
+            Some(i) => &key[i + 1..],
+            None => key,
+        }
+    }
+
+    /// Returns the tag's value as it appears in the message, without unescaping it.
+    pub fn raw_value(&self) -> Option<&'a [u8]> {
+        self.raw_value
+    }
+
+    /// Returns the tag's unescaped value. A tag with no value is equivalent to one with an empty
+    /// value, so this returns an empty value for such a tag.
+    pub fn value(&self) -> Cow<'a, [u8]> {
+        unescape_value(self.raw_value.unwrap_or(b""))
+    }
+
+    fn key_sans_marker(&self) -> &'a [u8] {
+        if self.is_client_only() {
+            &self.key[1..]
+        } else {
+            self.key
+        }
+    }
+}
+
+impl<'a> Tags<'a> {
+    /// Returns an iterator over the tags in the given tag section, which should not include the
+    /// leading `@`.
+    pub fn new(tag_section: &'a [u8]) -> Self {
+        Tags { remaining: tag_section }
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.remaining.is_empty() {
+                return None;
+            }
+
+            let (tag, rest) = match self.remaining.iter().position(|&b| b == b';') {
+                Some(i) => (&self.remaining[..i], &self.remaining[i + 1..]),
+                None => (self.remaining, &b""[..]),
+            };
+
+            self.remaining = rest;
+
+            // Tolerate empty entries, as from a doubled or trailing semicolon.
+            if tag.is_empty() {
+                continue;
+            }
+
+            return Some(match tag.iter().position(|&b| b == b'=') {
+                Some(i) => Tag {
+                    key: &tag[..i],
+                    raw_value: Some(&tag[i + 1..]),
+                },
+                None => Tag {
+                    key: tag,
+                    raw_value: None,
+                },
+            });
+        }
+    }
+}
+
+/// Returns whether the given bytes form a valid tag key.
+pub fn is_valid_key(key: &[u8]) -> bool {
+    let key = if key.starts_with(b"+") {
+        &key[1..]
+    } else {
+        key
+    };
+
+    let (vendor, name) = match key.iter().rposition(|&b| b == b'/') {
+        Some(i) => (Some(&key[..i]), &key[i + 1..]),
+        None => (None, key),
+    };
+
+    let vendor_ok = vendor.map_or(true, |vendor| {
+        !vendor.is_empty() &&
+            vendor
+                .iter()
+                .all(|&b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+    });
+
+    let name_ok = !name.is_empty() && name.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'-');
+
+    vendor_ok && name_ok
+}
+
+/// Reverses the escaping of a tag value.
+///
+/// Per the specification, a backslash followed by a character with no defined escape meaning
+/// stands for that character alone, and a backslash at the end of the value is dropped.
+pub fn unescape_value(raw: &[u8]) -> Cow<[u8]> {
+    if !raw.contains(&b'\\') {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut value = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+
+    while let Some(&b) = bytes.next() {
+        if b != b'\\' {
+            value.push(b);
+            continue;
+        }
+
+        match bytes.next() {
+            Some(&b':') => value.push(b';'),
+            Some(&b's') => value.push(b' '),
+            Some(&b'r') => value.push(b'\r'),
+            Some(&b'n') => value.push(b'\n'),
+            Some(&other) => value.push(other),
+            None => {}
+        }
+    }
+
+    Cow::Owned(value)
+}
+
+/// Escapes a tag value for inclusion in a message.
+pub fn escape_value(value: &[u8]) -> Cow<[u8]> {
+    if !value.iter().any(|&b| needs_escape(b)) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut raw = Vec::with_capacity(value.len() + 8);
+
+    for &b in value {
+        match b {
+            b';' => raw.extend_from_slice(b"\\:"),
+            b' ' => raw.extend_from_slice(b"\\s"),
+            b'\\' => raw.extend_from_slice(b"\\\\"),
+            b'\r' => raw.extend_from_slice(b"\\r"),
+            b'\n' => raw.extend_from_slice(b"\\n"),
+            _ => raw.push(b),
+        }
+    }
+
+    Cow::Owned(raw)
+}
+
+fn needs_escape(b: u8) -> bool {
+    match b {
+        b';' | b' ' | b'\\' | b'\r' | b'\n' => true,
+        _ => false,
+    }
+}
+
+/// Serializes the given keys and unescaped values into a tag section, without the leading `@`.
+///
+/// Returns an error if any key is invalid or any value contains a NUL character.
+pub fn serialize<'t, I>(tags: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = (&'t [u8], Option<&'t [u8]>)>,
+{
+    let mut section = Vec::new();
+
+    for (key, value) in tags {
+        ensure!(
+            is_valid_key(key),
+            ErrorKind::InvalidTag(key.to_owned(), "invalid key".into())
+        );
+
+        if !section.is_empty() {
+            section.push(b';');
+        }
+
+        section.extend_from_slice(key);
+
+        if let Some(value) = value {
+            ensure!(
+                !value.contains(&b'\0'),
+                ErrorKind::InvalidTag(key.to_owned(), "value contains a NUL character".into())
+            );
+
+            if !value.is_empty() {
+                section.push(b'=');
+                section.extend_from_slice(&escape_value(value));
+            }
+        }
+    }
+
+    Ok(section)
+}
@@ -0,0 +1,129 @@
+#![cfg(test)]
+
+use super::*;
+use message::Message;
+use message::OwnedMessage;
+use quickcheck::TestResult;
+
+#[test]
+fn parse_tags_1() {
+    let tags = Tags::new(b"aaa=bbb;ccc;example.com/ddd=eee;+fff=;;+example.com/ggg=h\\si")
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        tags.iter().map(|tag| tag.key()).collect::<Vec<_>>(),
+        [
+            &b"aaa"[..],
+            b"ccc",
+            b"example.com/ddd",
+            b"+fff",
+            b"+example.com/ggg",
+        ]
+    );
+    assert_eq!(
+        tags.iter().map(|tag| tag.name()).collect::<Vec<_>>(),
+        [&b"aaa"[..], b"ccc", b"ddd", b"fff", b"ggg"]
+    );
+    assert_eq!(
+        tags.iter().map(|tag| tag.vendor()).collect::<Vec<_>>(),
+        [None, None, Some(&b"example.com"[..]), None, Some(b"example.com")]
+    );
+    assert_eq!(
+        tags.iter().map(|tag| tag.is_client_only()).collect::<Vec<_>>(),
+        [false, false, false, true, true]
+    );
+    assert_eq!(tags[1].raw_value(), None);
+    assert_eq!(tags[1].value(), &b""[..]);
+    assert_eq!(tags[3].raw_value(), Some(&b""[..]));
+    assert_eq!(tags[4].value(), &b"h i"[..]);
+}
+
+#[test]
+fn unescape_value_1() {
+    for &(raw, value) in &[
+        (&b"plain"[..], &b"plain"[..]),
+        (b"semi\\:colon", b"semi;colon"),
+        (b"a\\sspace", b"a space"),
+        (b"back\\\\slash", b"back\\slash"),
+        (b"cr\\rlf\\n", b"cr\rlf\n"),
+        (b"\\b\\x", b"bx"),
+        (b"dangling\\", b"dangling"),
+        (b"\\\\\\", b"\\"),
+        (b"\\", b""),
+    ] {
+        assert_eq!(unescape_value(raw), value, "raw value: {:?}", raw);
+    }
+}
+
+#[test]
+fn escape_value_1() {
+    assert_eq!(escape_value(b"; \\\r\n"), &b"\\:\\s\\\\\\r\\n"[..]);
+    assert_eq!(escape_value(b"plain"), &b"plain"[..]);
+}
+
+#[test]
+fn serialize_1() {
+    assert_eq!(
+        serialize(vec![
+            (&b"aaa"[..], Some(&b"b c"[..])),
+            (b"+example.com/ddd", None),
+            (b"eee", Some(b"")),
+        ]).unwrap(),
+        &b"aaa=b\\sc;+example.com/ddd;eee"[..]
+    );
+
+    for &key in &[&b""[..], b"+", b"/name", b"vendor/", b"a b", b"a=b", b"a;b"] {
+        assert!(
+            serialize(vec![(key, None)]).is_err(),
+            "Serialized invalid key {:?}",
+            key
+        );
+    }
+}
+
+#[test]
+fn message_tag_access_1() {
+    let msg = OwnedMessage::try_from(Cow::Borrowed(
+        &b"@time=2017-08-01T00:00:00.000Z;msgid=a\\:b;msgid=c :nick PRIVMSG #chan :hi"[..],
+    )).unwrap();
+
+    assert_eq!(msg.tags().count(), 3);
+    assert_eq!(msg.tag(b"msgid").unwrap(), &b"c"[..]);
+    assert_eq!(msg.tag(b"account"), None);
+
+    let map = msg.tag_map();
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map[&b"time"[..]], &b"2017-08-01T00:00:00.000Z"[..]);
+}
+
+quickcheck! {
+    fn escape_roundtrip_1(value: Vec<u8>) -> bool {
+        let raw = escape_value(&value);
+
+        !raw.iter().any(|&b| b == b';' || b == b' ' || b == b'\r' || b == b'\n') &&
+            unescape_value(&raw) == &value[..]
+    }
+
+    fn serialize_roundtrip_1(values: Vec<Option<Vec<u8>>>) -> TestResult {
+        if values.iter().any(|v| v.as_ref().map_or(false, |v| v.contains(&0))) {
+            return TestResult::discard();
+        }
+
+        let keys = (0..values.len()).map(|i| format!("+vendor.example/k{}", i)).collect::<Vec<_>>();
+        let section = serialize(
+            keys.iter()
+                .map(|k| k.as_bytes())
+                .zip(values.iter().map(|v| v.as_ref().map(|v| &v[..]))),
+        ).unwrap();
+        let parsed = Tags::new(&section).collect::<Vec<_>>();
+
+        TestResult::from_bool(
+            parsed.len() == values.len() &&
+                parsed.iter().zip(keys.iter().zip(values.iter())).all(|(tag, (k, v))| {
+                    tag.key() == k.as_bytes() &&
+                        tag.value() == v.as_ref().map_or(&b""[..], |v| &v[..])
+                })
+        )
+    }
+}
@@ -1,23 +1,58 @@
 use Message;
 use message;
+use message::Command;
+use message::MessageRef;
 use std::borrow::Cow;
 
+mod tests;
+
 error_chain! {
     links {
         Message(message::Error, message::ErrorKind);
     }
+
+    errors {
+        NotAPing(message: Vec<u8>) {
+            description("an IRC message that was not a `PING` was treated as a `PING`")
+            display("An IRC message that was not a `PING` was treated as a `PING`: {:?}",
+                    String::from_utf8_lossy(&message))
+        }
+        PingWithoutParams(message: Vec<u8>) {
+            description("a `PING` message had no parameters to echo in a `PONG`")
+            display("A `PING` message had no parameters to echo in a `PONG`: {:?}",
+                    String::from_utf8_lossy(&message))
+        }
+    }
 }
 
-// TODO: Write test cases.
+/// Constructs the `PONG` message with which to answer the given `PING` message.
+///
+/// The parameters of the `PING` are echoed verbatim. Any IRCv3 tags and prefix of the `PING` are
+/// skipped over and not echoed.
 pub fn pong_from_ping<Msg>(msg: Msg) -> Result<Msg>
 where
     Msg: Message,
 {
-    let mut pong_bytes = msg.as_bytes().to_owned();
+    let pong_bytes = {
+        let ping = MessageRef::parse(msg.as_bytes())?;
+
+        ensure!(
+            ping.command() == Command::Ping,
+            ErrorKind::NotAPing(msg.as_bytes().to_owned())
+        );
+
+        let params = ping.raw_params();
+
+        ensure!(
+            !params.is_empty(),
+            ErrorKind::PingWithoutParams(msg.as_bytes().to_owned())
+        );
 
-    // TODO: Skip over prefix and IRCv3 tags, if any, rather than assuming that the message starts
-    // with the command, "PING". (<http://ircv3.net/specs/core/message-tags-3.2.html>)
-    pong_bytes[1] = b'O';
+        let mut pong_bytes = Vec::with_capacity(b"PONG ".len() + params.len());
+        pong_bytes.extend_from_slice(b"PONG ");
+        pong_bytes.extend_from_slice(params);
+        pong_bytes
+    };
 
     Ok(Msg::try_from(Cow::Owned(pong_bytes))?)
 }
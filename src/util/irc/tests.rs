@@ -0,0 +1,118 @@
+#![cfg(test)]
+
+use super::*;
+use message::OwnedMessage;
+use quickcheck::TestResult;
+
+fn msg(line: &[u8]) -> OwnedMessage {
+    OwnedMessage::try_from(Cow::Borrowed(line)).unwrap()
+}
+
+#[test]
+fn pong_from_ping_1() {
+    for &(ping, pong) in &[
+        (&b"PING :irc.example.net"[..], &b"PONG :irc.example.net"[..]),
+        (b"PING irc.example.net", b"PONG irc.example.net"),
+        (b"ping :token", b"PONG :token"),
+        (b":irc.example.net PING :token", b"PONG :token"),
+        (b"@time=2017-08-01T00:00:00Z :irc.example.net PING :token", b"PONG :token"),
+        (b"@a=b PING   server1   :server two ", b"PONG server1   :server two "),
+        (b"PING :", b"PONG :"),
+    ] {
+        assert_eq!(
+            pong_from_ping(msg(ping)).unwrap().as_bytes(),
+            pong,
+            "PING: {:?}",
+            String::from_utf8_lossy(ping)
+        );
+    }
+}
+
+#[test]
+fn pong_from_non_ping_1() {
+    for &line in &[&b"PONG :token"[..], b":nick PRIVMSG PING :PING", b"PINGS x"] {
+        match pong_from_ping(msg(line)) {
+            Err(Error(ErrorKind::NotAPing(_), _)) => {}
+            other => panic!("Unexpected result for {:?}: {:?}", line, other),
+        }
+    }
+}
+
+#[test]
+fn pong_from_ping_without_params_1() {
+    for &line in &[&b"PING"[..], b":irc.example.net PING   "] {
+        match pong_from_ping(msg(line)) {
+            Err(Error(ErrorKind::PingWithoutParams(_), _)) => {}
+            other => panic!("Unexpected result for {:?}: {:?}", line, other),
+        }
+    }
+}
+
+/// Replaces characters that cannot appear in an IRC message.
+fn sanitize(s: &str) -> String {
+    s.replace(|c| c == '\0' || c == '\r' || c == '\n', "_")
+}
+
+quickcheck! {
+    fn pong_echoes_ping_params_1(
+        tags: bool,
+        prefix: bool,
+        middles: Vec<String>,
+        trailing: Option<String>
+    ) -> TestResult {
+        let mut line = String::new();
+
+        if tags {
+            line += "@example.com/tag=value ";
+        }
+
+        if prefix {
+            line += ":irc.example.net ";
+        }
+
+        line += "PING";
+
+        for middle in &middles {
+            line += " ";
+            line += &sanitize(middle.trim_left_matches(':'));
+        }
+
+        if let Some(ref trailing) = trailing {
+            line += " :";
+            line += &sanitize(trailing);
+        }
+
+        let ping = match OwnedMessage::try_from(Cow::Borrowed(line.as_bytes())) {
+            Ok(ping) => ping,
+            Err(_) => return TestResult::discard(),
+        };
+
+        if ping.params().count() == 0 {
+            return TestResult::discard();
+        }
+
+        let pong = match pong_from_ping(ping.clone()) {
+            Ok(pong) => pong,
+            Err(err) => return TestResult::error(format!("{}", err)),
+        };
+
+        TestResult::from_bool(
+            pong.command_bytes() == b"PONG" && pong.prefix().is_none() &&
+                pong.tags_bytes().is_none() &&
+                pong.params().collect::<Vec<_>>() == ping.params().collect::<Vec<_>>()
+        )
+    }
+
+    fn pong_from_arbitrary_ping_never_panics_1(params: Vec<u8>) -> TestResult {
+        let mut line = b"PING ".to_vec();
+        line.extend(params.into_iter().filter(|&b| b != b'\0' && b != b'\r' && b != b'\n'));
+
+        match OwnedMessage::try_from(Cow::Owned(line)) {
+            Ok(msg) => {
+                let _ = pong_from_ping(msg);
+                TestResult::passed()
+            }
+            Err(_) => TestResult::discard(),
+        }
+    }
+}